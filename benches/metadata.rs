@@ -22,7 +22,8 @@ fn create_mock_function(name: &str) -> Function {
         deprecated: Some(false),
         local_path: None,
         line: None,
-        extra: todo!(),
+        origin: Default::default(),
+        extra: Default::default(),
     }
 }
 
@@ -44,6 +45,10 @@ fn bench_metadata_lookup(c: &mut Criterion) {
         b.iter(|| manager.get_exact(black_box("$FUNCTION_250")))
     });
 
+    c.bench_function("metadata_with_function_hit", |b| {
+        b.iter(|| manager.with_function(black_box("$FUNCTION_250"), |f| f.unwrap))
+    });
+
     c.bench_function("metadata_get_exact_miss", |b| {
         b.iter(|| manager.get_exact(black_box("$nonExistentFunction")))
     });
@@ -74,6 +79,23 @@ fn bench_metadata_lookup(c: &mut Criterion) {
             });
         })
     });
+
+    // Same workload against a lock-free snapshot
+    let snapshot = manager.snapshot();
+    c.bench_function("metadata_concurrent_reads_snapshot", |b| {
+        b.iter(|| {
+            std::thread::scope(|s| {
+                for _ in 0..4 {
+                    let snapshot = snapshot.clone();
+                    s.spawn(move || {
+                        for i in 0..50 {
+                            snapshot.get(&format!("$function_{}", i));
+                        }
+                    });
+                }
+            });
+        })
+    });
 }
 
 fn bench_trie_internals(c: &mut Criterion) {
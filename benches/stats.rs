@@ -0,0 +1,58 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use forge_kit::parser::parse;
+use forge_kit::utils::{calculate_stats, stats_of};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Tracks live and peak heap usage so the two stats paths can be compared
+struct PeakAlloc;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAlloc = PeakAlloc;
+
+fn peak_bytes<T>(f: impl FnOnce() -> T) -> usize {
+    let base = LIVE.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    black_box(f());
+    PEAK.load(Ordering::Relaxed) - base
+}
+
+fn bench_stats(c: &mut Criterion) {
+    let large_script = include_str!("data/large_script.js");
+
+    let two_step = peak_bytes(|| calculate_stats(&parse(large_script).0));
+    let streaming = peak_bytes(|| stats_of(large_script));
+    println!(
+        "peak heap on large script: parse + calculate_stats = {two_step} B, stats_of = {streaming} B"
+    );
+
+    c.bench_function("stats_large_script_two_step", |b| {
+        b.iter(|| calculate_stats(&parse(black_box(large_script)).0))
+    });
+
+    c.bench_function("stats_large_script_streaming", |b| {
+        b.iter(|| stats_of(black_box(large_script)))
+    });
+}
+
+criterion_group!(benches, bench_stats);
+criterion_main!(benches);
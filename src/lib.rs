@@ -1,3 +1,4 @@
+pub mod lint;
 pub mod metadata;
 pub mod parser;
 pub mod types;
@@ -0,0 +1,228 @@
+//! Opt-in style lints for the ForgeScript AST
+//!
+//! Lints run over an already-parsed tree and only ever produce
+//! [`Severity::Warning`](crate::parser::Severity::Warning) diagnostics: they
+//! flag code that is valid but likely to be unintended or hard to read.
+//!
+//! [`lint_report`] bundles parsing, validation and line resolution into a
+//! single serializable report for batch linting in CI.
+
+use crate::metadata::MetadataManager;
+use crate::parser::{AstNode, ErrorKind, ParseError, Span};
+#[cfg(feature = "validation")]
+use crate::parser::{Severity, ValidationConfig};
+use crate::visitor::fold;
+#[cfg(feature = "validation")]
+use serde::Serialize;
+#[cfg(feature = "validation")]
+use std::sync::Arc;
+
+/// Functions that end execution when no terminators are configured
+pub const DEFAULT_TERMINATORS: &[&str] = &["return", "stop"];
+
+/// Configuration selecting which lints to run
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    /// Flag lines whose leading whitespace mixes tabs and spaces
+    pub lint_indentation: bool,
+    /// Flag calls to functions that metadata marks as deprecated
+    pub lint_deprecated: bool,
+    /// Flag code following a call that ends execution in the same code block
+    pub lint_unreachable: bool,
+    /// Names (without `$`) of functions that end execution, compared
+    /// case-insensitively; `None` uses [`DEFAULT_TERMINATORS`]
+    pub terminators: Option<Vec<String>>,
+}
+
+/// Run every enabled lint over `ast`, returning warning diagnostics
+///
+/// Lints that depend on function metadata are skipped when `metadata` is
+/// `None`.
+pub fn lint(
+    ast: &AstNode,
+    config: &LintConfig,
+    metadata: Option<&MetadataManager>,
+) -> Vec<ParseError> {
+    let mut warnings = Vec::new();
+
+    if config.lint_indentation {
+        lint_indentation(ast, &mut warnings);
+    }
+
+    if config.lint_deprecated
+        && let Some(metadata) = metadata
+    {
+        lint_deprecated(ast, metadata, &mut warnings);
+    }
+
+    if config.lint_unreachable {
+        lint_unreachable(ast, config.terminators.as_deref(), &mut warnings);
+    }
+
+    warnings
+}
+
+/// A diagnostic in a [`LintReport`], with its 0-based line and column (in chars)
+#[cfg(feature = "validation")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintDiagnostic {
+    pub message: String,
+    pub kind: ErrorKind,
+    pub severity: Severity,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Machine-readable result of [`lint_report`]
+#[cfg(feature = "validation")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintReport {
+    /// `true` when no diagnostic has [`Severity::Error`]
+    pub valid: bool,
+    pub errors: Vec<LintDiagnostic>,
+}
+
+/// Parse and validate `source`, resolving each diagnostic to its line and
+/// column (requires "validation" feature).
+///
+/// Without `metadata` the source is checked as by
+/// [`parse_with_config`](crate::parser::parse_with_config).
+#[cfg(feature = "validation")]
+pub fn lint_report(
+    source: &str,
+    config: ValidationConfig,
+    metadata: Option<Arc<MetadataManager>>,
+) -> LintReport {
+    let (_, errors) = match metadata {
+        Some(metadata) => crate::parser::parse_with_validation(source, config, metadata),
+        None => crate::parser::parse_with_config(source, config),
+    };
+
+    let index = crate::utils::LineIndex::new(source);
+    let errors: Vec<LintDiagnostic> = errors
+        .into_iter()
+        .map(|error| {
+            let (line, col) = index.line_col(error.span.start);
+            LintDiagnostic {
+                message: error.message,
+                kind: error.kind,
+                severity: error.severity,
+                start: error.span.start,
+                end: error.span.end,
+                line,
+                col,
+            }
+        })
+        .collect();
+
+    LintReport {
+        valid: errors.iter().all(|e| e.severity == Severity::Warning),
+        errors,
+    }
+}
+
+/// Scan the top-level text nodes for lines indented with both tabs and spaces.
+///
+/// Only lines that begin after a newline inside a text node are considered,
+/// since the first line of a node may continue a line started by a call.
+fn lint_indentation(ast: &AstNode, warnings: &mut Vec<ParseError>) {
+    let AstNode::Program { body, .. } = ast else {
+        return;
+    };
+
+    for node in body {
+        let AstNode::Text { content, span } = node else {
+            continue;
+        };
+
+        let mut offset = 0;
+        for (i, line) in content.split('\n').enumerate() {
+            let start = offset;
+            offset += line.len() + 1;
+            if i == 0 {
+                continue;
+            }
+
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+            if indent.contains(' ') && indent.contains('\t') {
+                warnings.push(ParseError::warning(
+                    "Line is indented with a mix of tabs and spaces",
+                    Span::new(span.start + start, span.start + start + line.len()),
+                    ErrorKind::MixedIndentation,
+                ));
+            }
+        }
+    }
+}
+
+/// Flag every call whose function is marked `deprecated` in metadata.
+fn lint_deprecated(ast: &AstNode, metadata: &MetadataManager, warnings: &mut Vec<ParseError>) {
+    fold(ast, (), |(), node| {
+        let (AstNode::FunctionCall { name_span, .. }, Some(name)) =
+            (node, node.function_full_name())
+        else {
+            return;
+        };
+
+        let deprecated = metadata.with_function(&name, |func| {
+            (func.deprecated == Some(true)).then(|| func.name.clone())
+        });
+        if let Some(Some(func_name)) = deprecated {
+            warnings.push(ParseError::warning(
+                format!("Function {func_name} is deprecated"),
+                *name_span,
+                ErrorKind::Deprecated,
+            ));
+        }
+    });
+}
+
+/// Flag the nodes following a terminating call in the same code block.
+///
+/// Only the program body is considered, never nested arguments. Blocks are
+/// flattened into one body, so a gap between consecutive spans marks the end
+/// of a block. Trailing whitespace is not reported.
+fn lint_unreachable(ast: &AstNode, terminators: Option<&[String]>, warnings: &mut Vec<ParseError>) {
+    let AstNode::Program { body, .. } = ast else {
+        return;
+    };
+    let is_terminator = |name: &str| match terminators {
+        Some(names) => names.iter().any(|t| t.eq_ignore_ascii_case(name)),
+        None => DEFAULT_TERMINATORS
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(name)),
+    };
+
+    let mut i = 0;
+    while i < body.len() {
+        let AstNode::FunctionCall { name, .. } = &body[i] else {
+            i += 1;
+            continue;
+        };
+        if !is_terminator(name) {
+            i += 1;
+            continue;
+        }
+
+        let mut end = i + 1;
+        while end < body.len() && body[end].span().start == body[end - 1].span().end {
+            end += 1;
+        }
+
+        let mut dead = body[i + 1..end].iter().filter(
+            |node| !matches!(node, AstNode::Text { content, .. } if content.trim().is_empty()),
+        );
+        if let Some(first) = dead.next() {
+            let last = dead.next_back().unwrap_or(first);
+            warnings.push(ParseError::warning(
+                format!("Unreachable code after ${}", name),
+                Span::new(first.span().start, last.span().end),
+                ErrorKind::Unreachable,
+            ));
+        }
+        i = end;
+    }
+}
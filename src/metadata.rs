@@ -7,11 +7,12 @@
 //! - Robust error handling with no panics
 //! - Concurrent access with DashMap
 
-use crate::types::{Event, Function};
+use crate::types::{Arg, CompletionItem, Event, Function, FunctionOrigin};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 // ============================================================================
 // Core Types
@@ -28,6 +29,8 @@ pub struct EventField {
 #[derive(Debug, Clone)]
 pub struct MetadataSource {
     pub extension: String,
+    /// Base URL that relative functions/enums/events URLs are resolved against
+    pub base_url: Option<String>,
     pub functions_url: Option<String>,
     pub enums_url: Option<String>,
     pub events_url: Option<String>,
@@ -38,12 +41,50 @@ impl MetadataSource {
     pub fn new(extension: impl Into<String>) -> Self {
         Self {
             extension: extension.into(),
+            base_url: None,
             functions_url: None,
             enums_url: None,
             events_url: None,
         }
     }
 
+    /// Set the base URL used to resolve relative URLs
+    pub fn with_base(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Resolve `url` against the base URL, if it is relative and a base is set
+    pub fn resolve_url(&self, url: &str) -> String {
+        match &self.base_url {
+            Some(base) if !url.contains("://") => {
+                format!(
+                    "{}/{}",
+                    base.trim_end_matches('/'),
+                    url.trim_start_matches('/')
+                )
+            }
+            _ => url.to_string(),
+        }
+    }
+
+    /// Functions URL resolved against the base URL
+    pub fn resolved_functions_url(&self) -> Option<String> {
+        self.functions_url
+            .as_deref()
+            .map(|url| self.resolve_url(url))
+    }
+
+    /// Enums URL resolved against the base URL
+    pub fn resolved_enums_url(&self) -> Option<String> {
+        self.enums_url.as_deref().map(|url| self.resolve_url(url))
+    }
+
+    /// Events URL resolved against the base URL
+    pub fn resolved_events_url(&self) -> Option<String> {
+        self.events_url.as_deref().map(|url| self.resolve_url(url))
+    }
+
     /// Set functions URL
     pub fn with_functions(mut self, url: impl Into<String>) -> Self {
         self.functions_url = Some(url.into());
@@ -96,14 +137,40 @@ pub type Result<T> = std::result::Result<T, MetadataError>;
 // Fast Trie for Function Lookup
 // ============================================================================
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 struct TrieNode {
     children: HashMap<char, Box<TrieNode>>,
     value: Option<Arc<Function>>,
+    /// Key as originally inserted, before lowercasing
+    key: Option<String>,
+}
+
+/// Lookup result that reports how the query's casing compares to the key
+/// the function was registered under.
+#[derive(Debug, Clone)]
+pub struct CaseMatch {
+    /// The key with its registered (canonical) casing
+    pub canonical: String,
+    pub function: Arc<Function>,
+    /// `true` when the query used exactly the canonical casing
+    pub exact_case: bool,
+}
+
+/// Size and shape of a [`FunctionTrie`], from [`FunctionTrie::stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TrieStats {
+    /// Number of nodes, including the root
+    pub nodes: usize,
+    /// Length in characters of the longest key
+    pub max_depth: usize,
+    /// Mean key length in characters, `0.0` when empty
+    pub avg_depth: f64,
+    /// Number of registered keys (functions and aliases)
+    pub entries: usize,
 }
 
 /// High-performance prefix trie for function lookup
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct FunctionTrie {
     root: TrieNode,
     count: usize,
@@ -131,20 +198,70 @@ impl FunctionTrie {
             self.count += 1;
         }
         node.value = Some(func);
+        node.key = Some(key.to_string());
+    }
+
+    /// Insert every entry of `other`. On key collisions the entry from `other`
+    /// replaces the existing one when `overwrite` is set, and is skipped otherwise.
+    pub fn merge(&mut self, other: &FunctionTrie, overwrite: bool) {
+        Self::merge_node(&mut self.root, &other.root, overwrite, &mut self.count);
+    }
+
+    fn merge_node(node: &mut TrieNode, other: &TrieNode, overwrite: bool, count: &mut usize) {
+        if let Some(func) = &other.value
+            && (node.value.is_none() || overwrite)
+        {
+            if node.value.is_none() {
+                *count += 1;
+            }
+            node.value = Some(func.clone());
+            node.key = other.key.clone();
+        }
+
+        for (ch, other_child) in &other.children {
+            let child = node.children.entry(*ch).or_default();
+            Self::merge_node(child, other_child, overwrite, count);
+        }
     }
 
     /// Get exact match (case-insensitive)
     pub fn get_exact(&self, key: &str) -> Option<Arc<Function>> {
-        let mut node = &self.root;
+        self.get_exact_ref(key).cloned()
+    }
 
-        for ch in key.to_lowercase().chars() {
-            match node.children.get(&ch) {
-                Some(next) => node = next,
-                None => return None,
+    /// Get exact match (case-insensitive) without cloning the `Arc`.
+    pub fn get_exact_ref(&self, key: &str) -> Option<&Arc<Function>> {
+        self.find_node(key)?.value.as_ref()
+    }
+
+    /// Get exact match (case-insensitive), also reporting the canonical casing
+    /// of the registered key and whether `key` matched it exactly.
+    pub fn get_case_match(&self, key: &str) -> Option<CaseMatch> {
+        let node = self.find_node(key)?;
+        let function = node.value.clone()?;
+        let canonical = node.key.clone().unwrap_or_else(|| key.to_lowercase());
+
+        Some(CaseMatch {
+            exact_case: canonical == key,
+            canonical,
+            function,
+        })
+    }
+
+    fn find_node(&self, key: &str) -> Option<&TrieNode> {
+        let mut node = &self.root;
+        // Function names are almost always ASCII; lowercase them on the fly
+        // rather than allocating a lowered copy of the key.
+        if key.is_ascii() {
+            for b in key.bytes() {
+                node = node.children.get(&(b.to_ascii_lowercase() as char))?;
+            }
+        } else {
+            for ch in key.to_lowercase().chars() {
+                node = node.children.get(&ch)?;
             }
         }
-
-        node.value.clone()
+        Some(node)
     }
 
     /// Get the longest registered function name that is a prefix of `text`,
@@ -178,6 +295,38 @@ impl FunctionTrie {
         last_match
     }
 
+    /// Like [`get_prefix`](Self::get_prefix), also returning the part of
+    /// `text` after the matched name.
+    ///
+    /// The name is the lowercased trie key, while the remainder is sliced from
+    /// the original `text`, so `"$PINGmsoko"` yields `("$ping", …, "msoko")`.
+    pub fn get_prefix_with_remainder<'t>(
+        &self,
+        text: &'t str,
+    ) -> Option<(String, Arc<Function>, &'t str)> {
+        let mut node = &self.root;
+        let mut last_match: Option<(String, Arc<Function>, usize)> = None;
+        let mut matched = String::with_capacity(text.len());
+
+        'chars: for (idx, ch) in text.char_indices() {
+            // Lowercasing may expand one char into several trie edges
+            for lower in ch.to_lowercase() {
+                match node.children.get(&lower) {
+                    Some(next) => {
+                        matched.push(lower);
+                        node = next;
+                    }
+                    None => break 'chars,
+                }
+            }
+            if let Some(func) = &node.value {
+                last_match = Some((matched.clone(), func.clone(), idx + ch.len_utf8()));
+            }
+        }
+
+        last_match.map(|(name, func, end)| (name, func, &text[end..]))
+    }
+
     /// Get all functions with a given prefix
     pub fn get_completions(&self, prefix: &str) -> Vec<Arc<Function>> {
         let mut node = &self.root;
@@ -195,15 +344,24 @@ impl FunctionTrie {
     }
 
     fn collect_all(&self, node: &TrieNode, results: &mut Vec<Arc<Function>>) {
+        Self::visit_all(node, &mut |func| results.push(func.clone()));
+    }
+
+    fn visit_all(node: &TrieNode, f: &mut impl FnMut(&Arc<Function>)) {
         if let Some(func) = &node.value {
-            results.push(func.clone());
+            f(func);
         }
 
         for child in node.children.values() {
-            self.collect_all(child, results);
+            Self::visit_all(child, f);
         }
     }
 
+    /// Call `f` for every function in the trie without collecting them
+    pub fn for_each(&self, mut f: impl FnMut(&Function)) {
+        Self::visit_all(&self.root, &mut |func| f(func));
+    }
+
     /// Get all functions in the trie
     pub fn all_functions(&self) -> Vec<Arc<Function>> {
         let mut results = Vec::with_capacity(self.count);
@@ -211,6 +369,32 @@ impl FunctionTrie {
         results
     }
 
+    /// Get all functions paired with the (lowercased) key they were registered
+    /// under, so alias entries can be told apart from canonical ones.
+    pub fn get_all_with_keys(&self) -> Vec<(String, Arc<Function>)> {
+        let mut results = Vec::with_capacity(self.count);
+        let mut key = String::new();
+        self.collect_all_with_keys(&self.root, &mut key, &mut results);
+        results
+    }
+
+    fn collect_all_with_keys(
+        &self,
+        node: &TrieNode,
+        key: &mut String,
+        results: &mut Vec<(String, Arc<Function>)>,
+    ) {
+        if let Some(func) = &node.value {
+            results.push((key.clone(), func.clone()));
+        }
+
+        for (ch, child) in &node.children {
+            key.push(*ch);
+            self.collect_all_with_keys(child, key, results);
+            key.pop();
+        }
+    }
+
     /// Number of functions in trie
     #[inline]
     pub fn len(&self) -> usize {
@@ -223,24 +407,50 @@ impl FunctionTrie {
         self.count == 0
     }
 
-    /// Remove an exact match from the trie
-    pub fn remove(&mut self, key: &str) -> bool {
-        let mut node = &mut self.root;
-
-        for ch in key.to_lowercase().chars() {
-            match node.children.get_mut(&ch) {
-                Some(next) => node = next,
-                None => return false,
+    /// Count nodes and measure key depths in a single traversal
+    pub fn stats(&self) -> TrieStats {
+        let mut stats = TrieStats::default();
+        let mut total_depth = 0;
+        let mut stack = vec![(&self.root, 0)];
+
+        while let Some((node, depth)) = stack.pop() {
+            stats.nodes += 1;
+            if node.value.is_some() {
+                stats.entries += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+                total_depth += depth;
             }
+            stack.extend(node.children.values().map(|child| (&**child, depth + 1)));
         }
 
-        if node.value.is_some() {
-            node.value = None;
-            self.count -= 1;
-            true
-        } else {
-            false
+        if stats.entries > 0 {
+            stats.avg_depth = total_depth as f64 / stats.entries as f64;
+        }
+        stats
+    }
+
+    /// Remove an exact match from the trie, pruning any nodes left empty.
+    ///
+    /// Returns the removed function, or `None` if `key` was not registered.
+    pub fn remove(&mut self, key: &str) -> Option<Arc<Function>> {
+        let chars: Vec<char> = key.to_lowercase().chars().collect();
+        let removed = Self::remove_at(&mut self.root, &chars)?;
+        self.count -= 1;
+        Some(removed)
+    }
+
+    fn remove_at(node: &mut TrieNode, key: &[char]) -> Option<Arc<Function>> {
+        let Some((ch, rest)) = key.split_first() else {
+            node.key = None;
+            return node.value.take();
+        };
+
+        let child = node.children.get_mut(ch)?;
+        let removed = Self::remove_at(child, rest)?;
+        if child.value.is_none() && child.children.is_empty() {
+            node.children.remove(ch);
         }
+        Some(removed)
     }
 
     /// Clear all functions
@@ -254,13 +464,36 @@ impl FunctionTrie {
 // HTTP Fetcher
 // ============================================================================
 
-/// HTTP fetcher for metadata
-pub struct Fetcher {
+/// Future returned by [`MetadataTransport::get`]
+#[cfg(not(target_arch = "wasm32"))]
+pub type TransportFuture<'a> =
+    std::pin::Pin<Box<dyn std::future::Future<Output = Result<(u16, String)>> + Send + 'a>>;
+
+/// Future returned by [`MetadataTransport::get`]
+#[cfg(target_arch = "wasm32")]
+pub type TransportFuture<'a> =
+    std::pin::Pin<Box<dyn std::future::Future<Output = Result<(u16, String)>> + 'a>>;
+
+/// HTTP client used to download metadata.
+///
+/// Implement this to route requests through a custom client (e.g. one using
+/// mTLS) or to serve metadata from memory in tests.
+pub trait MetadataTransport: Send + Sync {
+    /// Perform a GET request with the given `(name, value)` headers,
+    /// returning the HTTP status code and the body.
+    ///
+    /// Only failures to complete the request are errors; non-success statuses
+    /// are returned normally and interpreted by the [`Fetcher`].
+    fn get<'a>(&'a self, url: &'a str, headers: &'a [(&'a str, &'a str)]) -> TransportFuture<'a>;
+}
+
+/// Default [`MetadataTransport`] backed by `reqwest`
+pub struct ReqwestTransport {
     client: reqwest::Client,
 }
 
-impl Fetcher {
-    /// Create a new fetcher
+impl ReqwestTransport {
+    /// Create a transport with the default client configuration
     pub fn new() -> Self {
         #[cfg(not(target_arch = "wasm32"))]
         let client = reqwest::Client::builder()
@@ -276,27 +509,102 @@ impl Fetcher {
         Self { client }
     }
 
-    /// Fetch JSON from a URL with proper error handling
-    pub async fn fetch_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
-        let response =
-            self.client.get(url).send().await.map_err(|e| {
+    /// Create a transport using an already configured client
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetadataTransport for ReqwestTransport {
+    fn get<'a>(&'a self, url: &'a str, headers: &'a [(&'a str, &'a str)]) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let mut request = self.client.get(url);
+            for &(name, value) in headers {
+                request = request.header(name, value);
+            }
+            let response = request.send().await.map_err(|e| {
                 MetadataError::NetworkError(format!("Failed to fetch {}: {}", url, e))
             })?;
+            let status = response.status().as_u16();
+            let body = response.text().await.map_err(|e| {
+                MetadataError::NetworkError(format!("Failed to read response from {}: {}", url, e))
+            })?;
+            Ok((status, body))
+        })
+    }
+}
+
+/// HTTP fetcher for metadata
+pub struct Fetcher {
+    transport: Box<dyn MetadataTransport>,
+    /// Headers sent with every request
+    headers: Vec<(String, String)>,
+}
+
+impl Fetcher {
+    /// Create a new fetcher using [`ReqwestTransport`]
+    pub fn new() -> Self {
+        Self::with_transport(ReqwestTransport::new())
+    }
 
-        let status = response.status();
-        if status == reqwest::StatusCode::NOT_FOUND {
+    /// Create a fetcher that sends requests through `transport`
+    pub fn with_transport(transport: impl MetadataTransport + 'static) -> Self {
+        // Browsers set their own User-Agent and refuse to override it
+        #[cfg(not(target_arch = "wasm32"))]
+        let headers = vec![(
+            "User-Agent".to_string(),
+            concat!("forge-kit/", env!("CARGO_PKG_VERSION")).to_string(),
+        )];
+        #[cfg(target_arch = "wasm32")]
+        let headers = Vec::new();
+
+        Self {
+            transport: Box::new(transport),
+            headers,
+        }
+    }
+
+    /// Send `name: value` with every request, e.g. an `Authorization` header.
+    /// Replaces any header of the same name, including the default User-Agent.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Fetch the body of a URL, mapping 404 to [`MetadataError::NotFound`]
+    /// and other non-success statuses to [`MetadataError::NetworkError`]
+    async fn fetch_text(&self, url: &str) -> Result<String> {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let (status, body) = self.transport.get(url, &headers).await?;
+
+        if status == 404 {
             return Err(MetadataError::NotFound(format!("URL not found: {}", url)));
         }
-        if !status.is_success() {
+        if !(200..300).contains(&status) {
             return Err(MetadataError::NetworkError(format!(
                 "HTTP {}: {}",
                 status, url
             )));
         }
 
-        let text = response.text().await.map_err(|e| {
-            MetadataError::NetworkError(format!("Failed to read response from {}: {}", url, e))
-        })?;
+        Ok(body)
+    }
+
+    /// Fetch JSON from a URL with proper error handling
+    pub async fn fetch_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let text = self.fetch_text(url).await?;
 
         serde_json::from_str(&text).map_err(|e| {
             let preview: String = text.chars().take(200).collect();
@@ -310,48 +618,31 @@ impl Fetcher {
     /// Fetch functions from URL, parsing each item individually so one bad entry
     /// doesn't block the rest.
     pub async fn fetch_functions(&self, url: &str, extension: String) -> Result<Vec<Function>> {
-        let response =
-            self.client.get(url).send().await.map_err(|e| {
-                MetadataError::NetworkError(format!("Failed to fetch {}: {}", url, e))
-            })?;
-
-        let status = response.status();
-        if status == reqwest::StatusCode::NOT_FOUND {
-            return Err(MetadataError::NotFound(format!("URL not found: {}", url)));
-        }
-        if !status.is_success() {
-            return Err(MetadataError::NetworkError(format!(
-                "HTTP {}: {}",
-                status, url
-            )));
-        }
-
-        let text = response.text().await.map_err(|e| {
-            MetadataError::NetworkError(format!("Failed to read response from {}: {}", url, e))
-        })?;
+        let text = self.fetch_text(url).await?;
+        Self::parse_functions(&text, url, &extension)
+    }
 
-        let raw_items: Vec<serde_json::Value> = serde_json::from_str(&text).map_err(|e| {
-            let preview: String = text.chars().take(200).collect();
+    /// Parse a `functions.json` array fetched from `url`, tagging every
+    /// function with `extension` and `url`.
+    ///
+    /// Entries are deserialized one at a time, so only a single entry is held
+    /// as a generic JSON value at once. Entries that are not valid functions
+    /// are logged and skipped.
+    pub fn parse_functions(json: &str, url: &str, extension: &str) -> Result<Vec<Function>> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let functions = serde::Deserializer::deserialize_seq(
+            &mut deserializer,
+            FunctionsVisitor { url, extension },
+        )
+        .and_then(|functions| deserializer.end().map(|()| functions))
+        .map_err(|e| {
+            let preview: String = json.chars().take(200).collect();
             MetadataError::ParseError(format!(
                 "Failed to parse JSON array from {}: {}\nJSON preview: {}…",
                 url, e, preview
             ))
         })?;
 
-        let mut functions = Vec::with_capacity(raw_items.len());
-        for (i, raw) in raw_items.into_iter().enumerate() {
-            match serde_json::from_value::<Function>(raw) {
-                Ok(mut func) => {
-                    func.extension = Some(extension.clone());
-                    func.source_url = Some(url.to_string());
-                    functions.push(func);
-                }
-                Err(e) => {
-                    eprintln!("[forge-kit] Skipping function #{} from {}: {}", i, url, e);
-                }
-            }
-        }
-
         Ok(functions)
     }
 
@@ -366,6 +657,46 @@ impl Fetcher {
     }
 }
 
+/// Deserializes a JSON array of functions entry by entry, skipping bad entries
+struct FunctionsVisitor<'a> {
+    url: &'a str,
+    extension: &'a str,
+}
+
+impl<'de> serde::de::Visitor<'de> for FunctionsVisitor<'_> {
+    type Value = Vec<Function>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of functions")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut functions = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        let mut i = 0;
+        while let Some(raw) = seq.next_element::<serde_json::Value>()? {
+            match serde_json::from_value::<Function>(raw) {
+                Ok(mut func) => {
+                    func.origin = FunctionOrigin::from_extension(Some(self.extension));
+                    func.extension = Some(self.extension.to_string());
+                    func.source_url = Some(self.url.to_string());
+                    functions.push(func);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "[forge-kit] Skipping function #{} from {}: {}",
+                        i, self.url, e
+                    );
+                }
+            }
+            i += 1;
+        }
+        Ok(functions)
+    }
+}
+
 impl Default for Fetcher {
     fn default() -> Self {
         Self::new()
@@ -376,29 +707,108 @@ impl Default for Fetcher {
 // Metadata Manager
 // ============================================================================
 
+/// What to do when a fetched function's name (or alias) is already
+/// registered by a different extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionPolicy {
+    /// Replace the registered function with the new one
+    #[default]
+    Overwrite,
+    /// Keep the registered function under the colliding name; the new one
+    /// is still registered under its names that did not collide
+    KeepFirst,
+    /// Like `KeepFirst`, and also report the collision as a fetch error
+    Error,
+}
+
+/// A function name registered by two different extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    /// The colliding name or alias, with its `$` prefix
+    pub name: String,
+    /// Extension of the function that was registered first
+    pub existing: Option<String>,
+    /// Extension of the function that collided with it
+    pub incoming: Option<String>,
+}
+
+impl std::fmt::Display for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let extension = |ext: &Option<String>| ext.clone().unwrap_or_else(|| "core".to_string());
+        write!(
+            f,
+            "{} from {} collides with {} from {}",
+            self.name,
+            extension(&self.incoming),
+            self.name,
+            extension(&self.existing)
+        )
+    }
+}
+
+/// Functions using each enum, keyed by enum name and sorted by function name
+type EnumIndex = HashMap<String, Vec<Arc<Function>>>;
+
 /// High-performance metadata manager
 pub struct MetadataManager {
     trie: std::sync::RwLock<FunctionTrie>,
     enums: DashMap<String, Vec<String>>,
     events: DashMap<String, Event>,
     sources: std::sync::RwLock<Vec<MetadataSource>>,
-    fetcher: Fetcher,
+    fetcher: Arc<Fetcher>,
     custom_function_names: DashMap<String, ()>,
+    collision_policy: CollisionPolicy,
+    collisions: std::sync::RwLock<Vec<Collision>>,
+    /// Bumped after every change to functions, enums or events
+    generation: AtomicU64,
+    /// Reverse index from enum name to the functions using it, with the
+    /// generation it was built at
+    enum_index: std::sync::RwLock<Option<(u64, EnumIndex)>>,
+    /// Last snapshot handed out by [`snapshot`](Self::snapshot)
+    snapshot: std::sync::RwLock<Option<Arc<MetadataSnapshot>>>,
 }
 
 impl MetadataManager {
     /// Create a new metadata manager
     pub fn new() -> Self {
+        Self::with_fetcher(Arc::new(Fetcher::new()))
+    }
+
+    /// Create a metadata manager that downloads sources through `transport`
+    pub fn with_transport(transport: impl MetadataTransport + 'static) -> Self {
+        Self::with_fetcher(Arc::new(Fetcher::with_transport(transport)))
+    }
+
+    /// Create a metadata manager that downloads sources through a shared
+    /// `fetcher`, so several managers can reuse one client and its connections
+    pub fn with_fetcher(fetcher: Arc<Fetcher>) -> Self {
         Self {
             trie: std::sync::RwLock::new(FunctionTrie::new()),
             enums: DashMap::new(),
             events: DashMap::new(),
             sources: std::sync::RwLock::new(Vec::new()),
-            fetcher: Fetcher::new(),
+            fetcher,
             custom_function_names: DashMap::new(),
+            collision_policy: CollisionPolicy::default(),
+            collisions: std::sync::RwLock::new(Vec::new()),
+            generation: AtomicU64::new(0),
+            enum_index: std::sync::RwLock::new(None),
+            snapshot: std::sync::RwLock::new(None),
         }
     }
 
+    /// Choose how functions colliding across extensions are handled
+    pub fn with_collision_policy(mut self, policy: CollisionPolicy) -> Self {
+        self.collision_policy = policy;
+        self
+    }
+
+    /// Every cross-extension name collision seen while adding functions
+    /// since the last [`clear`](Self::clear)
+    pub fn collisions(&self) -> Vec<Collision> {
+        self.collisions.read().unwrap().clone()
+    }
+
     /// Add a metadata source
     pub fn add_source(&self, source: MetadataSource) {
         self.sources.write().unwrap().push(source);
@@ -408,93 +818,163 @@ impl MetadataManager {
     pub async fn fetch_all(&self) -> Result<FetchStats> {
         let sources = self.sources.read().unwrap().clone();
 
-        let mut total_functions = 0;
-        let mut total_enums = 0;
-        let mut total_events = 0;
-        let mut errors = Vec::new();
-
-        for source in sources {
-            if let Some(url) = &source.functions_url {
-                match self
-                    .fetcher
-                    .fetch_functions(url, source.extension.clone())
-                    .await
-                {
-                    Ok(functions) => {
-                        total_functions += functions.len();
-                        self.add_functions(functions);
+        let mut stats = FetchStats::default();
+        for source in &sources {
+            self.fetch_source_into(source, false, &mut stats).await;
+        }
+
+        Ok(stats)
+    }
+
+    /// Refetch the configured source for `extension` only.
+    ///
+    /// Once its functions file loads, every function previously fetched for
+    /// that extension is replaced, so functions dropped upstream disappear.
+    /// Custom functions are never removed.
+    pub async fn fetch_source(&self, extension: &str) -> Result<FetchStats> {
+        let source = self
+            .sources
+            .read()
+            .unwrap()
+            .iter()
+            .find(|source| source.extension == extension)
+            .cloned()
+            .ok_or_else(|| {
+                MetadataError::NotFound(format!("No source for extension {}", extension))
+            })?;
+
+        let mut stats = FetchStats::default();
+        self.fetch_source_into(&source, true, &mut stats).await;
+        Ok(stats)
+    }
+
+    /// Fetch every file of `source`, recording counts and failures in `stats`.
+    /// With `replace`, functions of the source's extension are removed before
+    /// the fetched ones are added.
+    async fn fetch_source_into(
+        &self,
+        source: &MetadataSource,
+        replace: bool,
+        stats: &mut FetchStats,
+    ) {
+        if let Some(url) = &source.resolved_functions_url() {
+            match self
+                .fetcher
+                .fetch_functions(url, source.extension.clone())
+                .await
+            {
+                Ok(functions) => {
+                    stats.functions += functions.len();
+                    if replace {
+                        self.remove_extension_functions(&source.extension);
                     }
-                    Err(MetadataError::NotFound(_)) => {}
-                    Err(e) => {
-                        errors.push(format!("Functions from {}: {}", source.extension, e));
+                    let collisions = self.add_functions(functions);
+                    if self.collision_policy == CollisionPolicy::Error {
+                        stats.errors.extend(collisions.into_iter().map(|collision| {
+                            FetchError::new(
+                                source,
+                                FetchErrorKind::Functions,
+                                MetadataError::InvalidData(collision.to_string()),
+                            )
+                        }));
                     }
                 }
+                Err(MetadataError::NotFound(_)) => {}
+                Err(e) => {
+                    stats
+                        .errors
+                        .push(FetchError::new(source, FetchErrorKind::Functions, e));
+                }
             }
+        }
 
-            if let Some(url) = &source.enums_url {
-                match self.fetcher.fetch_enums(url).await {
-                    Ok(enums) => {
-                        total_enums += enums.len();
-                        for (name, values) in enums {
-                            self.enums.insert(name, values);
-                        }
+        if let Some(url) = &source.resolved_enums_url() {
+            match self.fetcher.fetch_enums(url).await {
+                Ok(enums) => {
+                    stats.enums += enums.len();
+                    for (name, values) in enums {
+                        self.enums.insert(name, values);
                     }
-                    Err(e) => {
-                        if !matches!(e, MetadataError::NotFound(_)) {
-                            errors.push(format!("Enums from {}: {}", source.extension, e));
-                        }
+                    self.invalidate_caches();
+                }
+                Err(e) => {
+                    if !matches!(e, MetadataError::NotFound(_)) {
+                        stats
+                            .errors
+                            .push(FetchError::new(source, FetchErrorKind::Enums, e));
                     }
                 }
             }
+        }
 
-            if let Some(url) = &source.events_url {
-                match self.fetcher.fetch_events(url).await {
-                    Ok(events) => {
-                        total_events += events.len();
-                        for event in events {
-                            self.events.insert(event.name.clone(), event);
-                        }
+        if let Some(url) = &source.resolved_events_url() {
+            match self.fetcher.fetch_events(url).await {
+                Ok(events) => {
+                    stats.events += events.len();
+                    for event in events {
+                        self.events.insert(event.name.clone(), event);
                     }
-                    Err(e) => {
-                        if !matches!(e, MetadataError::NotFound(_)) {
-                            errors.push(format!("Events from {}: {}", source.extension, e));
-                        }
+                    self.invalidate_caches();
+                }
+                Err(e) => {
+                    if !matches!(e, MetadataError::NotFound(_)) {
+                        stats
+                            .errors
+                            .push(FetchError::new(source, FetchErrorKind::Events, e));
                     }
                 }
             }
         }
+    }
 
-        Ok(FetchStats {
-            functions: total_functions,
-            enums: total_enums,
-            events: total_events,
-            errors,
-        })
+    /// Remove every non-custom function (and alias) fetched for `extension`
+    fn remove_extension_functions(&self, extension: &str) {
+        let mut trie = self.trie.write().unwrap();
+        let stale: Vec<String> = trie
+            .get_all_with_keys()
+            .into_iter()
+            .filter(|(_, func)| {
+                func.extension.as_deref() == Some(extension)
+                    && !self.custom_function_names.contains_key(&func.name)
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        for key in stale {
+            trie.remove(&key);
+        }
+        self.invalidate_caches();
     }
 
-    fn add_functions(&self, functions: Vec<Function>) {
+    /// Register fetched functions, applying the collision policy to names
+    /// already registered by another extension. Returns the collisions found.
+    fn add_functions(&self, functions: Vec<Function>) -> Vec<Collision> {
         let mut trie = self.trie.write().unwrap();
+        let mut collisions = Vec::new();
 
         for func in functions {
             if self.custom_function_names.contains_key(&func.name) {
                 continue;
             }
-            let arc_func = Arc::new(func.clone());
-            trie.insert(&func.name, arc_func.clone());
-
-            if let Some(aliases) = &func.aliases {
-                for alias in aliases {
-                    let alias_name = if alias.starts_with('$') {
-                        alias.clone()
-                    } else {
-                        format!("${}", alias)
-                    };
-                    let mut alias_func = (*arc_func).clone();
-                    alias_func.name = alias_name.clone();
-                    trie.insert(&alias_name, Arc::new(alias_func));
-                }
+
+            let found = find_collisions(&trie, &func);
+            if self.collision_policy == CollisionPolicy::Overwrite {
+                register_function(&mut trie, func);
+            } else {
+                let taken: Vec<&str> = found.iter().map(|c| c.name.as_str()).collect();
+                register_function_except(&mut trie, func, &taken);
             }
+            collisions.extend(found);
+        }
+
+        if !collisions.is_empty() {
+            self.collisions
+                .write()
+                .unwrap()
+                .extend(collisions.iter().cloned());
         }
+        self.invalidate_caches();
+        collisions
     }
 
     pub fn add_custom_functions_from_json(&self, json: &str) -> Result<usize> {
@@ -511,43 +991,24 @@ impl MetadataManager {
         for (i, raw) in raw_items.into_iter().enumerate() {
             match serde_json::from_value::<Function>(raw) {
                 Ok(mut func) => {
-                    // Guarantee $ prefix
-                    if !func.name.starts_with('$') {
-                        func.name = format!("${}", func.name);
-                    }
+                    func.name = dollar_prefixed(&func.name);
 
                     func.category = func.category.or(Some("custom".to_string()));
+                    func.origin = FunctionOrigin::Custom;
 
-                    let arc_func = Arc::new(func.clone());
-
-                    // Insert primary function
-                    trie.insert(&func.name, arc_func.clone());
-                    self.custom_function_names.insert(func.name.clone(), ());
+                    // Insert primary function and its aliases
+                    for key in register_function(&mut trie, func) {
+                        self.custom_function_names.insert(key, ());
+                    }
 
                     count += 1;
-
-                    // Register aliases
-                    if let Some(aliases) = &func.aliases {
-                        for alias in aliases {
-                            let alias_name = if alias.starts_with('$') {
-                                alias.clone()
-                            } else {
-                                format!("${}", alias)
-                            };
-
-                            let mut alias_func = (*arc_func).clone();
-                            alias_func.name = alias_name.clone();
-
-                            trie.insert(&alias_name, Arc::new(alias_func));
-                            self.custom_function_names.insert(alias_name.clone(), ());
-                        }
-                    }
                 }
                 Err(e) => {
                     eprintln!("[forge-kit] Skipping custom function #{}: {}", i, e);
                 }
             }
         }
+        self.invalidate_caches();
 
         Ok(count)
     }
@@ -560,6 +1021,50 @@ impl MetadataManager {
         }
 
         self.custom_function_names.clear();
+        self.invalidate_caches();
+    }
+
+    /// Remove a function and every alias registered alongside it.
+    ///
+    /// Pass the canonical name: alias entries are separate copies, so removing
+    /// by alias only unlinks that alias (and any aliases it lists).
+    pub fn remove_function(&self, name: &str) -> Option<Arc<Function>> {
+        let mut trie = self.trie.write().unwrap();
+        let removed = self.remove_function_locked(&mut trie, name);
+        self.invalidate_caches();
+        removed
+    }
+
+    /// Replace a registered function (and its aliases) with `func`, or insert
+    /// it if it was not registered yet. Custom functions stay custom.
+    pub fn update_function(&self, func: Function) {
+        let mut trie = self.trie.write().unwrap();
+        let is_custom = self.custom_function_names.contains_key(&func.name);
+
+        self.remove_function_locked(&mut trie, &func.name);
+        let keys = register_function(&mut trie, func);
+
+        if is_custom {
+            for key in keys {
+                self.custom_function_names.insert(key, ());
+            }
+        }
+        self.invalidate_caches();
+    }
+
+    fn remove_function_locked(&self, trie: &mut FunctionTrie, name: &str) -> Option<Arc<Function>> {
+        let removed = trie.remove(name)?;
+        self.custom_function_names.remove(name);
+
+        if let Some(aliases) = &removed.aliases {
+            for alias in aliases {
+                let alias_name = dollar_prefixed(alias);
+                trie.remove(&alias_name);
+                self.custom_function_names.remove(&alias_name);
+            }
+        }
+
+        Some(removed)
     }
 
     /// Load custom-functions JSON from a file on disk and register every entry.
@@ -587,7 +1092,7 @@ impl MetadataManager {
     // ========================================================================
 
     /// Scan `folder` (and all sub-folders) for `*.js` / `*.ts` files, extract
-    /// every custom function found via regex-based heuristics, and return a
+    /// every custom function found by scanning their object literals, and return a
     /// pretty-printed JSON string of `Function` objects.
     ///
     /// **Intended as a one-time build / CLI step.**  Save the result to a file
@@ -595,10 +1100,23 @@ impl MetadataManager {
     /// — no source parsing is needed at runtime.
     ///
     /// The output is directly consumable by [`add_custom_functions_from_json`].
+    /// `node_modules`, `.git` and `dist` directories are skipped; see
+    /// [`generate_custom_functions_json_with_options`] to change that.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn generate_custom_functions_json(
         &self,
         folder: impl AsRef<std::path::Path>,
+    ) -> Result<String> {
+        self.generate_custom_functions_json_with_options(folder, ScanOptions::default())
+    }
+
+    /// Like [`generate_custom_functions_json`] but with control over which
+    /// directories are scanned.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn generate_custom_functions_json_with_options(
+        &self,
+        folder: impl AsRef<std::path::Path>,
+        options: ScanOptions,
     ) -> Result<String> {
         let folder = folder.as_ref();
         if !folder.exists() || !folder.is_dir() {
@@ -609,7 +1127,7 @@ impl MetadataManager {
         }
 
         let mut functions: Vec<Function> = Vec::new();
-        collect_functions_from_folder(folder, &mut functions)?;
+        collect_functions_from_folder(folder, &options, &mut functions)?;
 
         serde_json::to_string_pretty(&functions).map_err(|e| {
             MetadataError::ParseError(format!("Failed to serialize custom functions: {}", e))
@@ -656,6 +1174,19 @@ impl MetadataManager {
         self.trie.read().unwrap().get_exact(name)
     }
 
+    /// Run `f` on the function registered under `name` (case-insensitive)
+    /// while holding the read lock, without cloning its `Arc`.
+    ///
+    /// `f` must not call back into this manager's write methods.
+    #[inline]
+    pub fn with_function<R>(&self, name: &str, f: impl FnOnce(&Function) -> R) -> Option<R> {
+        self.trie
+            .read()
+            .unwrap()
+            .get_exact_ref(name)
+            .map(|func| f(func))
+    }
+
     /// Get the longest registered function name that is a prefix of `text`,
     /// matching strictly from the start of `text`.
     #[inline]
@@ -663,6 +1194,16 @@ impl MetadataManager {
         self.trie.read().unwrap().get_prefix(text)
     }
 
+    /// Like [`get_prefix`](Self::get_prefix), also returning the unmatched
+    /// tail of `text` following the function name
+    #[inline]
+    pub fn get_prefix_with_remainder<'t>(
+        &self,
+        text: &'t str,
+    ) -> Option<(String, Arc<Function>, &'t str)> {
+        self.trie.read().unwrap().get_prefix_with_remainder(text)
+    }
+
     /// Get function: tries exact match first, then prefix match from the start.
     ///
     /// Use `get_exact` when you need strict lookup (e.g. bracketed calls).
@@ -675,14 +1216,24 @@ impl MetadataManager {
     }
 
     /// Get function with match info (matched key + Arc)
+    ///
+    /// Exact matches report the key with its registered casing, so comparing
+    /// it against `name` reveals casing mismatches.
     pub fn get_with_match(&self, name: &str) -> Option<(String, Arc<Function>)> {
         let trie = self.trie.read().unwrap();
-        if let Some(func) = trie.get_exact(name) {
-            return Some((name.to_string(), func));
+        if let Some(found) = trie.get_case_match(name) {
+            return Some((found.canonical, found.function));
         }
         trie.get_prefix(name)
     }
 
+    /// Get function by exact name (case-insensitive) along with its canonical
+    /// casing and whether `name` matched that casing exactly
+    #[inline]
+    pub fn get_case_match(&self, name: &str) -> Option<CaseMatch> {
+        self.trie.read().unwrap().get_case_match(name)
+    }
+
     /// Get multiple functions
     pub fn get_many(&self, names: &[&str]) -> Vec<Option<Arc<Function>>> {
         names.iter().map(|name| self.get(name)).collect()
@@ -694,18 +1245,164 @@ impl MetadataManager {
         self.trie.read().unwrap().get_completions(prefix)
     }
 
+    /// Get completions for a prefix as insert-ready editor items with
+    /// argument placeholders
+    pub fn completion_items(&self, prefix: &str) -> Vec<CompletionItem> {
+        self.get_completions(prefix)
+            .iter()
+            .map(|func| CompletionItem::from(&**func))
+            .collect()
+    }
+
+    /// Fuzzy (subsequence) completion across all function names, ranked by
+    /// relevance and truncated to `limit` results.
+    ///
+    /// Prefix matches, hits on camelCase/`_` boundaries, and consecutive runs
+    /// score higher; gaps between matched characters score lower. Use
+    /// [`get_completions`](Self::get_completions) for the cheap exact-prefix path.
+    pub fn fuzzy_complete(&self, query: &str, limit: usize) -> Vec<(Arc<Function>, i64)> {
+        let mut scored: Vec<(Arc<Function>, i64)> = self
+            .all_functions()
+            .into_iter()
+            .filter_map(|func| fuzzy_score(query, &func.name).map(|score| (func, score)))
+            .collect();
+
+        scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+        scored.truncate(limit);
+        scored
+    }
+
     /// Get all functions
     #[inline]
     pub fn all_functions(&self) -> Vec<Arc<Function>> {
         self.trie.read().unwrap().all_functions()
     }
 
+    /// Call `f` for every function while holding the read lock, without
+    /// cloning `Arc`s or building a `Vec`. `f` must not write to this manager.
+    pub fn for_each_function(&self, f: impl FnMut(&Function)) {
+        self.trie.read().unwrap().for_each(f);
+    }
+
+    /// Get up to `limit` functions starting at `offset`, ordered by name so
+    /// consecutive pages cover every function exactly once
+    pub fn functions_page(&self, offset: usize, limit: usize) -> Vec<Arc<Function>> {
+        let mut functions = self.all_functions();
+        functions.sort_by_cached_key(|f| f.name.to_lowercase());
+        functions.into_iter().skip(offset).take(limit).collect()
+    }
+
+    /// Get all user-defined functions (those with [`FunctionOrigin::Custom`])
+    pub fn custom_functions(&self) -> Vec<Arc<Function>> {
+        self.all_functions()
+            .into_iter()
+            .filter(|f| f.origin == FunctionOrigin::Custom)
+            .collect()
+    }
+
     /// Get enum values
     #[inline]
     pub fn get_enum(&self, name: &str) -> Option<Vec<String>> {
         self.enums.get(name).map(|v| v.clone())
     }
 
+    /// Get all functions with an argument that accepts the given enum, either
+    /// by `enum_name` or through an inline enum equal to its values.
+    ///
+    /// Answered from a reverse index that is built on the first query and
+    /// rebuilt after functions or enums change.
+    pub fn functions_using_enum(&self, enum_name: &str) -> Vec<Arc<Function>> {
+        let generation = self.generation.load(Ordering::Acquire);
+        if let Some((built, index)) = &*self.enum_index.read().unwrap()
+            && *built == generation
+        {
+            return index.get(enum_name).cloned().unwrap_or_default();
+        }
+
+        let index = self.build_enum_index();
+        let results = index.get(enum_name).cloned().unwrap_or_default();
+        *self.enum_index.write().unwrap() = Some((generation, index));
+        results
+    }
+
+    fn build_enum_index(&self) -> EnumIndex {
+        let mut by_values: HashMap<Vec<String>, Vec<String>> = HashMap::new();
+        self.for_each_enum(|name, values| {
+            by_values
+                .entry(values.to_vec())
+                .or_default()
+                .push(name.to_string());
+        });
+
+        let mut index = EnumIndex::new();
+        for func in self.all_functions() {
+            let mut names: Vec<&str> = Vec::new();
+            for arg in func.args.iter().flatten() {
+                names.extend(arg.enum_name.as_deref());
+                if let Some(enums) = arg.arg_enum.as_ref().and_then(|v| by_values.get(v)) {
+                    names.extend(enums.iter().map(String::as_str));
+                }
+            }
+            names.sort_unstable();
+            names.dedup();
+            for name in names {
+                index
+                    .entry(name.to_string())
+                    .or_default()
+                    .push(func.clone());
+            }
+        }
+
+        for functions in index.values_mut() {
+            functions.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        index
+    }
+
+    /// Get an immutable view of the current functions, enums and events.
+    ///
+    /// Lookups on the snapshot never touch this manager's locks, so a shared
+    /// `Arc` can serve many concurrent readers. The snapshot is built on the
+    /// first call and reused until metadata changes; later calls then return
+    /// a fresh one while earlier snapshots stay valid and unchanged.
+    pub fn snapshot(&self) -> Arc<MetadataSnapshot> {
+        let generation = self.generation.load(Ordering::Acquire);
+        if let Some(snapshot) = &*self.snapshot.read().unwrap()
+            && snapshot.generation == generation
+        {
+            return snapshot.clone();
+        }
+
+        let snapshot = Arc::new(MetadataSnapshot {
+            trie: self.trie.read().unwrap().clone(),
+            enums: self.all_enums(),
+            events: self
+                .events
+                .iter()
+                .map(|e| (e.key().clone(), e.value().clone()))
+                .collect(),
+            generation,
+        });
+        *self.snapshot.write().unwrap() = Some(snapshot.clone());
+        snapshot
+    }
+
+    /// Get the values an argument accepts: its named enum when it has one,
+    /// otherwise its inline enum
+    pub fn arg_enum_values(&self, arg: &Arg) -> Option<Vec<String>> {
+        match &arg.enum_name {
+            Some(enum_name) => self.get_enum(enum_name),
+            None => arg.arg_enum.clone(),
+        }
+    }
+
+    /// Get the output types declared by a function, or `None` if it is not
+    /// registered. An empty list means the function declares no output.
+    pub fn output_types(&self, name: &str) -> Option<Vec<String>> {
+        self.get_exact(&dollar_prefixed(name))
+            .map(|func| func.output_types().to_vec())
+    }
+
     /// Get all enums
     pub fn all_enums(&self) -> HashMap<String, Vec<String>> {
         self.enums
@@ -714,6 +1411,13 @@ impl MetadataManager {
             .collect()
     }
 
+    /// Call `f` for every enum name and its values without cloning them
+    pub fn for_each_enum(&self, mut f: impl FnMut(&str, &[String])) {
+        for entry in self.enums.iter() {
+            f(entry.key(), entry.value());
+        }
+    }
+
     /// Get event by name
     #[inline]
     pub fn get_event(&self, name: &str) -> Option<Event> {
@@ -725,12 +1429,24 @@ impl MetadataManager {
         self.events.iter().map(|e| e.value().clone()).collect()
     }
 
+    /// Call `f` for every event without cloning them
+    pub fn for_each_event(&self, mut f: impl FnMut(&Event)) {
+        for entry in self.events.iter() {
+            f(entry.value());
+        }
+    }
+
     /// Get function count
     #[inline]
     pub fn function_count(&self) -> usize {
         self.trie.read().unwrap().len()
     }
 
+    /// Get the size and shape of the function lookup trie
+    pub fn trie_stats(&self) -> TrieStats {
+        self.trie.read().unwrap().stats()
+    }
+
     /// Get enum count
     #[inline]
     pub fn enum_count(&self) -> usize {
@@ -748,6 +1464,13 @@ impl MetadataManager {
         self.trie.write().unwrap().clear();
         self.enums.clear();
         self.events.clear();
+        self.collisions.write().unwrap().clear();
+        self.invalidate_caches();
+    }
+
+    /// Mark the enum reverse index and snapshot as stale after metadata changed
+    fn invalidate_caches(&self) {
+        self.generation.fetch_add(1, Ordering::Release);
     }
 }
 
@@ -757,14 +1480,363 @@ impl Default for MetadataManager {
     }
 }
 
+/// Immutable, lock-free view of a [`MetadataManager`] at one point in time.
+///
+/// Obtained from [`MetadataManager::snapshot`]; lookups mirror the manager's.
+pub struct MetadataSnapshot {
+    trie: FunctionTrie,
+    enums: HashMap<String, Vec<String>>,
+    events: HashMap<String, Event>,
+    generation: u64,
+}
+
+impl MetadataSnapshot {
+    /// Get function by exact name (case-insensitive)
+    #[inline]
+    pub fn get_exact(&self, name: &str) -> Option<Arc<Function>> {
+        self.trie.get_exact(name)
+    }
+
+    /// Get function: tries exact match first, then prefix match from the start
+    pub fn get(&self, name: &str) -> Option<Arc<Function>> {
+        self.trie
+            .get_exact(name)
+            .or_else(|| self.trie.get_prefix(name).map(|(_, func)| func))
+    }
+
+    /// Get the longest registered function name that is a prefix of `text`
+    #[inline]
+    pub fn get_prefix(&self, text: &str) -> Option<(String, Arc<Function>)> {
+        self.trie.get_prefix(text)
+    }
+
+    /// Like [`get_prefix`](Self::get_prefix), also returning the unmatched
+    /// tail of `text` following the function name
+    #[inline]
+    pub fn get_prefix_with_remainder<'t>(
+        &self,
+        text: &'t str,
+    ) -> Option<(String, Arc<Function>, &'t str)> {
+        self.trie.get_prefix_with_remainder(text)
+    }
+
+    /// Get function by exact name along with its canonical casing
+    #[inline]
+    pub fn get_case_match(&self, name: &str) -> Option<CaseMatch> {
+        self.trie.get_case_match(name)
+    }
+
+    /// Get completions for a prefix
+    #[inline]
+    pub fn get_completions(&self, prefix: &str) -> Vec<Arc<Function>> {
+        self.trie.get_completions(prefix)
+    }
+
+    /// Get all functions
+    #[inline]
+    pub fn all_functions(&self) -> Vec<Arc<Function>> {
+        self.trie.all_functions()
+    }
+
+    /// Get enum values
+    #[inline]
+    pub fn get_enum(&self, name: &str) -> Option<&[String]> {
+        self.enums.get(name).map(Vec::as_slice)
+    }
+
+    /// Get event by name
+    #[inline]
+    pub fn get_event(&self, name: &str) -> Option<&Event> {
+        self.events.get(name)
+    }
+
+    /// Get function count
+    #[inline]
+    pub fn function_count(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Get enum count
+    #[inline]
+    pub fn enum_count(&self) -> usize {
+        self.enums.len()
+    }
+
+    /// Get event count
+    #[inline]
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
+}
+
+/// Insert `func` under its name and every alias, returning all registered keys.
+fn register_function(trie: &mut FunctionTrie, func: Function) -> Vec<String> {
+    register_function_except(trie, func, &[])
+}
+
+/// Like [`register_function`], but leave the keys in `skip` untouched
+fn register_function_except(trie: &mut FunctionTrie, func: Function, skip: &[&str]) -> Vec<String> {
+    let mut keys = Vec::with_capacity(1 + func.aliases.as_ref().map_or(0, Vec::len));
+    let arc_func = Arc::new(func);
+    if !skip.contains(&arc_func.name.as_str()) {
+        trie.insert(&arc_func.name, arc_func.clone());
+        keys.push(arc_func.name.clone());
+    }
+
+    if let Some(aliases) = &arc_func.aliases {
+        for alias in aliases {
+            let alias_name = dollar_prefixed(alias);
+            if skip.contains(&alias_name.as_str()) {
+                continue;
+            }
+            let mut alias_func = (*arc_func).clone();
+            alias_func.name = alias_name.clone();
+            trie.insert(&alias_name, Arc::new(alias_func));
+            keys.push(alias_name);
+        }
+    }
+
+    keys
+}
+
+/// Score `candidate` against `query` as a case-insensitive subsequence match,
+/// ignoring any leading `$`. Returns `None` when `query` is not a subsequence.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    const MATCH: i64 = 16;
+    const CONSECUTIVE: i64 = 8;
+    const BOUNDARY: i64 = 12;
+    const PREFIX: i64 = 50;
+
+    let query = query.trim_start_matches('$');
+    let candidate = candidate.trim_start_matches('$');
+    let chars: Vec<char> = candidate.chars().collect();
+
+    let mut score = 0i64;
+    let mut last_match: Option<usize> = None;
+    let mut idx = 0usize;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let found = (idx..chars.len()).find(|&i| chars[i].to_lowercase().eq(std::iter::once(q)))?;
+
+        score += MATCH;
+        let is_boundary = found == 0
+            || (chars[found].is_uppercase() && chars[found - 1].is_lowercase())
+            || matches!(chars[found - 1], '_' | '-');
+        if is_boundary {
+            score += BOUNDARY;
+        }
+        match last_match {
+            Some(prev) if prev + 1 == found => score += CONSECUTIVE,
+            Some(prev) => score -= (found - prev - 1) as i64,
+            None => score -= found as i64,
+        }
+
+        last_match = Some(found);
+        idx = found + 1;
+    }
+
+    if candidate.to_lowercase().starts_with(&query.to_lowercase()) {
+        score += PREFIX;
+    }
+
+    // Prefer shorter names when everything else is equal
+    Some(score - chars.len() as i64 / 4)
+}
+
+/// Names of `func` (and its aliases) already registered by a function from
+/// another extension
+fn find_collisions(trie: &FunctionTrie, func: &Function) -> Vec<Collision> {
+    let aliases = func
+        .aliases
+        .iter()
+        .flatten()
+        .map(|alias| dollar_prefixed(alias));
+    std::iter::once(func.name.clone())
+        .chain(aliases)
+        .filter_map(|name| {
+            let existing = trie.get_exact_ref(&name)?;
+            (existing.extension != func.extension).then(|| Collision {
+                name,
+                existing: existing.extension.clone(),
+                incoming: func.extension.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Guarantee a leading `$` on a function or alias name.
+pub(crate) fn dollar_prefixed(name: &str) -> String {
+    if name.starts_with('$') {
+        name.to_string()
+    } else {
+        format!("${}", name)
+    }
+}
+
 // ============================================================================
 // JS/TS source parser  (used only by generate_custom_functions_json)
 // ============================================================================
 
+/// Options for scanning a folder of JS/TS sources
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Directory names to skip. `*` matches any run of characters, so
+    /// `build*` skips both `build` and `build-cache`.
+    pub ignore: Vec<String>,
+    /// Parse files on a thread pool (requires the "parallel" feature,
+    /// ignored otherwise). The output is the same either way.
+    pub parallel: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ScanOptions {
+    /// Skips `node_modules`, `.git` and `dist`
+    fn default() -> Self {
+        Self {
+            ignore: ["node_modules", ".git", "dist"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            parallel: true,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ScanOptions {
+    /// Replace the ignored directory patterns
+    pub fn with_ignore<I, P>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        self.ignore = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Enable or disable parallel parsing
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    fn is_ignored(&self, dir_name: &str) -> bool {
+        self.ignore
+            .iter()
+            .any(|pattern| wildcard_match(pattern, dir_name))
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters
+#[cfg(not(target_arch = "wasm32"))]
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
 /// Recursively walk `path`, collecting `Function` values from every JS/TS file.
 /// No trie registration happens here — output is for serialization only.
+///
+/// Files are parsed in path order (in parallel with the "parallel" feature),
+/// so the output is stable across runs.
 #[cfg(not(target_arch = "wasm32"))]
-fn collect_functions_from_folder(path: &std::path::Path, out: &mut Vec<Function>) -> Result<()> {
+fn collect_functions_from_folder(
+    path: &std::path::Path,
+    options: &ScanOptions,
+    out: &mut Vec<Function>,
+) -> Result<()> {
+    let files = collect_js_ts_files(path, options)?;
+    let parse_file = |file_path: &std::path::PathBuf| -> Result<Vec<Function>> {
+        let content = read_source_file(file_path)?;
+        Ok(parse_functions_from_js_ts(
+            &content,
+            file_path.to_str().unwrap_or_default(),
+        ))
+    };
+
+    #[cfg(feature = "parallel")]
+    let parsed: Vec<Result<Vec<Function>>> = if options.parallel {
+        use rayon::prelude::*;
+        files.par_iter().map(parse_file).collect()
+    } else {
+        files.iter().map(parse_file).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let parsed: Vec<Result<Vec<Function>>> = files.iter().map(parse_file).collect();
+
+    for functions in parsed {
+        out.extend(functions?);
+    }
+    Ok(())
+}
+
+/// Recursively walk `path`, calling `visit` with the path and contents of
+/// every `*.js` / `*.ts` file found, in path order.
+///
+/// Directories matching `options.ignore` are skipped, and each directory is
+/// visited at most once so symlink cycles terminate.
+#[cfg(all(feature = "validation", not(target_arch = "wasm32")))]
+pub(crate) fn walk_js_ts_files(
+    path: &std::path::Path,
+    options: &ScanOptions,
+    visit: &mut dyn FnMut(&std::path::Path, String),
+) -> Result<()> {
+    for file_path in collect_js_ts_files(path, options)? {
+        let content = read_source_file(&file_path)?;
+        visit(&file_path, content);
+    }
+    Ok(())
+}
+
+/// Every `*.js` / `*.ts` file under `path`, sorted
+#[cfg(not(target_arch = "wasm32"))]
+fn collect_js_ts_files(
+    path: &std::path::Path,
+    options: &ScanOptions,
+) -> Result<Vec<std::path::PathBuf>> {
+    let mut visited = std::collections::HashSet::new();
+    let mut files = Vec::new();
+    walk_dir(path, options, &mut visited, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_source_file(path: &std::path::Path) -> Result<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| MetadataError::InvalidData(format!("Cannot read {}: {}", path.display(), e)))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn walk_dir(
+    path: &std::path::Path,
+    options: &ScanOptions,
+    visited: &mut std::collections::HashSet<std::path::PathBuf>,
+    files: &mut Vec<std::path::PathBuf>,
+) -> Result<()> {
+    let canonical = std::fs::canonicalize(path).map_err(|e| {
+        MetadataError::InvalidData(format!("Cannot read dir {}: {}", path.display(), e))
+    })?;
+    if !visited.insert(canonical) {
+        return Ok(());
+    }
+
     let entries = std::fs::read_dir(path).map_err(|e| {
         MetadataError::InvalidData(format!("Cannot read dir {}: {}", path.display(), e))
     })?;
@@ -775,7 +1847,13 @@ fn collect_functions_from_folder(path: &std::path::Path, out: &mut Vec<Function>
             .path();
 
         if entry_path.is_dir() {
-            collect_functions_from_folder(&entry_path, out)?;
+            let ignored = entry_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| options.is_ignored(name));
+            if !ignored {
+                walk_dir(&entry_path, options, visited, files)?;
+            }
         } else if entry_path.is_file() {
             let is_js_ts = entry_path
                 .extension()
@@ -784,17 +1862,7 @@ fn collect_functions_from_folder(path: &std::path::Path, out: &mut Vec<Function>
                 .unwrap_or(false);
 
             if is_js_ts {
-                let content = std::fs::read_to_string(&entry_path).map_err(|e| {
-                    MetadataError::InvalidData(format!(
-                        "Cannot read {}: {}",
-                        entry_path.display(),
-                        e
-                    ))
-                })?;
-                out.extend(parse_functions_from_js_ts(
-                    &content,
-                    entry_path.to_str().unwrap_or_default(),
-                ));
+                files.push(entry_path);
             }
         }
     }
@@ -802,184 +1870,98 @@ fn collect_functions_from_folder(path: &std::path::Path, out: &mut Vec<Function>
     Ok(())
 }
 
-/// Extract `Function` metadata from a single JS/TS source file using regex
-/// heuristics.  Mirrors the logic from `parse_custom_functions_from_js` in the
-/// older metadata implementation but produces `Function` values directly so they
-/// can be round-tripped through JSON without a lossy intermediate type.
+/// Extract `Function` metadata from a single JS/TS source file.
+///
+/// Every object literal with a top-level string `name:` is a function, unless
+/// it is nested inside another function object (e.g. its `args` entries).
+/// Fields are read only from the object's own top level, so adjacent function
+/// objects never borrow each other's `args` or `description`. Keys inside
+/// string literals and comments are ignored.
 #[cfg(not(target_arch = "wasm32"))]
 fn parse_functions_from_js_ts(content: &str, file_path: &str) -> Vec<Function> {
-    use regex::Regex;
     use serde_json::Value as JsonValue;
 
-    // ── Regexes ──────────────────────────────────────────────────────────────
-    let name_re = Regex::new(r#"name:\s*['"]([^'"]+)['"]"#).expect("regex");
-    let params_re = Regex::new(r#"(?:params|args):\s*\["#).expect("regex");
-    let desc_re = Regex::new(
-        r#"(?s)description:\s*(?:'((?:[^'\\]|\\.)*?)'|"((?:[^"\\]|\\.)*?)"|`((?:[^`\\]|\\.)*?)`)"#,
-    )
-    .expect("regex");
-    let brackets_re = Regex::new(r"brackets:\s*(true|false)").expect("regex");
-    let p_name_re = Regex::new(r#"name:\s*['"]([^'"]+)['"]"#).expect("regex");
-    let required_re = Regex::new(r"(?i)required:\s*(true|false)").expect("regex");
-    let rest_re = Regex::new(r"(?i)rest:\s*(true|false)").expect("regex");
-    let type_re = Regex::new(r"type:\s*([^,}\n\s]+)").expect("regex");
-    let output_re = Regex::new(r"output:\s*([^,}\n\s]+)").expect("regex");
-
-    // ── Collect all name: positions with line numbers ─────────────────────────
-    let name_matches: Vec<(usize, usize, String, u32)> = name_re
-        .captures_iter(content)
-        .map(|c: regex::Captures| {
-            let m = c.get(0).unwrap();
-            let start = m.start();
-            let line = content[..start].chars().filter(|&c| c == '\n').count() as u32;
-            (start, m.end(), c[1].to_string(), line)
-        })
-        .collect();
-
-    // ── Collect params/args array ranges ─────────────────────────────────────
-    let mut params_ranges: Vec<std::ops::Range<usize>> = Vec::new();
-    for m in params_re.find_iter(content) {
-        let start = m.start();
-        let mut depth = 0i32;
-        for (i, c) in content[start..].char_indices() {
-            if c == '[' {
-                depth += 1;
-            } else if c == ']' {
-                depth -= 1;
-                if depth == 0 {
-                    params_ranges.push(start..start + i);
-                    break;
-                }
-            }
-        }
-    }
-
-    // ── Filter: keep only top-level function name: declarations ──────────────
-    let func_names: Vec<_> = name_matches
-        .into_iter()
-        .filter(|m| !params_ranges.iter().any(|r| r.contains(&m.0)))
-        .collect();
+    let masked = mask_js_literals(content);
+    let scan = JsScan {
+        content,
+        masked: &masked,
+    };
 
-    // ── Build Function values ─────────────────────────────────────────────────
+    let mut function_objects: Vec<(usize, usize)> = Vec::new();
     let mut functions = Vec::new();
 
-    for i in 0..func_names.len() {
-        let (_, end_pos, raw_name, line) = &func_names[i];
-        let chunk_end = if i + 1 < func_names.len() {
-            func_names[i + 1].0
-        } else {
-            content.len()
-        };
-        let chunk = &content[*end_pos..chunk_end];
+    for (open, close) in scan.objects() {
+        if function_objects.iter().any(|&(o, c)| o < open && close < c) {
+            continue;
+        }
 
-        // Ensure $ prefix
-        let name = if raw_name.starts_with('$') {
-            raw_name.clone()
-        } else {
-            format!("${}", raw_name)
+        let object = scan.object(open, close);
+        let Some(name_pos) = object.value_of("name") else {
+            continue;
+        };
+        let Some(raw_name) = scan.read_string(name_pos) else {
+            continue;
         };
+        function_objects.push((open, close));
 
-        let description = desc_re
-            .captures(chunk)
-            .and_then(|c: regex::Captures| c.get(1).or(c.get(2)).or(c.get(3)))
-            .map(|m: regex::Match| m.as_str().to_string())
+        let description = object
+            .value_of("description")
+            .and_then(|pos| scan.read_string(pos))
             .unwrap_or_else(|| "Custom function".to_string());
 
-        let brackets = brackets_re
-            .captures(chunk)
-            .map(|c: regex::Captures| &c[1] == "true");
-
-        let output: Option<serde_json::Value> = output_re.captures(chunk).map(|c: regex::Captures| {
-            let parts: Vec<String> = c[1].split(',')
-                .map(|s: &str| {
-                    s.trim()
-                        .trim_matches(|c: char| c == '\'' || c == '"')
-                        .to_string()
-                })
-                .filter(|s: &String| !s.is_empty())
-                .collect();
-            if parts.len() == 1 {
-                serde_json::Value::String(parts[0].clone())
-            } else {
-                serde_json::Value::Array(parts.into_iter().map(serde_json::Value::String).collect())
-            }
+        let brackets = object
+            .value_of("brackets")
+            .and_then(|pos| scan.read_bool(pos));
+
+        // `output: ArgType.Number` or `output: [ArgType.String, "Number"]`,
+        // normalized to the type-name list used by functions.json
+        let output: Option<Vec<String>> = object.value_of("output").map(|pos| {
+            scan.read_list(pos)
+                .unwrap_or_else(|| vec![scan.read_token(pos).to_string()])
+                .iter()
+                .map(|t| js_type_name(t))
+                .filter(|t| !t.is_empty())
+                .collect()
         });
 
-        // Parse args from the params block that belongs to this function chunk
-        let args: Option<Vec<crate::types::Arg>> = params_ranges
-            .iter()
-            .find(|r| r.start >= *end_pos && r.start < chunk_end)
-            .and_then(|p_range| {
-                let p_content = &content[p_range.clone()];
-                let mut parsed_args: Vec<crate::types::Arg> = Vec::new();
-                let mut search = 0;
-
-                while let Some(bstart) = p_content[search..].find('{') {
-                    let abs = search + bstart;
-                    let mut depth = 0i32;
-                    for (j, c) in p_content[abs..].char_indices() {
-                        if c == '{' {
-                            depth += 1;
-                        } else if c == '}' {
-                            depth -= 1;
-                            if depth == 0 {
-                                let body = &p_content[abs + 1..abs + j];
-                                if let Some(n_cap) = p_name_re.captures(body) {
-                                    let raw_type = type_re
-                                        .captures(body)
-                                        .map(|c: regex::Captures| {
-                                            let t = c[1]
-                                                .trim()
-                                                .trim_matches(|c: char| c == '\'' || c == '"');
-                                            t.strip_prefix("ArgType.").unwrap_or(t).to_string()
-                                        })
-                                        .unwrap_or_else(|| "String".to_string());
-
-                                    parsed_args.push(crate::types::Arg {
-                                        name: n_cap[1].to_string(),
-                                        description: desc_re
-                                            .captures(body)
-                                            .and_then(|c: regex::Captures| {
-                                                c.get(1).or(c.get(2)).or(c.get(3))
-                                            })
-                                            .map(|m: regex::Match| m.as_str().to_string())
-                                            .unwrap_or_default(),
-                                        rest: rest_re
-                                            .captures(body)
-                                            .map(|c: regex::Captures| &c[1] == "true")
-                                            .unwrap_or(false),
-                                        required: required_re
-                                            .captures(body)
-                                            .map(|c: regex::Captures| &c[1] == "true"),
-                                        arg_type: JsonValue::String(raw_type),
-                                        ..Default::default()
-                                    });
-                                }
-                                search = abs + j + 1;
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                if parsed_args.is_empty() {
-                    None
-                } else {
-                    Some(parsed_args)
-                }
+        let args: Option<Vec<crate::types::Arg>> = object
+            .value_of("args")
+            .or_else(|| object.value_of("params"))
+            .filter(|&pos| masked.as_bytes()[pos] == b'[')
+            .and_then(|pos| {
+                let parsed: Vec<_> = scan
+                    .elements(pos)
+                    .into_iter()
+                    .filter_map(|(open, close)| scan.parse_arg(open, close))
+                    .collect();
+                (!parsed.is_empty()).then_some(parsed)
             });
 
+        let aliases = object
+            .value_of("aliases")
+            .and_then(|pos| scan.read_list(pos))
+            .filter(|aliases| !aliases.is_empty());
+
+        let category = object
+            .value_of("category")
+            .and_then(|pos| scan.read_string(pos))
+            .unwrap_or_else(|| "custom".to_string());
+
+        let line = content[..name_pos].matches('\n').count() as u32;
+
         functions.push(Function {
-            name,
+            name: dollar_prefixed(&raw_name),
             version: Some(JsonValue::String("1.0.0".to_string())),
             description,
             brackets: brackets.or(if args.is_some() { Some(true) } else { None }),
             unwrap: false,
             args,
             output,
-            category: Some("custom".to_string()),
+            category: Some(category),
+            aliases,
+            origin: FunctionOrigin::Custom,
             local_path: Some(std::path::PathBuf::from(file_path)),
-            line: Some(*line),
+            line: Some(line),
             ..Default::default()
         });
     }
@@ -987,17 +1969,369 @@ fn parse_functions_from_js_ts(content: &str, file_path: &str) -> Vec<Function> {
     functions
 }
 
+/// Copy of `content` with the contents of string literals and comments
+/// replaced by spaces. Quote characters and byte offsets are kept, so
+/// brackets and keys can be located without tripping over quoted text.
+#[cfg(not(target_arch = "wasm32"))]
+fn mask_js_literals(content: &str) -> String {
+    let bytes = content.as_bytes();
+    let mut masked = bytes.to_vec();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != quote {
+                    if bytes[j] == b'\\' && j + 1 < bytes.len() {
+                        masked[j] = b' ';
+                        j += 1;
+                    }
+                    masked[j] = b' ';
+                    j += 1;
+                }
+                i = j + 1;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                let end = memchr::memchr(b'\n', &bytes[i..]).map_or(bytes.len(), |e| i + e);
+                masked[i..end].fill(b' ');
+                i = end;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = memchr::memmem::find(&bytes[i + 2..], b"*/")
+                    .map_or(bytes.len(), |e| i + 2 + e + 2);
+                masked[i..end].fill(b' ');
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    // Only whole characters inside literals were replaced, and only with ASCII
+    String::from_utf8(masked).expect("masking keeps UTF-8 valid")
+}
+
+/// Strip quotes and an `ArgType.` prefix from a type written in JS/TS
+#[cfg(not(target_arch = "wasm32"))]
+fn js_type_name(raw: &str) -> String {
+    let t = raw.trim().trim_matches(|c: char| c == '\'' || c == '"');
+    t.strip_prefix("ArgType.").unwrap_or(t).to_string()
+}
+
+/// A JS/TS source alongside its [`mask_js_literals`] copy
+#[cfg(not(target_arch = "wasm32"))]
+struct JsScan<'a> {
+    content: &'a str,
+    masked: &'a str,
+}
+
+/// The top level of one object literal: everything nested deeper is blanked
+#[cfg(not(target_arch = "wasm32"))]
+struct JsObject {
+    /// Offset of the first byte inside the braces
+    base: usize,
+    top: Vec<u8>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl JsObject {
+    /// Offset of the value of the top-level `key:`, past any whitespace
+    fn value_of(&self, key: &str) -> Option<usize> {
+        let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'$';
+        let top = &self.top;
+        let mut from = 0;
+
+        while let Some(found) = memchr::memmem::find(&top[from..], key.as_bytes()) {
+            let start = from + found;
+            from = start + 1;
+            if start > 0 && is_ident(top[start - 1]) {
+                continue;
+            }
+
+            let mut p = start + key.len();
+            while top.get(p).is_some_and(u8::is_ascii_whitespace) {
+                p += 1;
+            }
+            if top.get(p) != Some(&b':') {
+                continue;
+            }
+            p += 1;
+            while top.get(p).is_some_and(u8::is_ascii_whitespace) {
+                p += 1;
+            }
+            return (p < top.len()).then_some(self.base + p);
+        }
+        None
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl JsScan<'_> {
+    /// Every `{ ... }` range, ordered by opening brace
+    fn objects(&self) -> Vec<(usize, usize)> {
+        let mut open = Vec::new();
+        let mut objects = Vec::new();
+        for (i, b) in self.masked.bytes().enumerate() {
+            match b {
+                b'{' => open.push(i),
+                b'}' => {
+                    if let Some(start) = open.pop() {
+                        objects.push((start, i));
+                    }
+                }
+                _ => {}
+            }
+        }
+        objects.sort_unstable();
+        objects
+    }
+
+    /// Offset of the bracket closing the one at `open`
+    fn matching_close(&self, open: usize) -> Option<usize> {
+        let mut depth = 0usize;
+        for (i, b) in self.masked.bytes().enumerate().skip(open) {
+            match b {
+                b'{' | b'[' | b'(' => depth += 1,
+                b'}' | b']' | b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// The interior of `open..=close` with nested brackets' contents blanked
+    fn object(&self, open: usize, close: usize) -> JsObject {
+        let mut depth = 0usize;
+        let top = self.masked.as_bytes()[open + 1..close]
+            .iter()
+            .map(|&b| {
+                let at_top = match b {
+                    b'{' | b'[' | b'(' => {
+                        depth += 1;
+                        depth == 1
+                    }
+                    b'}' | b']' | b')' => {
+                        depth = depth.saturating_sub(1);
+                        depth == 0
+                    }
+                    _ => depth == 0,
+                };
+                if at_top { b } else { b' ' }
+            })
+            .collect();
+        JsObject {
+            base: open + 1,
+            top,
+        }
+    }
+
+    /// The `{ ... }` elements of the array opening at `open`
+    fn elements(&self, open: usize) -> Vec<(usize, usize)> {
+        let Some(close) = self.matching_close(open) else {
+            return Vec::new();
+        };
+        let array = self.object(open, close);
+        array
+            .top
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'{')
+            .filter_map(|(i, _)| {
+                let start = array.base + i;
+                self.matching_close(start).map(|end| (start, end))
+            })
+            .collect()
+    }
+
+    /// A string literal at `pos`, or several joined with `+`
+    fn read_string(&self, pos: usize) -> Option<String> {
+        let bytes = self.content.as_bytes();
+        let mut result: Option<String> = None;
+        let mut p = pos;
+
+        while let Some(&quote) = bytes.get(p) {
+            if !matches!(quote, b'\'' | b'"' | b'`') {
+                break;
+            }
+            let close = p + 1 + memchr::memchr(quote, &self.masked.as_bytes()[p + 1..])?;
+            result
+                .get_or_insert_with(String::new)
+                .push_str(&self.content[p + 1..close]);
+
+            let rest = &self.content[close + 1..];
+            let after_plus = rest.trim_start().strip_prefix('+');
+            match after_plus {
+                Some(next) => p = self.content.len() - next.trim_start().len(),
+                None => break,
+            }
+        }
+        result
+    }
+
+    fn read_bool(&self, pos: usize) -> Option<bool> {
+        let rest = &self.content[pos..];
+        if rest.starts_with("true") {
+            Some(true)
+        } else if rest.starts_with("false") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// The expression at `pos` up to the next `,`, `}`, `]` or line break
+    fn read_token(&self, pos: usize) -> &str {
+        let end = self.masked[pos..]
+            .find([',', '}', ']', '\n'])
+            .map_or(self.content.len(), |e| pos + e);
+        self.content[pos..end].trim()
+    }
+
+    /// The comma-separated items of an array literal at `pos`
+    fn read_list(&self, pos: usize) -> Option<Vec<String>> {
+        if self.masked.as_bytes().get(pos) != Some(&b'[') {
+            return None;
+        }
+        let close = self.matching_close(pos)?;
+        let array = self.object(pos, close);
+
+        let mut items = Vec::new();
+        let mut start = 0;
+        for end in array
+            .top
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b',')
+            .map(|(i, _)| i)
+            .chain([array.top.len()])
+        {
+            let item = self.content[array.base + start..array.base + end].trim();
+            if !item.is_empty() {
+                items.push(
+                    item.trim_matches(|c| c == '\'' || c == '"' || c == '`')
+                        .to_string(),
+                );
+            }
+            start = end + 1;
+        }
+        Some(items)
+    }
+
+    /// Read one entry of an `args`/`params` array
+    fn parse_arg(&self, open: usize, close: usize) -> Option<crate::types::Arg> {
+        let object = self.object(open, close);
+        let name = self.read_string(object.value_of("name")?)?;
+
+        let mut arg_enum = None;
+        let mut enum_name = object
+            .value_of("enumName")
+            .and_then(|pos| self.read_string(pos));
+        if let Some(pos) = object.value_of("enum") {
+            match self.read_list(pos) {
+                Some(values) => arg_enum = Some(values),
+                None => {
+                    let token = self.read_token(pos);
+                    enum_name.get_or_insert_with(|| {
+                        token.trim_matches(|c| c == '\'' || c == '"').to_string()
+                    });
+                }
+            }
+        }
+
+        Some(crate::types::Arg {
+            name,
+            description: object
+                .value_of("description")
+                .and_then(|pos| self.read_string(pos))
+                .unwrap_or_default(),
+            rest: object
+                .value_of("rest")
+                .and_then(|pos| self.read_bool(pos))
+                .unwrap_or(false),
+            required: object
+                .value_of("required")
+                .and_then(|pos| self.read_bool(pos)),
+            arg_type: serde_json::Value::String(
+                object
+                    .value_of("type")
+                    .map(|pos| js_type_name(self.read_token(pos)))
+                    .unwrap_or_else(|| "String".to_string()),
+            ),
+            arg_enum,
+            enum_name,
+            ..Default::default()
+        })
+    }
+}
+
 // ============================================================================
 // FetchStats
 // ============================================================================
 
 /// Statistics from a fetch operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct FetchStats {
     pub functions: usize,
     pub enums: usize,
     pub events: usize,
-    pub errors: Vec<String>,
+    pub errors: Vec<FetchError>,
+    /// Every source failed and metadata was loaded from a saved cache instead
+    /// (see [`MetadataManager::fetch_all_or_cache`])
+    pub from_cache: bool,
+}
+
+/// Which metadata file of a source failed to load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchErrorKind {
+    Functions,
+    Enums,
+    Events,
+}
+
+impl std::fmt::Display for FetchErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Functions => write!(f, "Functions"),
+            Self::Enums => write!(f, "Enums"),
+            Self::Events => write!(f, "Events"),
+        }
+    }
+}
+
+/// A metadata file that failed to load during [`MetadataManager::fetch_all`]
+#[derive(Debug, Clone)]
+pub struct FetchError {
+    /// Extension of the source the file belongs to
+    pub extension: String,
+    pub kind: FetchErrorKind,
+    pub source: MetadataError,
+}
+
+impl FetchError {
+    fn new(source: &MetadataSource, kind: FetchErrorKind, error: MetadataError) -> Self {
+        Self {
+            extension: source.extension.clone(),
+            kind,
+            source: error,
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} from {}: {}", self.kind, self.extension, self.source)
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
 }
 
 impl std::fmt::Display for FetchStats {
@@ -1010,6 +2344,9 @@ impl std::fmt::Display for FetchStats {
         if !self.errors.is_empty() {
             write!(f, " ({} errors)", self.errors.len())?;
         }
+        if self.from_cache {
+            write!(f, ", loaded from cache")?;
+        }
         Ok(())
     }
 }
@@ -1027,9 +2364,50 @@ pub struct MetadataCache {
     pub version: u32,
 }
 
+/// Upgrades applied by [`MetadataCache::migrate`]; entry `i` rewrites a
+/// version `i + 1` cache into the next version, and the last entry, for the
+/// current version, leaves it as is
+const CACHE_MIGRATIONS: &[fn(serde_json::Value) -> serde_json::Value] = &[migrate_cache_v1];
+
+const _: () = assert!(CACHE_MIGRATIONS.len() == MetadataCache::VERSION as usize);
+
+/// Version 1 is the current format
+fn migrate_cache_v1(value: serde_json::Value) -> serde_json::Value {
+    value
+}
+
 impl MetadataCache {
     const VERSION: u32 = 1;
 
+    /// Deserialize a cache written by this or any earlier version of the
+    /// crate, upgrading it step by step to the current format.
+    ///
+    /// Fails if the cache has no version or one newer than this crate supports.
+    pub fn migrate(cache_json: &str) -> Result<MetadataCache> {
+        let mut value: serde_json::Value = serde_json::from_str(cache_json)
+            .map_err(|e| MetadataError::CacheError(format!("Deserialization failed: {}", e)))?;
+
+        let version = value
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| MetadataError::CacheError("Cache has no version".to_string()))?;
+        if version == 0 || version > u64::from(Self::VERSION) {
+            return Err(MetadataError::CacheError(format!(
+                "Unsupported cache version: expected at most {}, got {}",
+                Self::VERSION,
+                version
+            )));
+        }
+
+        for migration in &CACHE_MIGRATIONS[version as usize - 1..] {
+            value = migration(value);
+        }
+        value["version"] = Self::VERSION.into();
+
+        serde_json::from_value(value)
+            .map_err(|e| MetadataError::CacheError(format!("Deserialization failed: {}", e)))
+    }
+
     pub fn new(
         functions: Vec<Function>,
         enums: HashMap<String, Vec<String>>,
@@ -1056,7 +2434,7 @@ impl MetadataManager {
         MetadataCache::new(functions, self.all_enums(), self.all_events())
     }
 
-    pub fn import_cache(&self, cache: MetadataCache) -> Result<()> {
+    fn check_cache_version(cache: &MetadataCache) -> Result<()> {
         if cache.version != MetadataCache::VERSION {
             return Err(MetadataError::CacheError(format!(
                 "Incompatible cache version: expected {}, got {}",
@@ -1064,6 +2442,75 @@ impl MetadataManager {
                 cache.version
             )));
         }
+        Ok(())
+    }
+
+    /// Add the contents of `cache` on top of the existing metadata, without
+    /// clearing it first.
+    ///
+    /// On name collisions the entry from `cache` wins, including over
+    /// functions added with `add_custom_functions_from_json`.
+    pub fn merge(&self, cache: MetadataCache) -> Result<()> {
+        Self::check_cache_version(&cache)?;
+        {
+            let mut trie = self.trie.write().unwrap();
+            for func in cache.functions {
+                for key in register_function(&mut trie, func) {
+                    self.custom_function_names.remove(&key);
+                }
+            }
+        }
+        for (name, values) in cache.enums {
+            self.enums.insert(name, values);
+        }
+        for event in cache.events {
+            self.events.insert(event.name.clone(), event);
+        }
+        self.invalidate_caches();
+        Ok(())
+    }
+
+    /// Copy every function, enum and event of `other` into this manager.
+    ///
+    /// On name collisions the entry from `other` wins. Functions `other`
+    /// registered as custom stay custom here.
+    pub fn merge_from(&self, other: &MetadataManager) {
+        if std::ptr::eq(self, other) {
+            return;
+        }
+
+        // Lock both tries in address order, so that merges running the other
+        // way at the same time cannot deadlock
+        let (mut trie, other_trie);
+        if std::ptr::from_ref(self) < std::ptr::from_ref(other) {
+            trie = self.trie.write().unwrap();
+            other_trie = other.trie.read().unwrap();
+        } else {
+            other_trie = other.trie.read().unwrap();
+            trie = self.trie.write().unwrap();
+        }
+        trie.merge(&other_trie, true);
+        drop(trie);
+        other_trie.for_each(|func| {
+            if other.custom_function_names.contains_key(&func.name) {
+                self.custom_function_names.insert(func.name.clone(), ());
+            } else {
+                self.custom_function_names.remove(&func.name);
+            }
+        });
+        drop(other_trie);
+        other.for_each_enum(|name, values| {
+            self.enums.insert(name.to_string(), values.to_vec());
+        });
+        for entry in other.events.iter() {
+            self.events
+                .insert(entry.key().clone(), entry.value().clone());
+        }
+        self.invalidate_caches();
+    }
+
+    pub fn import_cache(&self, cache: MetadataCache) -> Result<()> {
+        Self::check_cache_version(&cache)?;
         self.clear();
         self.add_functions(cache.functions);
         for (name, values) in cache.enums {
@@ -1072,6 +2519,7 @@ impl MetadataManager {
         for event in cache.events {
             self.events.insert(event.name.clone(), event);
         }
+        self.invalidate_caches();
         Ok(())
     }
 
@@ -1081,9 +2529,7 @@ impl MetadataManager {
     }
 
     pub fn cache_from_json(&self, json: &str) -> Result<()> {
-        let cache: MetadataCache = serde_json::from_str(json)
-            .map_err(|e| MetadataError::CacheError(format!("Deserialization failed: {}", e)))?;
-        self.import_cache(cache)
+        self.import_cache(MetadataCache::migrate(json)?)
     }
 }
 
@@ -1104,6 +2550,26 @@ impl MetadataManager {
             .map_err(|e| MetadataError::CacheError(format!("Failed to read file: {}", e)))?;
         self.cache_from_json(&json)
     }
+
+    /// Fetch all sources like [`fetch_all`](Self::fetch_all), falling back to
+    /// the cache saved at `cache_path` when every source failed.
+    ///
+    /// The cache is only loaded when at least one file failed and nothing was
+    /// fetched; partially successful fetches keep the fresh data. On fallback
+    /// the returned stats keep the fetch errors and set `from_cache`, and an
+    /// unreadable cache is returned as an error.
+    pub async fn fetch_all_or_cache(
+        &self,
+        cache_path: impl AsRef<std::path::Path>,
+    ) -> Result<FetchStats> {
+        let mut stats = self.fetch_all().await?;
+        let fetched_nothing = stats.functions == 0 && stats.enums == 0 && stats.events == 0;
+        if fetched_nothing && !stats.errors.is_empty() {
+            self.load_cache_from_file(cache_path)?;
+            stats.from_cache = true;
+        }
+        Ok(stats)
+    }
 }
 
 // ============================================================================
@@ -3,6 +3,8 @@
 //! This module provides a fast, single-pass parser that builds a proper Abstract Syntax Tree
 //! with extensive optimizations for speed and memory efficiency, plus optional validation.
 
+use crate::metadata::dollar_prefixed;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 // Optional validation support
@@ -10,7 +12,6 @@ use smallvec::SmallVec;
 use crate::metadata::MetadataManager;
 #[cfg(feature = "validation")]
 use crate::types::{Arg, Function};
-#[cfg(feature = "validation")]
 use std::sync::Arc;
 
 // ============================================================================
@@ -76,6 +77,38 @@ pub struct ValidationConfig {
     pub validate_functions: bool,
     /// Validate bracket usage (required/optional/forbidden)
     pub validate_brackets: bool,
+    /// Warn when an `unwrap` function is passed where a concrete value is
+    /// expected (opt-in lint, not enabled by [`ValidationConfig::strict`])
+    pub validate_unwrap_usage: bool,
+    /// Warn on repeated `!`/`#` modifiers and extra `@[n]` counts (opt-in
+    /// lint, not enabled by [`ValidationConfig::strict`]). Also checks that
+    /// counts are positive integers or expressions, which `validate_arguments`
+    /// enables as well.
+    pub validate_modifiers: bool,
+    /// Warn on unescaped `[`, `]` and `;` in text outside function calls
+    /// (opt-in lint, not enabled by [`ValidationConfig::strict`])
+    pub strict_text: bool,
+    /// Report unmatched block openers and closers such as `$if`/`$endif`
+    /// (opt-in lint, not enabled by [`ValidationConfig::strict`])
+    pub validate_blocks: bool,
+    /// Report every `${...}` expression, for environments that do not allow
+    /// embedded JavaScript (not enabled by [`ValidationConfig::strict`])
+    pub forbid_javascript: bool,
+    /// Report calls to functions marked deprecated in metadata as errors
+    /// (not enabled by [`ValidationConfig::strict`])
+    pub deny_deprecated: bool,
+    /// Warn on calls to functions marked experimental in metadata
+    /// (not enabled by [`ValidationConfig::strict`])
+    pub warn_experimental: bool,
+    /// Treat enum values differing only in case as invalid. Off by default,
+    /// matching ForgeScript at runtime: such values only get a warning naming
+    /// the canonical casing.
+    pub enum_case_sensitive: bool,
+    /// Stop parsing once this many errors (not warnings) were reported and
+    /// return the tree built so far, for fail-fast checks. Only complete nodes
+    /// are kept and later diagnostics are dropped; `Some(0)` stops at the first
+    /// error like `Some(1)`, and `None` parses everything.
+    pub max_errors: Option<usize>,
 }
 
 impl ValidationConfig {
@@ -86,6 +119,15 @@ impl ValidationConfig {
             validate_enums: true,
             validate_functions: true,
             validate_brackets: true,
+            validate_unwrap_usage: false,
+            validate_modifiers: false,
+            strict_text: false,
+            validate_blocks: false,
+            forbid_javascript: false,
+            deny_deprecated: false,
+            warn_experimental: false,
+            enum_case_sensitive: false,
+            max_errors: None,
         }
     }
 
@@ -96,6 +138,15 @@ impl ValidationConfig {
             validate_enums: false,
             validate_functions: false,
             validate_brackets: true,
+            validate_unwrap_usage: false,
+            validate_modifiers: false,
+            strict_text: false,
+            validate_blocks: false,
+            forbid_javascript: false,
+            deny_deprecated: false,
+            warn_experimental: false,
+            enum_case_sensitive: false,
+            max_errors: None,
         }
     }
 
@@ -106,6 +157,13 @@ impl ValidationConfig {
             || self.validate_enums
             || self.validate_functions
             || self.validate_brackets
+            || self.validate_unwrap_usage
+            || self.validate_modifiers
+            || self.strict_text
+            || self.validate_blocks
+            || self.forbid_javascript
+            || self.deny_deprecated
+            || self.warn_experimental
     }
 }
 
@@ -113,7 +171,7 @@ impl ValidationConfig {
 // AST Node Definitions
 // ============================================================================
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -140,9 +198,17 @@ impl Span {
     pub fn is_empty(&self) -> bool {
         self.start >= self.end
     }
+
+    /// Start and end as UTF-16 code unit offsets into `source`
+    pub fn to_utf16(&self, source: &str) -> (usize, usize) {
+        (
+            crate::utils::utf8_to_utf16_offset(source, self.start),
+            crate::utils::utf8_to_utf16_offset(source, self.end),
+        )
+    }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Modifiers {
     pub silent: bool,
     pub negated: bool,
@@ -150,9 +216,31 @@ pub struct Modifiers {
     /// Span covering all modifier characters (e.g. `!#@[n]` before the name).
     /// `None` if no modifiers were present.
     pub span: Option<Span>,
+    /// Span of the silent `!` modifier
+    pub silent_span: Option<Span>,
+    /// Span of the negate `#` modifier
+    pub negated_span: Option<Span>,
+    /// Span of the `@[count]` modifier, including `@` and brackets
+    pub count_span: Option<Span>,
 }
 
-#[derive(Debug, Clone)]
+impl Modifiers {
+    fn offset_spans(&mut self, offset: usize) {
+        for span in [
+            &mut self.span,
+            &mut self.silent_span,
+            &mut self.negated_span,
+            &mut self.count_span,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            span.offset(offset);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Argument {
     pub parts: SmallVec<[AstNode; 4]>,
     pub span: Span,
@@ -168,11 +256,13 @@ impl Argument {
     }
 
     /// Get literal text value if argument is purely text
+    ///
+    /// Escapes are already resolved by the parser, so `\\;` yields `;`.
     pub fn as_text(&self) -> Option<String> {
-        if self.parts.len() == 1 {
-            if let AstNode::Text { content, .. } = &self.parts[0] {
-                return Some(content.clone());
-            }
+        if self.parts.len() == 1
+            && let AstNode::Text { content, .. } = &self.parts[0]
+        {
+            return Some(content.clone());
         }
 
         // Try to concatenate if all parts are text
@@ -180,7 +270,7 @@ impl Argument {
             let mut result = String::new();
             for part in &self.parts {
                 if let AstNode::Text { content, .. } = part {
-                    result.push_str(&content);
+                    result.push_str(content);
                 }
             }
             return Some(result);
@@ -190,19 +280,27 @@ impl Argument {
     }
 }
 
-#[derive(Debug, Clone)]
+// Function calls dominate real scripts, so boxing their fields would add an
+// allocation per call for little gain.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum AstNode {
     Program {
         body: Vec<AstNode>,
         span: Span,
     },
     Text {
+        /// The text with escape sequences resolved
         content: String,
+        /// Source range the text was read from. For escape sequences this is
+        /// the whole sequence (`\\` gives content `\` over two bytes), so
+        /// `source[span]` may differ from `content`; see [`AstNode::source_text`].
         span: Span,
     },
     FunctionCall {
         name: String,
-        /// Span of the function name identifier including any modifier characters (excludes `$`).
+        /// Span from `$` through the function name, including any modifier characters.
         name_span: Span,
         /// Span of the modifier characters between `$` and the name (e.g. `!#@[2]`).
         /// `None` when no modifiers are present.
@@ -210,6 +308,17 @@ pub enum AstNode {
         /// Span of the argument list including the surrounding `[` and `]`.
         /// `None` when the function was called without brackets.
         args_span: Option<Span>,
+        /// Span of the opening `[`. `None` when the function was called without brackets.
+        open_bracket: Option<Span>,
+        /// Span of the matching `]`. `None` without brackets or when the
+        /// argument list is unclosed.
+        close_bracket: Option<Span>,
+        /// Spans of the top-level `;` separating the arguments, one fewer
+        /// than there are arguments. Empty without parsed arguments.
+        separators: Vec<Span>,
+        /// Parsed arguments. `None` without brackets; empty brackets (`$f[]`)
+        /// give zero arguments, while any separator makes every slot count
+        /// (`$f[;]` has two empty arguments).
         args: Option<Vec<Argument>>,
         modifiers: Modifiers,
         /// Full span from the start of modifiers to the closing `]` (or end of name when no args).
@@ -217,6 +326,12 @@ pub enum AstNode {
         full_span: Span,
         /// Full span from `$` to the closing `]` (or end of name when no args).
         span: Span,
+        /// Whether the call matched a function in metadata. Only set when
+        /// parsing validates against metadata (requires "validation" feature).
+        resolved: bool,
+        /// Extension of the matched function, `None` when unresolved or when
+        /// the function does not declare one
+        resolved_extension: Option<String>,
     },
     JavaScript {
         code: String,
@@ -224,6 +339,9 @@ pub enum AstNode {
     },
     Escaped {
         content: String,
+        /// Name of the escape function that produced the node, without the
+        /// leading `$` and in its source casing (e.g. `c` or `escape`)
+        function: String,
         span: Span,
     },
 }
@@ -239,6 +357,30 @@ impl AstNode {
         }
     }
 
+    /// The source bytes this node was parsed from, before escape resolution.
+    ///
+    /// `source` must be the text that was parsed.
+    #[inline]
+    pub fn source_text<'a>(&self, source: &'a str) -> &'a str {
+        let span = self.span();
+        &source[span.start..span.end]
+    }
+
+    /// The name of a `FunctionCall` node with its `$` prefix (e.g. `$get`),
+    /// or `None` for any other node
+    pub fn function_full_name(&self) -> Option<String> {
+        match self {
+            AstNode::FunctionCall { name, .. } => Some(dollar_prefixed(name)),
+            _ => None,
+        }
+    }
+
+    /// Check whether this is a `Text` node produced by an escape sequence,
+    /// i.e. whose content differs from its source text
+    pub fn is_escape_text(&self, source: &str) -> bool {
+        matches!(self, AstNode::Text { content, .. } if content != self.source_text(source))
+    }
+
     pub fn offset_spans(&mut self, offset: usize) {
         match self {
             AstNode::Program { body, span } => {
@@ -254,22 +396,36 @@ impl AstNode {
             }
             AstNode::FunctionCall {
                 args,
+                modifiers,
                 span,
                 name_span,
                 modifier_span,
                 args_span,
+                open_bracket,
+                close_bracket,
+                separators,
                 full_span,
                 ..
             } => {
                 span.offset(offset);
+                modifiers.offset_spans(offset);
                 name_span.offset(offset);
                 full_span.offset(offset);
+                for separator in separators {
+                    separator.offset(offset);
+                }
                 if let Some(ms) = modifier_span {
                     ms.offset(offset);
                 }
                 if let Some(as_) = args_span {
                     as_.offset(offset);
                 }
+                if let Some(ob) = open_bracket {
+                    ob.offset(offset);
+                }
+                if let Some(cb) = close_bracket {
+                    cb.offset(offset);
+                }
                 if let Some(args) = args {
                     for arg in args {
                         arg.span.offset(offset);
@@ -283,24 +439,228 @@ impl AstNode {
     }
 }
 
+// ============================================================================
+// Borrowed AST
+// ============================================================================
+
+/// An argument of a [`BorrowedAstNode::FunctionCall`]
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowedArgument<'src> {
+    pub parts: SmallVec<[BorrowedAstNode<'src>; 4]>,
+    pub span: Span,
+}
+
+/// Zero-copy counterpart of [`AstNode`] whose text borrows from the source.
+///
+/// Produced by [`parse_borrowed`] for read-only analyses. Every field has the
+/// same meaning as in [`AstNode`]; resolved escape sequences borrow the
+/// character they stand for, so no variant ever owns its text. Calls are never
+/// resolved in metadata, so function calls have no `resolved` fields and
+/// [`BorrowedAstNode::into_owned`] leaves them unresolved.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum BorrowedAstNode<'src> {
+    Program {
+        body: Vec<BorrowedAstNode<'src>>,
+        span: Span,
+    },
+    Text {
+        content: &'src str,
+        span: Span,
+    },
+    FunctionCall {
+        name: &'src str,
+        name_span: Span,
+        modifier_span: Option<Span>,
+        args_span: Option<Span>,
+        open_bracket: Option<Span>,
+        close_bracket: Option<Span>,
+        separators: Vec<Span>,
+        args: Option<Vec<BorrowedArgument<'src>>>,
+        modifiers: Modifiers,
+        full_span: Span,
+        span: Span,
+    },
+    JavaScript {
+        code: &'src str,
+        span: Span,
+    },
+    Escaped {
+        content: &'src str,
+        function: &'src str,
+        span: Span,
+    },
+}
+
+impl BorrowedAstNode<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            BorrowedAstNode::Program { span, .. }
+            | BorrowedAstNode::Text { span, .. }
+            | BorrowedAstNode::FunctionCall { span, .. }
+            | BorrowedAstNode::JavaScript { span, .. }
+            | BorrowedAstNode::Escaped { span, .. } => *span,
+        }
+    }
+
+    pub fn offset_spans(&mut self, offset: usize) {
+        match self {
+            BorrowedAstNode::Program { body, span } => {
+                span.offset(offset);
+                for node in body {
+                    node.offset_spans(offset);
+                }
+            }
+            BorrowedAstNode::Text { span, .. }
+            | BorrowedAstNode::JavaScript { span, .. }
+            | BorrowedAstNode::Escaped { span, .. } => {
+                span.offset(offset);
+            }
+            BorrowedAstNode::FunctionCall {
+                args,
+                modifiers,
+                span,
+                name_span,
+                modifier_span,
+                args_span,
+                open_bracket,
+                close_bracket,
+                separators,
+                full_span,
+                ..
+            } => {
+                span.offset(offset);
+                modifiers.offset_spans(offset);
+                name_span.offset(offset);
+                full_span.offset(offset);
+                for separator in separators {
+                    separator.offset(offset);
+                }
+                for s in [modifier_span, args_span, open_bracket, close_bracket]
+                    .into_iter()
+                    .flatten()
+                {
+                    s.offset(offset);
+                }
+                for arg in args.iter_mut().flatten() {
+                    arg.span.offset(offset);
+                    for part in &mut arg.parts {
+                        part.offset_spans(offset);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Copy the borrowed text into an owned [`AstNode`]
+    pub fn into_owned(self) -> AstNode {
+        match self {
+            BorrowedAstNode::Program { body, span } => AstNode::Program {
+                body: body.into_iter().map(BorrowedAstNode::into_owned).collect(),
+                span,
+            },
+            BorrowedAstNode::Text { content, span } => AstNode::Text {
+                content: content.to_string(),
+                span,
+            },
+            BorrowedAstNode::FunctionCall {
+                name,
+                name_span,
+                modifier_span,
+                args_span,
+                open_bracket,
+                close_bracket,
+                separators,
+                args,
+                modifiers,
+                full_span,
+                span,
+            } => AstNode::FunctionCall {
+                name: name.to_string(),
+                name_span,
+                modifier_span,
+                args_span,
+                open_bracket,
+                close_bracket,
+                separators,
+                args: args.map(|args| {
+                    args.into_iter()
+                        .map(|arg| Argument {
+                            parts: arg
+                                .parts
+                                .into_iter()
+                                .map(BorrowedAstNode::into_owned)
+                                .collect(),
+                            span: arg.span,
+                        })
+                        .collect()
+                }),
+                modifiers,
+                full_span,
+                span,
+                resolved: false,
+                resolved_extension: None,
+            },
+            BorrowedAstNode::JavaScript { code, span } => AstNode::JavaScript {
+                code: code.to_string(),
+                span,
+            },
+            BorrowedAstNode::Escaped {
+                content,
+                function,
+                span,
+            } => AstNode::Escaped {
+                content: content.to_string(),
+                function: function.to_string(),
+                span,
+            },
+        }
+    }
+}
+
 // ============================================================================
 // Parse Errors
 // ============================================================================
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum ErrorKind {
     Syntax,
+    /// A `${` without its closing `}`
+    UnclosedJavaScript,
+    /// A `[` without its closing `]`
+    UnclosedBracket,
     ArgumentCount,
     EnumValue,
     UnknownFunction,
     BracketUsage,
+    UnwrapUsage,
+    MixedIndentation,
+    DuplicateModifier,
+    ArgumentOrder,
+    ArgumentPointer,
+    UnescapedText,
+    BlockBalance,
+    ForbiddenJavaScript,
+    Deprecated,
+    Experimental,
+    Unreachable,
 }
 
-#[derive(Debug, Clone)]
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseError {
     pub message: String,
     pub span: Span,
     pub kind: ErrorKind,
+    pub severity: Severity,
 }
 
 impl ParseError {
@@ -310,15 +670,73 @@ impl ParseError {
             message: message.into(),
             span,
             kind,
+            severity: Severity::Error,
+        }
+    }
+
+    /// Create a diagnostic with [`Severity::Warning`]
+    #[inline]
+    pub fn warning(message: impl Into<String>, span: Span, kind: ErrorKind) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(message, span, kind)
         }
     }
 
+    #[inline]
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+
     #[inline]
     pub fn syntax(message: impl Into<String>, span: Span) -> Self {
         Self::new(message, span, ErrorKind::Syntax)
     }
 }
 
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Formats as `error[Kind]: message (start..end)`
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}[{:?}]: {} ({}..{})",
+            self.severity, self.kind, self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// ============================================================================
+// Parse Events
+// ============================================================================
+
+/// Event emitted by [`parse_events`] while scanning the source.
+///
+/// Events are produced in the same depth-first order in which [`AstVisitor`]
+/// would visit the tree built by [`parse`]; every `EnterFunction` is paired
+/// with a matching `ExitFunction` once all of its arguments have been emitted.
+///
+/// [`AstVisitor`]: crate::visitor::AstVisitor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent {
+    EnterFunction { name: String, span: Span },
+    ExitFunction,
+    Text { span: Span },
+    JavaScript { span: Span },
+    Escaped { span: Span },
+    Error(ParseError),
+}
+
 // ============================================================================
 // Enum Validation Exemptions
 // ============================================================================
@@ -331,16 +749,225 @@ const ENUM_ACCEPTS: &[(&str, usize)] = &[("$color", 0), ("$modifyChannelPerms",
 // Parser
 // ============================================================================
 
+/// A parsed bracketed argument list
+struct ArgumentList<A> {
+    args: Vec<A>,
+    /// Spans of the `;` between arguments
+    separators: Vec<Span>,
+    close_bracket: Span,
+}
+
+/// Spans of the `;` ending every argument range but the last, shifted by `base`
+fn separator_spans(ranges: &[Span], base: usize) -> Vec<Span> {
+    let last = ranges.len().saturating_sub(1);
+    ranges[..last]
+        .iter()
+        .map(|range| Span::new(base + range.end, base + range.end + 1))
+        .collect()
+}
+
+/// Keep the ASCII characters of `delimiters` as bytes
+fn collect_delimiters(delimiters: impl IntoIterator<Item = char>) -> Vec<u8> {
+    delimiters
+        .into_iter()
+        .filter(char::is_ascii)
+        .map(|c| c as u8)
+        .collect()
+}
+
+/// Keys that introduce a code block when none are configured
+pub const DEFAULT_BLOCK_KEYS: &[&str] = &["code"];
+
+/// Characters that delimit a code block when none are configured
+pub const DEFAULT_BLOCK_DELIMITERS: &[char] = &['`'];
+
+/// Functions whose bracket content is kept raw when none are configured
+pub const DEFAULT_ESCAPE_FUNCTIONS: &[&str] = &["c", "C", "escape"];
+
+/// Opener/closer function pairs checked by `validate_blocks` when none are configured
+pub const DEFAULT_BLOCK_PAIRS: &[(&str, &str)] = &[("if", "endif"), ("try", "endtry")];
+
 pub struct Parser<'src> {
     source: &'src str,
     bytes: &'src [u8],
     pos: usize,
     errors: Vec<ParseError>,
+    /// Errors (not warnings) reported so far, including those already
+    /// handed to a parent or event sink
+    error_count: usize,
+    /// Length of the `errors` prefix included in `error_count`
+    errors_counted: usize,
     config: ValidationConfig,
+    /// Byte offset after which no new top-level node is started
+    limit: usize,
+    /// Keys introducing a code block (`<key>: \``); `None` uses [`DEFAULT_BLOCK_KEYS`]
+    block_keys: Option<Vec<String>>,
+    /// Characters that may delimit a code block; `None` uses [`DEFAULT_BLOCK_DELIMITERS`]
+    block_delimiters: Option<Vec<u8>>,
+    /// Delimiter of the code block this parser reads, escapable with `\`
+    delimiter: u8,
+    /// Raw-text escape functions; `None` uses [`DEFAULT_ESCAPE_FUNCTIONS`]
+    escape_functions: Option<Arc<[String]>>,
+    /// Escape functions that may be used without brackets; `None` requires
+    /// brackets for all of them
+    bare_escape_functions: Option<Arc<[String]>>,
+    /// Opener/closer block functions; `None` uses [`DEFAULT_BLOCK_PAIRS`]
+    block_pairs: Option<Arc<[(String, String)]>>,
+    /// Whether `$$` is read as a literal `$`
+    double_dollar: bool,
+    /// Function-argument nesting level of this parser's source
+    depth: usize,
+    /// Deepest argument nesting that is parsed; `None` means unlimited
+    max_depth: Option<usize>,
     #[cfg(feature = "validation")]
     metadata: Option<Arc<MetadataManager>>,
 }
 
+/// Extent of a region whose content cannot end a code block
+enum OpaqueRegion {
+    /// The region's closing delimiter is at this offset
+    EndsAt(usize),
+    /// The region runs to the end of the input
+    Unclosed,
+}
+
+/// Fields of a parsed function call, shared by both trees
+struct CallParts<'src, A> {
+    name: &'src str,
+    name_span: Span,
+    modifier_span: Option<Span>,
+    args_span: Option<Span>,
+    open_bracket: Option<Span>,
+    close_bracket: Option<Span>,
+    separators: Vec<Span>,
+    args: Option<Vec<A>>,
+    modifiers: Modifiers,
+    full_span: Span,
+    span: Span,
+}
+
+/// The kind of tree a parse builds. Scanning is shared; only node
+/// construction differs, and the owned tree also resolves calls in metadata.
+trait TreeBuilder<'src> {
+    type Node;
+    type Argument;
+
+    /// Convert a scanned leaf (text, escape, JavaScript) into a node
+    fn leaf(node: BorrowedAstNode<'src>) -> Self::Node;
+
+    fn argument(parts: SmallVec<[Self::Node; 4]>, span: Span) -> Self::Argument;
+
+    fn function_call(
+        parser: &mut Parser<'src>,
+        call: CallParts<'src, Self::Argument>,
+    ) -> Self::Node;
+
+    fn offset_spans(node: &mut Self::Node, offset: usize);
+
+    /// Checks over the top-level nodes of one code block
+    fn finish_block(_parser: &mut Parser<'src>, _body: &[Self::Node]) {}
+}
+
+/// Builds [`AstNode`]s, validating calls against metadata
+struct OwnedTree;
+
+impl<'src> TreeBuilder<'src> for OwnedTree {
+    type Node = AstNode;
+    type Argument = Argument;
+
+    fn leaf(node: BorrowedAstNode<'src>) -> AstNode {
+        node.into_owned()
+    }
+
+    fn argument(parts: SmallVec<[AstNode; 4]>, span: Span) -> Argument {
+        Argument { parts, span }
+    }
+
+    fn function_call(parser: &mut Parser<'src>, call: CallParts<'src, Argument>) -> AstNode {
+        // Validate with metadata if available
+        #[cfg(feature = "validation")]
+        let (resolved, resolved_extension) = match parser.resolve_function_call(
+            call.name,
+            call.args.as_ref(),
+            call.open_bracket.is_some(),
+            call.name_span,
+        ) {
+            Some(func) => (true, func.extension.clone()),
+            None => (false, None),
+        };
+        #[cfg(not(feature = "validation"))]
+        let (resolved, resolved_extension) = {
+            let _ = parser;
+            (false, None)
+        };
+
+        AstNode::FunctionCall {
+            name: call.name.to_string(),
+            name_span: call.name_span,
+            modifier_span: call.modifier_span,
+            args_span: call.args_span,
+            open_bracket: call.open_bracket,
+            close_bracket: call.close_bracket,
+            separators: call.separators,
+            args: call.args,
+            modifiers: call.modifiers,
+            full_span: call.full_span,
+            span: call.span,
+            resolved,
+            resolved_extension,
+        }
+    }
+
+    fn offset_spans(node: &mut AstNode, offset: usize) {
+        node.offset_spans(offset);
+    }
+
+    fn finish_block(parser: &mut Parser<'src>, body: &[AstNode]) {
+        if parser.config.validate_blocks {
+            parser.check_block_balance(body);
+        }
+    }
+}
+
+/// Builds [`BorrowedAstNode`]s without metadata validation
+struct BorrowedTree;
+
+impl<'src> TreeBuilder<'src> for BorrowedTree {
+    type Node = BorrowedAstNode<'src>;
+    type Argument = BorrowedArgument<'src>;
+
+    fn leaf(node: BorrowedAstNode<'src>) -> BorrowedAstNode<'src> {
+        node
+    }
+
+    fn argument(parts: SmallVec<[BorrowedAstNode<'src>; 4]>, span: Span) -> BorrowedArgument<'src> {
+        BorrowedArgument { parts, span }
+    }
+
+    fn function_call(
+        _parser: &mut Parser<'src>,
+        call: CallParts<'src, BorrowedArgument<'src>>,
+    ) -> BorrowedAstNode<'src> {
+        BorrowedAstNode::FunctionCall {
+            name: call.name,
+            name_span: call.name_span,
+            modifier_span: call.modifier_span,
+            args_span: call.args_span,
+            open_bracket: call.open_bracket,
+            close_bracket: call.close_bracket,
+            separators: call.separators,
+            args: call.args,
+            modifiers: call.modifiers,
+            full_span: call.full_span,
+            span: call.span,
+        }
+    }
+
+    fn offset_spans(node: &mut BorrowedAstNode<'src>, offset: usize) {
+        node.offset_spans(offset);
+    }
+}
+
 impl<'src> Parser<'src> {
     #[inline]
     pub fn new(source: &'src str) -> Self {
@@ -349,12 +976,30 @@ impl<'src> Parser<'src> {
             bytes: source.as_bytes(),
             pos: 0,
             errors: Vec::new(),
+            error_count: 0,
+            errors_counted: 0,
             config: ValidationConfig::default(),
+            limit: usize::MAX,
+            block_keys: None,
+            block_delimiters: None,
+            delimiter: b'`',
+            escape_functions: None,
+            bare_escape_functions: None,
+            block_pairs: None,
+            double_dollar: false,
+            depth: 0,
+            max_depth: None,
             #[cfg(feature = "validation")]
             metadata: None,
         }
     }
 
+    /// Start configuring a parser with a [`ParserBuilder`]
+    #[inline]
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::new()
+    }
+
     /// Create parser with validation configuration (requires "validation" feature)
     #[cfg(feature = "validation")]
     #[inline]
@@ -364,7 +1009,19 @@ impl<'src> Parser<'src> {
             bytes: source.as_bytes(),
             pos: 0,
             errors: Vec::new(),
+            error_count: 0,
+            errors_counted: 0,
             config,
+            limit: usize::MAX,
+            block_keys: None,
+            block_delimiters: None,
+            delimiter: b'`',
+            escape_functions: None,
+            bare_escape_functions: None,
+            block_pairs: None,
+            double_dollar: false,
+            depth: 0,
+            max_depth: None,
             metadata: None,
         }
     }
@@ -382,119 +1039,546 @@ impl<'src> Parser<'src> {
             bytes: source.as_bytes(),
             pos: 0,
             errors: Vec::new(),
+            error_count: 0,
+            errors_counted: 0,
             config,
+            limit: usize::MAX,
+            block_keys: None,
+            block_delimiters: None,
+            delimiter: b'`',
+            escape_functions: None,
+            bare_escape_functions: None,
+            block_pairs: None,
+            double_dollar: false,
+            depth: 0,
+            max_depth: None,
             metadata: Some(metadata),
         }
     }
 
+    /// Recognise code blocks introduced by any of `keys` (e.g. `reply` for
+    /// `reply: \`...\``) instead of the default `code`
+    pub fn with_block_keys<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.block_keys = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Recognise code blocks delimited by any of `delimiters` instead of only
+    /// backticks, e.g. `['`', '"', '\'']` to also accept `code: "..."` and
+    /// `code: '...'`. Non-ASCII characters are ignored.
+    ///
+    /// Inside a block, `\` followed by its delimiter is a literal delimiter.
+    pub fn with_block_delimiters<I>(mut self, delimiters: I) -> Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.block_delimiters = Some(collect_delimiters(delimiters));
+        self
+    }
+
+    /// Treat calls to any of `names` (e.g. `raw`) as escape functions whose
+    /// bracket content is kept as raw text, instead of the default `c`/`C`/`escape`
+    pub fn with_escape_functions<I, K>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.escape_functions = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Allow the escape functions in `names` (e.g. `c`) to be used without
+    /// brackets. A bare call is then kept as plain text without reporting a
+    /// bracket error; the others still require brackets.
+    pub fn with_bare_escape_functions<I, K>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.bare_escape_functions = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check `opener`/`closer` function pairs (e.g. `("if", "endif")`) when
+    /// `validate_blocks` is enabled, instead of the default pairs
+    pub fn with_block_pairs<I, O, C>(mut self, pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (O, C)>,
+        O: Into<String>,
+        C: Into<String>,
+    {
+        self.block_pairs = Some(
+            pairs
+                .into_iter()
+                .map(|(opener, closer)| (opener.into(), closer.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Read `$$` as a literal `$` instead of a `$` followed by a function call.
+    ///
+    /// Off by default since it changes how existing scripts tokenize. The
+    /// backslash escape takes precedence, so `\\$$f` is a literal `$`
+    /// followed by a call to `$f`.
+    pub fn with_double_dollar_escape(mut self, enabled: bool) -> Self {
+        self.double_dollar = enabled;
+        self
+    }
+
+    /// Parse function arguments nested at most `max_depth` deep. Deeper
+    /// arguments are kept as raw text and reported as a syntax error.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Create a parser for one argument of a call, one nesting level deeper.
+    ///
+    /// Returns `None` after reporting an error when that would exceed the
+    /// configured maximum depth.
+    fn argument_child<'a>(&mut self, content: &'a str, offset: usize) -> Option<Parser<'a>> {
+        let depth = self.depth + 1;
+        if let Some(max_depth) = self.max_depth
+            && depth > max_depth
+        {
+            self.errors.push(ParseError::syntax(
+                format!("Maximum nesting depth of {} exceeded", max_depth),
+                Span::new(offset, offset + content.len()),
+            ));
+            return None;
+        }
+        let mut child = self.child(content);
+        child.depth = depth;
+        Some(child)
+    }
+
+    /// Whether a `$$` escape starts at `p`
+    #[inline]
+    fn is_double_dollar(&self, bytes: &[u8], p: usize) -> bool {
+        self.double_dollar && bytes[p] == b'$' && bytes.get(p + 1) == Some(&b'$')
+    }
+
+    /// Create a parser for a nested slice that inherits this parser's settings
+    fn child<'a>(&self, source: &'a str) -> Parser<'a> {
+        let mut child = Parser::new(source);
+        child.config = self.config.clone();
+        // Children share the error budget with everything reported so far
+        child.error_count = self.error_count();
+        child.delimiter = self.delimiter;
+        child.escape_functions = self.escape_functions.clone();
+        child.bare_escape_functions = self.bare_escape_functions.clone();
+        child.block_pairs = self.block_pairs.clone();
+        child.double_dollar = self.double_dollar;
+        child.depth = self.depth;
+        child.max_depth = self.max_depth;
+        #[cfg(feature = "validation")]
+        {
+            child.metadata = self.metadata.clone();
+        }
+        child
+    }
+
+    /// Parse only up to byte `offset`: nodes starting after `offset` are left
+    /// unparsed, while the node spanning `offset` is parsed in full.
+    ///
+    /// Useful for editors that only need the context around the cursor.
+    pub fn parse_until(mut self, offset: usize) -> (AstNode, Vec<ParseError>) {
+        self.limit = offset;
+        self.parse()
+    }
+
     pub fn parse(mut self) -> (AstNode, Vec<ParseError>) {
+        let (body, span) = self.parse_program::<OwnedTree>();
+        (AstNode::Program { body, span }, self.errors)
+    }
+
+    /// Split the source into text and code blocks, parsing each block's
+    /// content into nodes of `T`
+    fn parse_program<T: TreeBuilder<'src>>(&mut self) -> (Vec<T::Node>, Span) {
         let start = self.pos;
         let mut body = Vec::new();
 
-        while !self.is_eof() {
+        while !self.is_eof() && self.pos <= self.limit && !self.error_limit_reached() {
             // Find start of "code: `" block
-            if let Some(block_start) = self.find_code_block_start() {
+            if let Some((block_start, content_start)) = self.find_code_block_start() {
                 // Add text before block
                 if block_start > self.pos {
-                    body.push(AstNode::Text {
-                        content: self.slice(self.pos, block_start).to_string(),
+                    body.push(T::leaf(BorrowedAstNode::Text {
+                        content: self.slice(self.pos, block_start),
                         span: Span::new(self.pos, block_start),
-                    });
+                    }));
+                }
+
+                // Leave blocks past the limit unparsed
+                if block_start > self.limit {
+                    self.pos = block_start;
+                    break;
                 }
 
                 // Move pos to start of content (after "code: `")
-                let content_start = block_start + 7; // len("code: `")
                 self.pos = content_start;
+                let delimiter = self.bytes[content_start - 1];
 
-                // Find end of block (unescaped `)
-                if let Some(block_end) = self.find_code_block_end() {
-                    let content_len = block_end - content_start;
-
-                    if content_len > 0 {
+                // Find end of block (unescaped delimiter)
+                if let Some(block_end) = self.find_code_block_end(delimiter) {
+                    if block_end > content_start && content_start <= self.limit {
                         // Parse content inside block
-                        let inner_source = self.slice(content_start, block_end);
-
-                        #[cfg(feature = "validation")]
-                        let inner_parser = if self.config.is_enabled() {
-                            if let Some(ref metadata) = self.metadata {
-                                Parser::with_validation(
-                                    inner_source,
-                                    self.config.clone(),
-                                    metadata.clone(),
-                                )
-                            } else {
-                                Parser::with_config(inner_source, self.config.clone())
-                            }
-                        } else {
-                            Parser::new(inner_source)
-                        };
+                        let mut inner_parser = self.child(self.slice(content_start, block_end));
+                        inner_parser.delimiter = delimiter;
+                        inner_parser.limit = self.limit - content_start;
+                        let nodes = self.adopt_script::<T>(inner_parser, content_start);
+                        T::finish_block(self, &nodes);
+                        body.extend(nodes);
+                    }
 
-                        #[cfg(not(feature = "validation"))]
-                        let inner_parser = Parser::new(inner_source);
+                    // Move past closing backtick
+                    self.pos = block_end + 1;
+                } else {
+                    // Unclosed block
+                    if self.config.validate_brackets {
+                        self.errors.push(ParseError::syntax(
+                            "Unclosed code block",
+                            Span::new(block_start, self.source.len()),
+                        ));
+                    }
+                    body.push(T::leaf(BorrowedAstNode::Text {
+                        content: self.slice(block_start, self.source.len()),
+                        span: Span::new(block_start, self.source.len()),
+                    }));
+                    self.pos = self.source.len();
+                }
+            } else {
+                // No more blocks, rest is text
+                if self.pos < self.source.len() {
+                    body.push(T::leaf(BorrowedAstNode::Text {
+                        content: self.slice(self.pos, self.source.len()),
+                        span: Span::new(self.pos, self.source.len()),
+                    }));
+                }
+                self.pos = self.source.len();
+            }
+            self.enforce_error_budget();
+        }
 
-                        let (mut inner_ast, inner_errors) = inner_parser.parse_forge_script();
+        self.enforce_error_budget();
+        (body, Span::new(start, self.pos.min(self.source.len())))
+    }
 
-                        inner_ast.offset_spans(content_start);
+    fn parse_forge_script(mut self) -> (AstNode, Vec<ParseError>) {
+        let start = self.pos;
+        let body = self.parse_script::<OwnedTree>();
+        OwnedTree::finish_block(&mut self, &body);
+        let span = Span::new(start, self.pos.min(self.source.len()));
+        (AstNode::Program { body, span }, self.errors)
+    }
 
-                        match inner_ast {
-                            AstNode::Program {
-                                body: inner_body, ..
-                            } => {
-                                body.extend(inner_body);
-                            }
-                            _ => body.push(inner_ast),
-                        }
+    /// Parse ForgeScript (the content of a code block or argument) into
+    /// nodes of `T`
+    fn parse_script<T: TreeBuilder<'src>>(&mut self) -> Vec<T::Node> {
+        let mut body = Vec::new();
+        while !self.is_eof() && self.pos <= self.limit && !self.error_limit_reached() {
+            if self.at_function_call() {
+                body.push(self.parse_function_call::<T>());
+            } else if let Some(node) = self.parse_forge_leaf() {
+                body.push(T::leaf(node));
+            }
+            self.enforce_error_budget();
+        }
+        self.enforce_error_budget();
+        body
+    }
+
+    /// Parse the script of `child`, whose source starts at `offset`, keeping
+    /// its errors and returning its nodes with spans shifted into this source
+    fn adopt_script<T: TreeBuilder<'src>>(
+        &mut self,
+        mut child: Parser<'src>,
+        offset: usize,
+    ) -> Vec<T::Node> {
+        let mut nodes = child.parse_script::<T>();
+        for node in &mut nodes {
+            T::offset_spans(node, offset);
+        }
+        for mut error in child.errors {
+            error.span.offset(offset);
+            self.errors.push(error);
+        }
+        nodes
+    }
+
+    /// Number of reported diagnostics that are errors rather than warnings
+    fn error_count(&self) -> usize {
+        let uncounted = &self.errors[self.errors_counted..];
+        self.error_count + uncounted.iter().filter(|e| !e.is_warning()).count()
+    }
+
+    /// Whether `max_errors` has been reached and parsing should stop
+    fn error_limit_reached(&self) -> bool {
+        self.config
+            .max_errors
+            .is_some_and(|max| self.error_count() >= max.max(1))
+    }
+
+    /// Add the errors recorded since the last call to the running count,
+    /// dropping those beyond `max_errors` and everything reported after them
+    fn enforce_error_budget(&mut self) {
+        let max = self.config.max_errors.map_or(usize::MAX, |max| max.max(1));
+        let mut count = self.error_count;
+        let excess = self.errors[self.errors_counted..].iter().position(|e| {
+            count += usize::from(!e.is_warning());
+            count > max
+        });
+        if let Some(excess) = excess {
+            self.errors.truncate(self.errors_counted + excess);
+            count = max;
+        }
+        self.error_count = count;
+        self.errors_counted = self.errors.len();
+    }
 
-                        for mut error in inner_errors {
-                            error.span.offset(content_start);
-                            self.errors.push(error);
+    /// Match block openers with their closers among the calls of one code block,
+    /// reporting any left unmatched
+    fn check_block_balance(&mut self, body: &[AstNode]) {
+        let pairs: Vec<(&str, &str)> = match &self.block_pairs {
+            Some(pairs) => pairs
+                .iter()
+                .map(|(opener, closer)| (opener.as_str(), closer.as_str()))
+                .collect(),
+            None => DEFAULT_BLOCK_PAIRS.to_vec(),
+        };
+        let unclosed = |name: &str, closer: &str, span: Span| {
+            ParseError::new(
+                format!("`${}` is missing a closing `${}`", name, closer),
+                span,
+                ErrorKind::BlockBalance,
+            )
+        };
+
+        let mut errors = Vec::new();
+        let mut open: Vec<(&str, &str, Span)> = Vec::new();
+        for node in body {
+            let AstNode::FunctionCall { name, span, .. } = node else {
+                continue;
+            };
+
+            if let Some(&(_, closer)) = pairs.iter().find(|(o, _)| o.eq_ignore_ascii_case(name)) {
+                open.push((name, closer, *span));
+            } else if pairs.iter().any(|(_, c)| c.eq_ignore_ascii_case(name)) {
+                match open
+                    .iter()
+                    .rposition(|(_, c, _)| c.eq_ignore_ascii_case(name))
+                {
+                    Some(i) => {
+                        // Blocks opened inside the one being closed never got their closer
+                        for (name, closer, span) in open.drain(i + 1..) {
+                            errors.push(unclosed(name, closer, span));
                         }
+                        open.pop();
                     }
+                    None => errors.push(ParseError::new(
+                        format!("`${}` has no matching opener", name),
+                        *span,
+                        ErrorKind::BlockBalance,
+                    )),
+                }
+            }
+        }
+        for (name, closer, span) in open {
+            errors.push(unclosed(name, closer, span));
+        }
 
-                    // Move past closing backtick
+        self.errors.extend(errors);
+    }
+
+    // ========================================================================
+    // Event Streaming
+    // ========================================================================
+
+    /// Scan the source like [`Parser::parse`], but push [`ParseEvent`]s into
+    /// `sink` instead of building nodes.
+    ///
+    /// Only one parser per nesting level is alive at a time, so peak memory
+    /// grows with the nesting depth rather than the number of nodes. Metadata
+    /// validation and block balance checks are not performed; syntax errors
+    /// are still reported according to the configured [`ValidationConfig`].
+    pub fn parse_events(mut self, sink: &mut dyn FnMut(ParseEvent)) {
+        while !self.is_eof() && !self.error_limit_reached() {
+            if let Some((block_start, content_start)) = self.find_code_block_start() {
+                if block_start > self.pos {
+                    sink(ParseEvent::Text {
+                        span: Span::new(self.pos, block_start),
+                    });
+                }
+
+                self.pos = content_start;
+                let delimiter = self.bytes[content_start - 1];
+
+                if let Some(block_end) = self.find_code_block_end(delimiter) {
+                    if block_end > content_start {
+                        let mut inner = self.child(self.slice(content_start, block_end));
+                        inner.delimiter = delimiter;
+                        inner.emit_forge_events(content_start, sink);
+                        self.error_count = inner.error_count;
+                    }
                     self.pos = block_end + 1;
                 } else {
-                    // Unclosed block
                     if self.config.validate_brackets {
-                        self.errors.push(ParseError::syntax(
+                        sink(ParseEvent::Error(ParseError::syntax(
                             "Unclosed code block",
                             Span::new(block_start, self.source.len()),
-                        ));
+                        )));
                     }
-                    body.push(AstNode::Text {
-                        content: self.slice(block_start, self.source.len()).to_string(),
+                    sink(ParseEvent::Text {
                         span: Span::new(block_start, self.source.len()),
                     });
                     self.pos = self.source.len();
                 }
             } else {
-                // No more blocks, rest is text
                 if self.pos < self.source.len() {
-                    body.push(AstNode::Text {
-                        content: self.slice(self.pos, self.source.len()).to_string(),
+                    sink(ParseEvent::Text {
                         span: Span::new(self.pos, self.source.len()),
                     });
                 }
                 self.pos = self.source.len();
             }
         }
+    }
 
-        let span = Span::new(start, self.source.len());
-        (AstNode::Program { body, span }, self.errors)
+    /// Event-streaming counterpart of `parse_forge_script`. All emitted spans
+    /// are shifted by `base`.
+    fn emit_forge_events(&mut self, base: usize, sink: &mut dyn FnMut(ParseEvent)) {
+        while !self.is_eof() && !self.error_limit_reached() {
+            if self.at_function_call() {
+                self.emit_function_events(base, sink);
+            } else if let Some(node) = self.parse_forge_leaf() {
+                Self::emit_leaf_event(node, base, sink);
+            }
+
+            self.enforce_error_budget();
+            self.errors_counted = 0;
+            for mut error in self.errors.drain(..) {
+                error.span.offset(base);
+                sink(ParseEvent::Error(error));
+            }
+        }
     }
 
-    fn parse_forge_script(mut self) -> (AstNode, Vec<ParseError>) {
+    /// Report a `$` that is not followed by a function name. The `$` itself
+    /// is still recovered as text by the caller. A digit after the `$` is
+    /// plain text such as a price (`$5`), so nothing is reported for it.
+    fn report_missing_name(&mut self, start: usize) {
+        let digit = self.current_byte().is_some_and(|b| b.is_ascii_digit());
+        if self.config.validate_brackets && !digit {
+            self.errors.push(ParseError::syntax(
+                "expected function name after `$`",
+                Span::new(start, start + 1),
+            ));
+        }
+    }
+
+    /// Event-streaming counterpart of `parse_function_call`. Arguments are
+    /// located with the same bracket matching and splitting as the tree
+    /// builder, then streamed one at a time.
+    fn emit_function_events(&mut self, base: usize, sink: &mut dyn FnMut(ParseEvent)) {
         let start = self.pos;
-        let mut body = Vec::new();
+        self.advance(); // '$'
+        self.parse_modifiers();
+        let name = self.parse_identifier();
+        let name_end = self.pos;
 
-        while !self.is_eof() {
-            if let Some(node) = self.parse_forge_node() {
-                body.push(node);
+        if name.is_empty() {
+            self.report_missing_name(start);
+            sink(ParseEvent::Text {
+                span: Span::new(start + base, start + base + 1),
+            });
+            return;
+        }
+
+        if self.is_escape_function(name) {
+            let node = self.parse_escape_function(start, name, Span::new(start, name_end));
+            Self::emit_leaf_event(node, base, sink);
+            return;
+        }
+
+        let has_brackets = self.current_byte() == Some(b'[');
+        let bracket_start = self.pos;
+        let close = if has_brackets {
+            self.advance();
+            self.find_matching_bracket(bracket_start)
+        } else {
+            None
+        };
+
+        if has_brackets && close.is_none() && self.config.validate_brackets {
+            self.errors.push(ParseError::new(
+                "Unclosed function arguments",
+                Span::new(bracket_start, bracket_start + 1),
+                ErrorKind::UnclosedBracket,
+            ));
+        }
+
+        let end = close.map_or(self.pos, |close| close + 1);
+        sink(ParseEvent::EnterFunction {
+            name: name.to_string(),
+            span: Span::new(start + base, end + base),
+        });
+
+        if let Some(close) = close {
+            let content = self.slice(bracket_start + 1, close);
+            let content_base = base + bracket_start + 1;
+            for range in self.split_arguments(content) {
+                let arg_offset = content_base + range.start;
+                // The child takes over the count, so settle ours first
+                self.enforce_error_budget();
+                if range.is_empty() {
+                    sink(ParseEvent::Text {
+                        span: Span::new(arg_offset, arg_offset),
+                    });
+                } else if let Some(mut inner) =
+                    self.argument_child(&content[range.start..range.end], arg_offset - base)
+                {
+                    inner.emit_forge_events(arg_offset, sink);
+                    self.error_count = inner.error_count;
+                } else {
+                    sink(ParseEvent::Text {
+                        span: Span::new(arg_offset, content_base + range.end),
+                    });
+                }
             }
+            self.pos = close + 1;
         }
 
-        let span = Span::new(start, self.source.len());
-        (AstNode::Program { body, span }, self.errors)
+        sink(ParseEvent::ExitFunction);
+    }
+
+    fn emit_leaf_event(node: BorrowedAstNode, base: usize, sink: &mut dyn FnMut(ParseEvent)) {
+        let mut span = node.span();
+        span.offset(base);
+        match node {
+            BorrowedAstNode::JavaScript { .. } => sink(ParseEvent::JavaScript { span }),
+            BorrowedAstNode::Escaped { .. } => sink(ParseEvent::Escaped { span }),
+            _ => sink(ParseEvent::Text { span }),
+        }
+    }
+
+    // ========================================================================
+    // Borrowed Tree
+    // ========================================================================
+
+    /// Parse like [`Parser::parse`], but build a [`BorrowedAstNode`] tree
+    /// whose text borrows from the source instead of being copied.
+    ///
+    /// Metadata validation and block balance checks are not performed; syntax
+    /// errors are still reported according to the configured
+    /// [`ValidationConfig`].
+    pub fn parse_borrowed(mut self) -> (BorrowedAstNode<'src>, Vec<ParseError>) {
+        let (body, span) = self.parse_program::<BorrowedTree>();
+        (BorrowedAstNode::Program { body, span }, self.errors)
     }
 
     // ========================================================================
@@ -528,53 +1612,142 @@ impl<'src> Parser<'src> {
         &self.source[start..end.min(self.source.len())]
     }
 
-    fn find_code_block_start(&self) -> Option<usize> {
-        let mut search_pos = self.pos;
-        while let Some(idx) = self.source[search_pos..].find("code:") {
-            let start = search_pos + idx;
-            let preceded_by_valid = start == 0
-                || self.bytes[start - 1].is_ascii_whitespace()
-                || self.bytes[start - 1] == b'{'
-                || self.bytes[start - 1] == b',';
+    /// Find the next code block, returning the offset of its key and the offset
+    /// of its content (just past the opening delimiter).
+    ///
+    /// Keys inside `${ ... }` or escape-function (`$c[...]`) regions are skipped.
+    /// Only bytes that can start an escape, a region or a key are inspected;
+    /// everything in between is skipped with `memchr`.
+    fn find_code_block_start(&self) -> Option<(usize, usize)> {
+        let keys: Vec<&str> = match &self.block_keys {
+            Some(keys) => keys.iter().map(String::as_str).collect(),
+            None => DEFAULT_BLOCK_KEYS.to_vec(),
+        };
+        let mut key_starts: Vec<u8> = keys.iter().filter_map(|key| key.bytes().next()).collect();
+        key_starts.sort_unstable();
+        key_starts.dedup();
 
-            if !preceded_by_valid {
-                search_pos = start + 5;
+        let mut p = self.pos;
+        // Cleared at the first unclosed region, see `find_code_block_end`
+        let mut regions = true;
+        while p < self.bytes.len() {
+            let rest = &self.bytes[p..];
+            let skip = match key_starts.as_slice() {
+                [] => memchr::memchr2(b'\\', b'$', rest),
+                [key_start] => memchr::memchr3(b'\\', b'$', *key_start, rest),
+                _ => rest
+                    .iter()
+                    .position(|b| matches!(b, b'\\' | b'$') || key_starts.contains(b)),
+            };
+            p += skip?;
+
+            if self.bytes[p] == b'\\' {
+                p += escape_sequence_len(self.bytes, p).max(1);
                 continue;
             }
-            let mut i = start + 5;
-            while let Some(b) = self.bytes.get(i) {
-                if matches!(*b, b' ' | b'\t' | b'\n' | b'\r') {
-                    i += 1;
-                } else {
-                    break;
+            if regions && let Some(region) = self.opaque_region(p) {
+                match region {
+                    OpaqueRegion::EndsAt(end) => {
+                        p = end + 1;
+                        continue;
+                    }
+                    OpaqueRegion::Unclosed => regions = false,
                 }
             }
-            if self.bytes.get(i) == Some(&b'`') {
-                if !is_escaped(self.source, i) {
-                    return Some(start);
-                }
+            if let Some(content_start) = keys.iter().find_map(|key| self.code_block_at(p, key)) {
+                return Some((p, content_start));
             }
-            search_pos = start + 5;
+            p += 1;
         }
         None
     }
 
-    /// Find the closing backtick of the current code block.
+    /// Check whether a block for `key` opens at `start`, returning its content offset.
+    fn code_block_at(&self, start: usize, key: &str) -> Option<usize> {
+        if key.is_empty() || !self.bytes[start..].starts_with(key.as_bytes()) {
+            return None;
+        }
+
+        let preceded_by_valid = start == 0
+            || self.bytes[start - 1].is_ascii_whitespace()
+            || self.bytes[start - 1] == b'{'
+            || self.bytes[start - 1] == b',';
+        let key_end = start + key.len();
+        if !preceded_by_valid || self.bytes.get(key_end) != Some(&b':') {
+            return None;
+        }
+
+        let mut i = key_end + 1;
+        while let Some(b) = self.bytes.get(i) {
+            if matches!(*b, b' ' | b'\t' | b'\n' | b'\r') {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        let opens = self
+            .bytes
+            .get(i)
+            .is_some_and(|b| match &self.block_delimiters {
+                Some(delimiters) => delimiters.contains(b),
+                None => DEFAULT_BLOCK_DELIMITERS.contains(&(*b as char)),
+            });
+        (opens && !is_escaped(self.source, i)).then_some(i + 1)
+    }
+
+    /// If a `${ ... }`, escape-function or `$$` region starts at `p`, return
+    /// where it ends.
+    fn opaque_region(&self, p: usize) -> Option<OpaqueRegion> {
+        if self.bytes[p] != b'$' {
+            return None;
+        }
+        let end = if self.is_double_dollar(self.bytes, p) {
+            Some(p + 1)
+        } else if self.bytes.get(p + 1) == Some(&b'{') {
+            self.find_matching_brace(p + 1)
+        } else {
+            let open = self.escape_function_open(self.source, p)?;
+            self.escape_function_close(self.source, open)
+        };
+        Some(end.map_or(OpaqueRegion::Unclosed, OpaqueRegion::EndsAt))
+    }
+
+    /// Find the closing `delimiter` (usually a backtick) of the current code block.
     ///
     /// A backtick is considered escaped (and therefore not a closer) when it is
     /// preceded by a single `\` — i.e. `` \` ``.  Double-backslash before a
     /// backtick (`\\` followed by `` ` ``) means the backslashes escape *each
     /// other*, so the backtick is **not** escaped and does close the block.
-    fn find_code_block_end(&self) -> Option<usize> {
+    /// Quote delimiters follow the same rule. Delimiters inside `${ ... }` or
+    /// escape-function regions never close the block.
+    fn find_code_block_end(&self, delimiter: u8) -> Option<usize> {
         let mut p = self.pos;
+        // An unclosed region already scanned to the end of the input. Looking
+        // for regions again at every later `$` would make runs of unclosed
+        // `${` quadratic, so the rest is scanned as plain text.
+        let mut regions = true;
         while p < self.bytes.len() {
+            p += memchr::memchr3(b'\\', b'$', delimiter, &self.bytes[p..])?;
             if self.bytes[p] == b'\\' {
                 // Skip the full escape sequence so we don't mistake an escaped
-                // backtick (`` \` ``) for a block terminator.
-                p += escape_sequence_len(self.bytes, p).max(1);
+                // delimiter (`` \` ``) for a block terminator.
+                p += if self.bytes.get(p + 1) == Some(&delimiter) {
+                    2
+                } else {
+                    escape_sequence_len(self.bytes, p).max(1)
+                };
                 continue;
             }
-            if self.bytes[p] == b'`' {
+            if regions && let Some(region) = self.opaque_region(p) {
+                match region {
+                    OpaqueRegion::EndsAt(end) => {
+                        p = end + 1;
+                        continue;
+                    }
+                    OpaqueRegion::Unclosed => regions = false,
+                }
+            }
+            if self.bytes[p] == delimiter {
                 return Some(p);
             }
             p += 1;
@@ -586,7 +1759,17 @@ impl<'src> Parser<'src> {
     // High-Level Parsing
     // ========================================================================
 
-    fn parse_forge_node(&mut self) -> Option<AstNode> {
+    /// Whether a function call (not `${` or `$$`) starts at the current position
+    #[inline]
+    fn at_function_call(&self) -> bool {
+        self.current_byte() == Some(b'$')
+            && self.peek_byte(1) != Some(b'{')
+            && !self.is_double_dollar(self.bytes, self.pos)
+    }
+
+    /// Parse the next node that is not a function call. Leaves only ever
+    /// borrow from the source; owned callers convert them.
+    fn parse_forge_leaf(&mut self) -> Option<BorrowedAstNode<'src>> {
         // Handle backslash escapes first — this consumes the backslash and the
         // escaped character(s) in one go, so subsequent checks never see them.
         if self.current_byte() == Some(b'\\') {
@@ -595,16 +1778,36 @@ impl<'src> Parser<'src> {
 
         // Any `$` we see here is real (escaped ones were consumed above).
         if self.current_byte() == Some(b'$') {
-            if self.peek_byte(1) == Some(b'{') {
-                return Some(self.parse_javascript());
+            if self.is_double_dollar(self.bytes, self.pos) {
+                let start = self.pos;
+                self.pos += 2;
+                return Some(BorrowedAstNode::Text {
+                    content: self.slice(start, start + 1),
+                    span: Span::new(start, self.pos),
+                });
             }
-            return Some(self.parse_function_call());
+            return Some(self.parse_javascript());
         }
 
         self.parse_text()
     }
 
-    fn parse_text(&mut self) -> Option<AstNode> {
+    /// Warn about each `[`, `]` or `;` in the text from `start` to the
+    /// current position
+    fn report_unescaped_text(&mut self, start: usize) {
+        for p in start..self.pos {
+            let ch = self.bytes[p];
+            if matches!(ch, b'[' | b']' | b';') {
+                self.errors.push(ParseError::warning(
+                    format!("Unescaped `{}` in text", ch as char),
+                    Span::new(p, p + 1),
+                    ErrorKind::UnescapedText,
+                ));
+            }
+        }
+    }
+
+    fn parse_text(&mut self) -> Option<BorrowedAstNode<'src>> {
         let start = self.pos;
         while !self.is_eof() {
             // Stop at a backslash — the escape handler must deal with it.
@@ -620,8 +1823,11 @@ impl<'src> Parser<'src> {
         }
 
         if self.pos > start {
-            Some(AstNode::Text {
-                content: self.slice(start, self.pos).to_string(),
+            if self.config.strict_text && self.depth == 0 {
+                self.report_unescaped_text(start);
+            }
+            Some(BorrowedAstNode::Text {
+                content: self.slice(start, self.pos),
                 span: Span::new(start, self.pos),
             })
         } else {
@@ -639,16 +1845,20 @@ impl<'src> Parser<'src> {
     /// | `\\;`       | `;`          | 3              |
     /// | `\\`        | `\`          | 2              |
     /// | `\x` (other)| `\`          | 1 (only the backslash; `x` re-parsed next) |
-    fn parse_escape_sequence(&mut self) -> Option<AstNode> {
+    ///
+    /// The emitted node's span covers all consumed bytes, so it may be longer than
+    /// its content; [`AstNode::source_text`] recovers the original sequence.
+    /// The content borrows the emitted character from within the sequence.
+    fn parse_escape_sequence(&mut self) -> Option<BorrowedAstNode<'src>> {
         let start = self.pos;
         self.advance(); // consume the leading `\`
 
         match self.current_byte() {
-            // \` → literal backtick
-            Some(b'`') => {
+            // \` → literal backtick, or the block's own delimiter
+            Some(ch) if ch == b'`' || ch == self.delimiter => {
                 self.advance();
-                Some(AstNode::Text {
-                    content: "`".to_string(),
+                Some(BorrowedAstNode::Text {
+                    content: self.slice(self.pos - 1, self.pos),
                     span: Span::new(start, self.pos),
                 })
             }
@@ -658,19 +1868,18 @@ impl<'src> Parser<'src> {
                 match self.peek_byte(1) {
                     Some(b'$') | Some(b']') | Some(b';') => {
                         // \\$, \\], or \\; — consume second `\` and the target char
-                        let ch = self.peek_byte(1).unwrap() as char;
                         self.advance(); // second `\`
                         self.advance(); // `$`, `]`, or `;`
-                        Some(AstNode::Text {
-                            content: ch.to_string(),
+                        Some(BorrowedAstNode::Text {
+                            content: self.slice(self.pos - 1, self.pos),
                             span: Span::new(start, self.pos),
                         })
                     }
                     _ => {
                         // \\ alone → single literal backslash
                         self.advance(); // second `\`
-                        Some(AstNode::Text {
-                            content: "\\".to_string(),
+                        Some(BorrowedAstNode::Text {
+                            content: self.slice(start, start + 1),
                             span: Span::new(start, self.pos),
                         })
                     }
@@ -679,42 +1888,52 @@ impl<'src> Parser<'src> {
 
             // Lone backslash or unrecognised sequence — emit the `\` and let the
             // next character be re-parsed normally (so `\$func` → `\` text + call).
-            _ => Some(AstNode::Text {
-                content: "\\".to_string(),
+            _ => Some(BorrowedAstNode::Text {
+                content: self.slice(start, start + 1),
                 span: Span::new(start, start + 1),
             }),
         }
     }
 
-    fn parse_javascript(&mut self) -> AstNode {
+    fn parse_javascript(&mut self) -> BorrowedAstNode<'src> {
         let start = self.pos;
         self.advance(); // '$'
         self.advance(); // '{'
         let brace_start = self.pos - 1;
 
-        if let Some(end) = self.find_matching_brace(brace_start) {
-            let code = self.slice(brace_start + 1, end).to_string();
+        let node = if let Some(end) = self.find_matching_brace(brace_start) {
+            let code = self.slice(brace_start + 1, end);
             self.pos = end + 1;
-            AstNode::JavaScript {
+            BorrowedAstNode::JavaScript {
                 code,
                 span: Span::new(start, self.pos),
             }
         } else {
             if self.config.validate_brackets {
-                self.errors.push(ParseError::syntax(
+                self.errors.push(ParseError::new(
                     "Unclosed JavaScript expression",
                     Span::new(start, self.source.len()),
+                    ErrorKind::UnclosedJavaScript,
                 ));
             }
             self.pos = self.source.len();
-            AstNode::JavaScript {
-                code: String::new(),
+            BorrowedAstNode::JavaScript {
+                code: "",
                 span: Span::new(start, self.pos),
             }
+        };
+
+        if self.config.forbid_javascript {
+            self.errors.push(ParseError::new(
+                "Embedded JavaScript is not allowed",
+                node.span(),
+                ErrorKind::ForbiddenJavaScript,
+            ));
         }
+        node
     }
 
-    fn parse_function_call(&mut self) -> AstNode {
+    fn parse_function_call<T: TreeBuilder<'src>>(&mut self) -> T::Node {
         let start = self.pos;
         self.advance(); // '$'
 
@@ -735,28 +1954,34 @@ impl<'src> Parser<'src> {
         let name_end = self.pos;
 
         if name.is_empty() {
-            return AstNode::Text {
-                content: "$".to_string(),
+            self.report_missing_name(start);
+            return T::leaf(BorrowedAstNode::Text {
+                content: self.slice(start, start + 1),
                 span: Span::new(start, start + 1),
-            };
+            });
         }
 
         // name_span includes '$' and modifiers up to end of name
         let name_span = Span::new(start, name_end);
 
-        if self.is_escape_function(&name) {
-            return self.parse_escape_function(start, name, name_span);
+        if self.is_escape_function(name) {
+            let node = self.parse_escape_function(start, name, name_span);
+            return T::leaf(node);
         }
 
         // Record bracket/args span
         let has_brackets = self.current_byte() == Some(b'[');
         let bracket_open = self.pos;
 
-        let args = if has_brackets {
-            self.parse_function_arguments()
+        let (args, separators, close_bracket) = if has_brackets {
+            match self.parse_function_arguments::<T>() {
+                Some(list) => (Some(list.args), list.separators, Some(list.close_bracket)),
+                None => (None, Vec::new(), None),
+            }
         } else {
-            None
+            (None, Vec::new(), None)
         };
+        let open_bracket = has_brackets.then(|| Span::new(bracket_open, bracket_open + 1));
 
         let args_span = if has_brackets {
             // self.pos now points just past the closing ']'
@@ -765,58 +1990,39 @@ impl<'src> Parser<'src> {
             None
         };
 
-        let full_span = Span::new(modifier_start, self.pos);
-        let span = Span::new(start, self.pos);
-
-        // Validate with metadata if available
-        #[cfg(feature = "validation")]
-        if self.config.is_enabled() {
-            let full_name = if name.starts_with('$') {
-                name.clone()
-            } else {
-                format!("${}", name)
-            };
-
-            if let Some(ref metadata) = self.metadata {
-                let resolved = if has_brackets {
-                    metadata.get_exact(&full_name)
-                } else {
-                    metadata.get(&full_name)
-                };
+        let call = CallParts {
+            name,
+            name_span,
+            modifier_span,
+            args_span,
+            open_bracket,
+            close_bracket,
+            separators,
+            args,
+            modifiers,
+            full_span: Span::new(modifier_start, self.pos),
+            span: Span::new(start, self.pos),
+        };
+        T::function_call(self, call)
+    }
 
-                if let Some(func) = resolved {
-                    self.validate_function_call(
-                        &full_name,
-                        &func,
-                        args.as_ref(),
-                        has_brackets,
-                        name_span,
-                    );
-                } else if self.config.validate_functions {
-                    let hint: Option<String> = if has_brackets {
-                        metadata.get_prefix(&full_name).map(|(matched, _)| matched)
-                    } else {
-                        None
-                    };
+    /// Look up the called function in metadata and validate the call against
+    /// it, returning the function when found
+    #[cfg(feature = "validation")]
+    fn resolve_function_call(
+        &mut self,
+        name: &str,
+        args: Option<&Vec<Argument>>,
+        has_brackets: bool,
+        name_span: Span,
+    ) -> Option<Arc<Function>> {
+        if !self.config.is_enabled() {
+            return None;
+        }
+        let full_name = dollar_prefixed(name);
 
-                    if let Some(matched) = hint {
-                        self.errors.push(ParseError::new(
-                            format!(
-                                "Unknown function: {} (did you mean {}?)",
-                                full_name, matched
-                            ),
-                            name_span,
-                            ErrorKind::UnknownFunction,
-                        ));
-                    } else {
-                        self.errors.push(ParseError::new(
-                            format!("Unknown function: {}", full_name),
-                            name_span,
-                            ErrorKind::UnknownFunction,
-                        ));
-                    }
-                }
-            } else if self.config.validate_functions {
+        let Some(metadata) = &self.metadata else {
+            if self.config.validate_functions {
                 self.errors.push(ParseError::new(
                     format!(
                         "Cannot validate function {}: no metadata available",
@@ -826,24 +2032,58 @@ impl<'src> Parser<'src> {
                     ErrorKind::UnknownFunction,
                 ));
             }
-        }
+            return None;
+        };
 
-        AstNode::FunctionCall {
-            name,
-            name_span,
-            modifier_span,
-            args_span,
-            args,
-            modifiers,
-            full_span,
-            span,
+        let resolved = if has_brackets {
+            metadata.get_exact(&full_name)
+        } else {
+            metadata.get(&full_name)
+        };
+
+        if let Some(func) = &resolved {
+            self.validate_function_call(&full_name, func, args, has_brackets, name_span);
+        } else if self.config.validate_functions {
+            let hint: Option<String> = if has_brackets {
+                metadata.get_prefix(&full_name).map(|(matched, _)| matched)
+            } else {
+                None
+            };
+
+            if let Some(matched) = hint {
+                self.errors.push(ParseError::new(
+                    format!(
+                        "Unknown function: {} (did you mean {}?)",
+                        full_name, matched
+                    ),
+                    name_span,
+                    ErrorKind::UnknownFunction,
+                ));
+            } else {
+                self.errors.push(ParseError::new(
+                    format!("Unknown function: {}", full_name),
+                    name_span,
+                    ErrorKind::UnknownFunction,
+                ));
+            }
         }
+        resolved
     }
 
     // ========================================================================
     // Validation
     // ========================================================================
 
+    /// ` (version x)` for functions whose metadata records a version, else empty
+    #[cfg(feature = "validation")]
+    fn version_suffix(func: &Function) -> String {
+        match &func.version {
+            Some(serde_json::Value::String(v)) if !v.is_empty() => format!(" (version {})", v),
+            Some(serde_json::Value::Number(v)) => format!(" (version {})", v),
+            _ => String::new(),
+        }
+    }
+
     #[cfg(feature = "validation")]
     fn validate_function_call(
         &mut self,
@@ -853,6 +2093,21 @@ impl<'src> Parser<'src> {
         has_brackets: bool,
         name_span: Span,
     ) {
+        if self.config.deny_deprecated && func.deprecated == Some(true) {
+            self.errors.push(ParseError::new(
+                format!("{} is deprecated{}", name, Self::version_suffix(func)),
+                name_span,
+                ErrorKind::Deprecated,
+            ));
+        }
+        if self.config.warn_experimental && func.experimental == Some(true) {
+            self.errors.push(ParseError::warning(
+                format!("{} is experimental{}", name, Self::version_suffix(func)),
+                name_span,
+                ErrorKind::Experimental,
+            ));
+        }
+
         // Validate brackets usage
         if self.config.validate_brackets {
             match func.brackets {
@@ -880,11 +2135,19 @@ impl<'src> Parser<'src> {
             }
         }
 
+        if self.config.validate_unwrap_usage
+            && has_brackets
+            && let (Some(args), Some(func_args)) = (args, &func.args)
+        {
+            self.validate_unwrap_usage(name, args, func_args);
+        }
+
         // Validate argument count and enums
-        if (self.config.validate_arguments || self.config.validate_enums) && has_brackets {
-            if let (Some(args), Some(func_args)) = (args, &func.args) {
-                self.validate_arguments(name, args, func_args, name_span);
-            }
+        if (self.config.validate_arguments || self.config.validate_enums)
+            && has_brackets
+            && let (Some(args), Some(func_args)) = (args, &func.args)
+        {
+            self.validate_arguments(name, args, func_args, name_span);
         }
     }
 
@@ -899,10 +2162,10 @@ impl<'src> Parser<'src> {
         let provided_count = provided_args.len();
 
         let has_rest = func_args.iter().any(|a| a.rest);
-        let required_count = func_args
-            .iter()
-            .filter(|a| a.required.unwrap_or(false) && !a.rest)
-            .count();
+        let is_required = |a: &Arg| a.required.unwrap_or(false) && !a.rest;
+        // Arguments are positional, so every slot up to the last required one
+        // must be filled even if metadata marks an earlier slot optional.
+        let required_count = func_args.iter().rposition(is_required).map_or(0, |i| i + 1);
         let max_count = if has_rest {
             usize::MAX
         } else {
@@ -910,11 +2173,61 @@ impl<'src> Parser<'src> {
         };
 
         if self.config.validate_arguments {
+            if let Some(optional) = func_args.iter().position(|a| !is_required(a))
+                && let Some(offset) = func_args[optional..].iter().position(is_required)
+            {
+                let position = optional + offset;
+                self.errors.push(ParseError::warning(
+                    format!(
+                        "{} metadata declares required argument `{}` (position {}) after optional argument `{}` (position {})",
+                        func_name,
+                        func_args[position].name,
+                        position + 1,
+                        func_args[optional].name,
+                        optional + 1
+                    ),
+                    name_span,
+                    ErrorKind::ArgumentOrder,
+                ));
+            }
+
+            for (position, func_arg) in func_args.iter().enumerate() {
+                let Some(pointer) = func_arg.pointer else {
+                    continue;
+                };
+                if func_arg
+                    .pointer_index()
+                    .is_none_or(|index| index >= func_args.len())
+                {
+                    self.errors.push(ParseError::warning(
+                        format!(
+                            "{} metadata: argument `{}` (position {}) points to argument {}, but only {} argument(s) exist",
+                            func_name,
+                            func_arg.name,
+                            position + 1,
+                            pointer,
+                            func_args.len()
+                        ),
+                        name_span,
+                        ErrorKind::ArgumentPointer,
+                    ));
+                }
+            }
+
             if provided_count < required_count {
+                let missing = provided_count
+                    + func_args[provided_count..]
+                        .iter()
+                        .position(is_required)
+                        .unwrap_or(0);
                 self.errors.push(ParseError::new(
                     format!(
-                        "{} requires at least {} argument(s), got {}",
-                        func_name, required_count, provided_count
+                        "{} requires at least {} argument(s), got {}; missing `{}` (position {})",
+                        func_name,
+                        required_count,
+                        provided_count,
+                        func_args[missing].name,
+                        missing + 1
                     ),
                     name_span,
                     ErrorKind::ArgumentCount,
@@ -953,6 +2266,71 @@ impl<'src> Parser<'src> {
         }
     }
 
+    /// Flag `unwrap` functions passed directly as an argument whose declared
+    /// type is concrete.
+    ///
+    /// Heuristic: an argument slot is considered to expect a concrete value
+    /// when its metadata `type` is a single type name other than `Unknown`.
+    /// Only calls that are an immediate part of such an argument are checked;
+    /// calls nested deeper are validated against their own parent.
+    #[cfg(feature = "validation")]
+    fn validate_unwrap_usage(
+        &mut self,
+        func_name: &str,
+        provided_args: &[Argument],
+        func_args: &[Arg],
+    ) {
+        let Some(metadata) = self.metadata.clone() else {
+            return;
+        };
+        let has_rest = func_args.iter().any(|a| a.rest);
+
+        for (i, provided_arg) in provided_args.iter().enumerate() {
+            let func_arg = if i < func_args.len() {
+                &func_args[i]
+            } else if has_rest {
+                func_args.last().unwrap()
+            } else {
+                continue;
+            };
+
+            let expects_concrete =
+                matches!(&func_arg.arg_type, serde_json::Value::String(t) if t != "Unknown");
+            if !expects_concrete {
+                continue;
+            }
+
+            for part in &provided_arg.parts {
+                let AstNode::FunctionCall {
+                    args_span, span, ..
+                } = part
+                else {
+                    continue;
+                };
+
+                let Some(inner_name) = part.function_full_name() else {
+                    continue;
+                };
+                let unwraps = if args_span.is_some() {
+                    metadata.with_function(&inner_name, |f| f.unwrap)
+                } else {
+                    metadata.get(&inner_name).map(|f| f.unwrap)
+                };
+
+                if unwraps == Some(true) {
+                    self.errors.push(ParseError::warning(
+                        format!(
+                            "{} unwraps its output and should not be used as the value of {} argument {}",
+                            inner_name, func_name, func_arg.name
+                        ),
+                        *span,
+                        ErrorKind::UnwrapUsage,
+                    ));
+                }
+            }
+        }
+    }
+
     #[cfg(feature = "validation")]
     fn validate_enum_value(
         &mut self,
@@ -965,30 +2343,54 @@ impl<'src> Parser<'src> {
             return;
         }
 
-        let enum_values = if let Some(enum_name) = &func_arg.enum_name {
-            if let Some(ref metadata) = self.metadata {
-                metadata.get_enum(enum_name)
-            } else {
-                None
-            }
-        } else {
-            func_arg.arg_enum.clone()
+        let enum_values = match &self.metadata {
+            Some(metadata) => metadata.arg_enum_values(func_arg),
+            None if func_arg.enum_name.is_none() => func_arg.arg_enum.clone(),
+            None => None,
         };
 
-        if let Some(valid_values) = enum_values {
-            if let Some(text_value) = arg.as_text() {
-                let trimmed = text_value.trim();
-                if !trimmed.is_empty() && !valid_values.contains(&trimmed.to_string()) {
-                    self.errors.push(ParseError::new(
-                        format!(
-                            "Invalid value for {} argument {}: expected one of {:?}",
-                            func_name, func_arg.name, valid_values
-                        ),
-                        name_span,
-                        ErrorKind::EnumValue,
-                    ));
-                }
+        if let Some(valid_values) = enum_values
+            && let Some(text_value) = arg.as_text()
+        {
+            let trimmed = text_value.trim();
+            if trimmed.is_empty() || valid_values.iter().any(|v| v == trimmed) {
+                return;
+            }
+
+            if !self.config.enum_case_sensitive
+                && let Some(canonical) = valid_values
+                    .iter()
+                    .find(|v| v.eq_ignore_ascii_case(trimmed))
+            {
+                self.errors.push(ParseError::warning(
+                    format!(
+                        "Value '{}' for {} should be written '{}'",
+                        trimmed, func_arg.name, canonical
+                    ),
+                    name_span,
+                    ErrorKind::EnumValue,
+                ));
+                return;
             }
+
+            let suggestions =
+                crate::utils::closest_matches(trimmed, valid_values.iter().map(String::as_str));
+            let message = if suggestions.is_empty() {
+                format!(
+                    "Invalid value for {} argument {}: expected one of {:?}",
+                    func_name, func_arg.name, valid_values
+                )
+            } else {
+                let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+                format!(
+                    "Invalid value '{}' for {}; did you mean {}?",
+                    trimmed,
+                    func_arg.name,
+                    quoted.join(" or ")
+                )
+            };
+            self.errors
+                .push(ParseError::new(message, name_span, ErrorKind::EnumValue));
         }
     }
 
@@ -1003,24 +2405,46 @@ impl<'src> Parser<'src> {
         loop {
             match self.current_byte() {
                 Some(b'!') => {
-                    modifiers.silent = true;
+                    let span = Span::new(self.pos, self.pos + 1);
+                    if modifiers.silent_span.is_some() {
+                        self.report_duplicate_modifier("!", span);
+                    } else {
+                        modifiers.silent = true;
+                        modifiers.silent_span = Some(span);
+                    }
                     self.advance();
                 }
                 Some(b'#') => {
-                    modifiers.negated = true;
+                    let span = Span::new(self.pos, self.pos + 1);
+                    if modifiers.negated_span.is_some() {
+                        self.report_duplicate_modifier("#", span);
+                    } else {
+                        modifiers.negated = true;
+                        modifiers.negated_span = Some(span);
+                    }
                     self.advance();
                 }
                 Some(b'@') if self.peek_byte(1) == Some(b'[') => {
+                    let at = self.pos;
                     self.advance(); // '@'
                     let bracket_start = self.pos;
                     self.advance(); // '['
                     if let Some(end) = self.find_matching_bracket(bracket_start) {
-                        modifiers.count = Some(self.slice(bracket_start + 1, end).to_string());
+                        let span = Span::new(at, end + 1);
+                        if modifiers.count_span.is_some() {
+                            self.report_duplicate_modifier("@[...]", span);
+                        } else {
+                            let count = self.slice(bracket_start + 1, end);
+                            self.validate_count(count, span);
+                            modifiers.count = Some(count.to_string());
+                            modifiers.count_span = Some(span);
+                        }
                         self.pos = end + 1;
                     } else if self.config.validate_brackets {
-                        self.errors.push(ParseError::syntax(
+                        self.errors.push(ParseError::new(
                             "Unclosed modifier bracket",
                             Span::new(bracket_start, bracket_start + 1),
+                            ErrorKind::UnclosedBracket,
                         ));
                         break;
                     } else {
@@ -1039,9 +2463,48 @@ impl<'src> Parser<'src> {
         modifiers
     }
 
+    /// Check that an `@[n]` count is a positive integer or contains a
+    /// `$func` / `${}` expression that yields one at runtime
+    fn validate_count(&mut self, count: &str, span: Span) {
+        if !(self.config.validate_arguments || self.config.validate_modifiers) {
+            return;
+        }
+
+        let count = count.trim();
+        let is_positive = count.parse::<u64>().is_ok_and(|n| n > 0);
+        let has_expression = count.as_bytes().windows(2).any(|pair| {
+            pair[0] == b'$' && (pair[1] == b'{' || pair[1] == b'_' || pair[1].is_ascii_alphabetic())
+        });
+
+        if !is_positive && !has_expression {
+            self.errors.push(ParseError::syntax(
+                format!(
+                    "Count modifier expects a positive integer or an expression, found `{}`",
+                    count
+                ),
+                span,
+            ));
+        }
+    }
+
+    fn report_duplicate_modifier(&mut self, modifier: &str, span: Span) {
+        if self.config.validate_modifiers {
+            self.errors.push(ParseError::warning(
+                format!("Duplicate modifier `{}` has no effect", modifier),
+                span,
+                ErrorKind::DuplicateModifier,
+            ));
+        }
+    }
+
+    /// Parse a function name: a letter or `_` followed by letters, digits or `_`.
+    /// Returns an empty string (consuming nothing) when no name starts here.
     #[inline]
-    fn parse_identifier(&mut self) -> String {
+    fn parse_identifier(&mut self) -> &'src str {
         let start = self.pos;
+        if self.current_byte().is_some_and(|b| b.is_ascii_digit()) {
+            return "";
+        }
         while let Some(b) = self.current_byte() {
             if b.is_ascii_alphanumeric() || b == b'_' {
                 self.advance();
@@ -1049,24 +2512,36 @@ impl<'src> Parser<'src> {
                 break;
             }
         }
-        self.slice(start, self.pos).to_string()
+        self.slice(start, self.pos)
     }
 
     fn is_escape_function(&self, name: &str) -> bool {
-        matches!(name, "c" | "C" | "escape")
+        match &self.escape_functions {
+            Some(names) => names.iter().any(|n| n == name),
+            None => DEFAULT_ESCAPE_FUNCTIONS.contains(&name),
+        }
     }
 
-    fn parse_escape_function(&mut self, start: usize, name: String, name_span: Span) -> AstNode {
+    fn parse_escape_function(
+        &mut self,
+        start: usize,
+        name: &'src str,
+        name_span: Span,
+    ) -> BorrowedAstNode<'src> {
         if self.current_byte() != Some(b'[') {
-            if self.config.validate_brackets {
+            let bare_allowed = self
+                .bare_escape_functions
+                .as_ref()
+                .is_some_and(|names| names.iter().any(|n| n == name));
+            if self.config.validate_brackets && !bare_allowed {
                 self.errors.push(ParseError::new(
                     format!("${} requires brackets", name),
                     name_span,
                     ErrorKind::BracketUsage,
                 ));
             }
-            return AstNode::Text {
-                content: self.slice(start, self.pos).to_string(),
+            return BorrowedAstNode::Text {
+                content: self.slice(start, self.pos),
                 span: Span::new(start, self.pos),
             };
         }
@@ -1074,49 +2549,75 @@ impl<'src> Parser<'src> {
         let bracket_start = self.pos;
         self.advance();
         if let Some(end) = self.find_matching_bracket(bracket_start) {
-            let content = self.slice(bracket_start + 1, end).to_string();
+            let content = self.slice(bracket_start + 1, end);
             self.pos = end + 1;
-            AstNode::Escaped {
+            BorrowedAstNode::Escaped {
                 content,
+                function: name,
                 span: Span::new(start, self.pos),
             }
         } else {
             if self.config.validate_brackets {
-                self.errors.push(ParseError::syntax(
+                self.errors.push(ParseError::new(
                     format!("Unclosed '[' for ${}", name),
                     name_span,
+                    ErrorKind::UnclosedBracket,
                 ));
             }
             self.pos = self.source.len();
-            AstNode::Escaped {
-                content: String::new(),
+            BorrowedAstNode::Escaped {
+                content: "",
+                function: name,
                 span: Span::new(start, self.pos),
             }
         }
     }
 
-    fn parse_function_arguments(&mut self) -> Option<Vec<Argument>> {
+    /// Parse a bracketed argument list starting at `[`
+    fn parse_function_arguments<T: TreeBuilder<'src>>(
+        &mut self,
+    ) -> Option<ArgumentList<T::Argument>> {
         let bracket_start = self.pos;
         self.advance();
-        if let Some(end) = self.find_matching_bracket(bracket_start) {
-            let args_content = self.slice(bracket_start + 1, end);
-            let parsed_args = self.parse_arguments(args_content, bracket_start + 1);
-            self.pos = end + 1;
-            Some(parsed_args)
-        } else {
+        let Some(end) = self.find_matching_bracket(bracket_start) else {
             if self.config.validate_brackets {
-                self.errors.push(ParseError::syntax(
+                self.errors.push(ParseError::new(
                     "Unclosed function arguments",
                     Span::new(bracket_start, bracket_start + 1),
+                    ErrorKind::UnclosedBracket,
                 ));
             }
-            None
-        }
+            return None;
+        };
+
+        let content = self.slice(bracket_start + 1, end);
+        let base = bracket_start + 1;
+        let ranges = self.split_arguments(content);
+        let separators = separator_spans(&ranges, base);
+        let args = ranges
+            .into_iter()
+            .map(|range| {
+                let offset = base + range.start;
+                let parts =
+                    self.parse_argument_parts::<T>(&content[range.start..range.end], offset);
+                T::argument(parts, Span::new(offset, base + range.end))
+            })
+            .collect();
+        self.pos = end + 1;
+        Some(ArgumentList {
+            args,
+            separators,
+            close_bracket: Span::new(end, end + 1),
+        })
     }
 
-    fn parse_arguments(&mut self, content: &str, base_offset: usize) -> Vec<Argument> {
-        let mut args = Vec::new();
-        let mut current = String::new();
+    /// Split the raw content between a function's brackets into per-argument
+    /// ranges (relative to `content`), honouring escapes and nested calls.
+    ///
+    /// Empty content yields no ranges; otherwise there is one range per `;`
+    /// plus one, empty ranges included.
+    fn split_arguments(&self, content: &str) -> SmallVec<[Span; 4]> {
+        let mut ranges = SmallVec::new();
         let mut depth = 0usize;
         let bytes = content.as_bytes();
         let mut i = 0;
@@ -1125,17 +2626,20 @@ impl<'src> Parser<'src> {
 
         while i < bytes.len() {
             // ----------------------------------------------------------------
-            // Escape sequences — consume the full sequence and pass it through
-            // verbatim so that parse_argument_parts can re-interpret it.
+            // Escape sequences — skip the full sequence so it stays part of
+            // the argument and parse_argument_parts can re-interpret it.
             // `\\;` (3 bytes) is handled here: escape_sequence_len returns 3,
-            // the whole sequence is pushed to `current`, and the `;` is never
-            // seen by the separator check below.
+            // and the `;` is never seen by the separator check below.
             // ----------------------------------------------------------------
             if bytes[i] == b'\\' {
                 let skip = escape_sequence_len(bytes, i).max(1);
-                let end = (i + skip).min(bytes.len());
-                current.push_str(&content[i..end]);
-                i = end;
+                i = (i + skip).min(bytes.len());
+                arg_end = i;
+                continue;
+            }
+
+            if self.is_double_dollar(bytes, i) {
+                i += 2;
                 arg_end = i;
                 continue;
             }
@@ -1144,13 +2648,13 @@ impl<'src> Parser<'src> {
             // Escape-function shorthand: $c[...] / $escape[...] inside args.
             // Track it as an opaque blob so its brackets don't confuse depth.
             // ----------------------------------------------------------------
-            if bytes[i] == b'$' && depth == 0 {
-                if let Some(esc_end) = self.find_escape_function_end(content, i) {
-                    current.push_str(&content[i..=esc_end]);
-                    i = esc_end + 1;
-                    arg_end = i;
-                    continue;
-                }
+            if bytes[i] == b'$'
+                && depth == 0
+                && let Some(esc_end) = self.find_escape_function_end(content, i)
+            {
+                i = esc_end + 1;
+                arg_end = i;
+                continue;
             }
 
             match bytes[i] {
@@ -1159,25 +2663,16 @@ impl<'src> Parser<'src> {
                 // as literal content so users don't need to escape it.
                 b'[' if self.is_function_bracket(content, i) => {
                     depth += 1;
-                    current.push('[');
                     arg_end = i + 1;
                 }
                 // Only decrement depth when we are actually inside a nested
                 // function bracket.  A `]` at depth == 0 is literal content.
                 b']' if depth > 0 => {
                     depth -= 1;
-                    current.push(']');
                     arg_end = i + 1;
                 }
                 b';' if depth == 0 => {
-                    let arg_offset = base_offset + arg_start;
-                    let arg_len = arg_end - arg_start;
-                    let parts = self.parse_argument_parts(&current, arg_offset);
-                    args.push(Argument {
-                        parts,
-                        span: Span::new(arg_offset, arg_offset + arg_len),
-                    });
-                    current.clear();
+                    ranges.push(Span::new(arg_start, arg_end));
                     arg_start = i + 1;
                     arg_end = i + 1;
                 }
@@ -1187,7 +2682,6 @@ impl<'src> Parser<'src> {
                         .next()
                         .map(|c| c.len_utf8())
                         .unwrap_or(1);
-                    current.push_str(&content[i..i + ch_len]);
                     arg_end = i + ch_len;
                     i += ch_len - 1;
                 }
@@ -1195,63 +2689,25 @@ impl<'src> Parser<'src> {
             i += 1;
         }
 
-        if !current.is_empty() || !args.is_empty() {
-            let arg_offset = base_offset + arg_start;
-            let arg_len = arg_end - arg_start;
-            let parts = self.parse_argument_parts(&current, arg_offset);
-            args.push(Argument {
-                parts,
-                span: Span::new(arg_offset, arg_offset + arg_len),
-            });
+        if arg_end > arg_start || !ranges.is_empty() {
+            ranges.push(Span::new(arg_start, arg_end));
         }
-        args
+        ranges
     }
 
-    fn parse_argument_parts(&mut self, content: &str, offset: usize) -> SmallVec<[AstNode; 4]> {
+    fn parse_argument_parts<T: TreeBuilder<'src>>(
+        &mut self,
+        content: &'src str,
+        offset: usize,
+    ) -> SmallVec<[T::Node; 4]> {
+        let span = Span::new(offset, offset + content.len());
         if content.is_empty() {
-            let mut parts = SmallVec::new();
-            parts.push(AstNode::Text {
-                content: String::new(),
-                span: Span::new(offset, offset),
-            });
-            return parts;
+            return smallvec::smallvec![T::leaf(BorrowedAstNode::Text { content, span })];
         }
-
-        #[cfg(feature = "validation")]
-        let inner_parser = if self.config.is_enabled() {
-            if let Some(ref metadata) = self.metadata {
-                Parser::with_validation(content, self.config.clone(), metadata.clone())
-            } else {
-                Parser::with_config(content, self.config.clone())
-            }
-        } else {
-            Parser::new(content)
-        };
-
-        #[cfg(not(feature = "validation"))]
-        let inner_parser = Parser::new(content);
-
-        let (ast, errors) = inner_parser.parse_forge_script();
-
-        let nodes = if let AstNode::Program { mut body, .. } = ast {
-            for node in &mut body {
-                node.offset_spans(offset);
-            }
-            body
-        } else {
-            vec![ast]
+        let Some(inner) = self.argument_child(content, offset) else {
+            return smallvec::smallvec![T::leaf(BorrowedAstNode::Text { content, span })];
         };
-
-        for mut error in errors {
-            error.span.offset(offset);
-            self.errors.push(error);
-        }
-
-        let mut parts = SmallVec::new();
-        for node in nodes {
-            parts.push(node);
-        }
-        parts
+        self.adopt_script::<T>(inner, offset).into_iter().collect()
     }
 
     // ========================================================================
@@ -1321,6 +2777,10 @@ impl<'src> Parser<'src> {
         while i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') {
             i -= 1;
         }
+        // Function names never start with a digit
+        if i < idx && bytes[i].is_ascii_digit() {
+            return false;
+        }
         while i > 0 && matches!(bytes[i - 1], b'!' | b'#' | b']') {
             if bytes[i - 1] == b']' {
                 let mut d = 1;
@@ -1340,10 +2800,26 @@ impl<'src> Parser<'src> {
                 i -= 1;
             }
         }
-        i > 0 && bytes[i - 1] == b'$' && (i == 1 || bytes[i - 2] != b'\\')
+        if i == 0 || bytes[i - 1] != b'$' || (i > 1 && bytes[i - 2] == b'\\') {
+            return false;
+        }
+        if !self.double_dollar {
+            return true;
+        }
+        // With `$$` escapes only an odd run of dollars ends in a real `$`
+        let run = bytes[..i].iter().rev().take_while(|&&b| b == b'$').count();
+        let escaped = i > run && bytes[i - run - 1] == b'\\';
+        (run - usize::from(escaped)) % 2 == 1
     }
 
     fn find_escape_function_end(&self, content: &str, start: usize) -> Option<usize> {
+        let open = self.escape_function_open(content, start)?;
+        self.escape_function_close(content, open)
+    }
+
+    /// If an escape-function call with brackets starts at `start`, return the
+    /// offset of its `[`
+    fn escape_function_open(&self, content: &str, start: usize) -> Option<usize> {
         let bytes = content.as_bytes();
         let mut p = start + 1;
         while p < bytes.len() && matches!(bytes[p], b'!' | b'#') {
@@ -1353,11 +2829,15 @@ impl<'src> Parser<'src> {
         while p < bytes.len() && (bytes[p].is_ascii_alphanumeric() || bytes[p] == b'_') {
             p += 1;
         }
-        if !self.is_escape_function(&content[name_start..p]) || bytes.get(p) != Some(&b'[') {
-            return None;
-        }
+        (self.is_escape_function(&content[name_start..p]) && bytes.get(p) == Some(&b'['))
+            .then_some(p)
+    }
+
+    /// Find the `]` closing the escape-function bracket opened at `open`
+    fn escape_function_close(&self, content: &str, open: usize) -> Option<usize> {
+        let bytes = content.as_bytes();
         let mut depth = 1usize;
-        p += 1;
+        let mut p = open + 1;
         while p < bytes.len() {
             if bytes[p] == b'\\' {
                 p += escape_sequence_len(bytes, p).max(1);
@@ -1377,6 +2857,150 @@ impl<'src> Parser<'src> {
     }
 }
 
+// ============================================================================
+// Parser Builder
+// ============================================================================
+
+/// Collects parser options so a configured [`Parser`] can be created for any
+/// number of sources.
+///
+/// ```
+/// use forge_kit::parser::Parser;
+///
+/// let (ast, errors) = Parser::builder()
+///     .block_keys(["code", "reply"])
+///     .max_depth(16)
+///     .parse("reply: `$ping`");
+/// assert!(errors.is_empty());
+/// assert_eq!(forge_kit::utils::extract_function_names(&ast), vec!["ping"]);
+/// ```
+#[derive(Clone, Default)]
+pub struct ParserBuilder {
+    #[cfg(feature = "validation")]
+    config: ValidationConfig,
+    #[cfg(feature = "validation")]
+    metadata: Option<Arc<MetadataManager>>,
+    max_depth: Option<usize>,
+    block_keys: Option<Vec<String>>,
+    block_delimiters: Option<Vec<u8>>,
+    escape_functions: Option<Arc<[String]>>,
+    bare_escape_functions: Option<Arc<[String]>>,
+    block_pairs: Option<Arc<[(String, String)]>>,
+    double_dollar: bool,
+}
+
+impl ParserBuilder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validation to perform while parsing (requires "validation" feature)
+    #[cfg(feature = "validation")]
+    pub fn validation(mut self, config: ValidationConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Metadata used for validation (requires "validation" feature)
+    #[cfg(feature = "validation")]
+    pub fn metadata(mut self, metadata: Arc<MetadataManager>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// See [`Parser::with_max_depth`]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// See [`Parser::with_block_keys`]
+    pub fn block_keys<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.block_keys = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// See [`Parser::with_block_delimiters`]
+    pub fn block_delimiters<I>(mut self, delimiters: I) -> Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.block_delimiters = Some(collect_delimiters(delimiters));
+        self
+    }
+
+    /// See [`Parser::with_escape_functions`]
+    pub fn escape_functions<I, K>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.escape_functions = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// See [`Parser::with_bare_escape_functions`]
+    pub fn bare_escape_functions<I, K>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.bare_escape_functions = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// See [`Parser::with_block_pairs`]
+    pub fn block_pairs<I, O, C>(mut self, pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (O, C)>,
+        O: Into<String>,
+        C: Into<String>,
+    {
+        self.block_pairs = Some(
+            pairs
+                .into_iter()
+                .map(|(opener, closer)| (opener.into(), closer.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// See [`Parser::with_double_dollar_escape`]
+    pub fn double_dollar_escape(mut self, enabled: bool) -> Self {
+        self.double_dollar = enabled;
+        self
+    }
+
+    /// Create a parser for `source` with the collected options
+    pub fn build<'src>(&self, source: &'src str) -> Parser<'src> {
+        let mut parser = Parser::new(source);
+        #[cfg(feature = "validation")]
+        {
+            parser.config = self.config.clone();
+            parser.metadata = self.metadata.clone();
+        }
+        parser.max_depth = self.max_depth;
+        parser.block_keys = self.block_keys.clone();
+        parser.block_delimiters = self.block_delimiters.clone();
+        parser.escape_functions = self.escape_functions.clone();
+        parser.bare_escape_functions = self.bare_escape_functions.clone();
+        parser.block_pairs = self.block_pairs.clone();
+        parser.double_dollar = self.double_dollar;
+        parser
+    }
+
+    /// Parse `source` with the collected options
+    #[inline]
+    pub fn parse(&self, source: &str) -> (AstNode, Vec<ParseError>) {
+        self.build(source).parse()
+    }
+}
+
 // ============================================================================
 // Public API
 // ============================================================================
@@ -1386,6 +3010,34 @@ pub fn parse(source: &str) -> (AstNode, Vec<ParseError>) {
     Parser::new(source).parse()
 }
 
+/// Parse bare ForgeScript code into an AST (no validation)
+///
+/// Unlike [`parse`], which only treats the contents of `code:` blocks as
+/// ForgeScript and keeps everything else as text, the whole of `source` is
+/// parsed as code. Use this when the code body has already been extracted.
+pub fn parse_raw(source: &str) -> (AstNode, Vec<ParseError>) {
+    Parser::new(source).parse_forge_script()
+}
+
+/// Parse ForgeScript source code into a [`BorrowedAstNode`] tree that
+/// borrows its text from `source` (no validation)
+pub fn parse_borrowed(source: &str) -> (BorrowedAstNode<'_>, Vec<ParseError>) {
+    Parser::new(source).parse_borrowed()
+}
+
+/// Stream parse events for ForgeScript source code without building an AST (no validation)
+pub fn parse_events(source: &str, sink: &mut dyn FnMut(ParseEvent)) {
+    Parser::new(source).parse_events(sink)
+}
+
+/// Parse ForgeScript source code up to byte `offset` (no validation)
+///
+/// Nodes that start after `offset` are absent from the result; the node
+/// spanning `offset` is included in full.
+pub fn parse_until(source: &str, offset: usize) -> (AstNode, Vec<ParseError>) {
+    Parser::new(source).parse_until(offset)
+}
+
 /// Parse with error handling
 pub fn parse_with_errors(source: &str) -> Result<AstNode, Vec<ParseError>> {
     let (ast, errors) = parse(source);
@@ -1427,3 +3079,107 @@ pub fn parse_forge_script_with_validation(
 pub fn parse_strict(source: &str, metadata: Arc<MetadataManager>) -> (AstNode, Vec<ParseError>) {
     Parser::with_validation(source, ValidationConfig::strict(), metadata).parse()
 }
+
+/// Validate `source` with each config in turn, stopping after the first one
+/// that reports an error (requires "validation" feature).
+///
+/// Supports the syntax-gate-then-semantic pattern: pass
+/// `[ValidationConfig::syntax_only(), ValidationConfig::strict()]` to skip
+/// metadata checks for sources that do not even parse. Warnings do not stop
+/// the run. Diagnostics reported by more than one config are returned once.
+#[cfg(feature = "validation")]
+pub fn validate_layered(
+    source: &str,
+    configs: &[ValidationConfig],
+    metadata: Option<&Arc<MetadataManager>>,
+) -> Vec<ParseError> {
+    let mut diagnostics: Vec<ParseError> = Vec::new();
+
+    for config in configs {
+        let (_, errors) = match metadata {
+            Some(metadata) => parse_with_validation(source, config.clone(), metadata.clone()),
+            None => parse_with_config(source, config.clone()),
+        };
+
+        let failed = errors.iter().any(|e| !e.is_warning());
+        for error in errors {
+            if !diagnostics.contains(&error) {
+                diagnostics.push(error);
+            }
+        }
+        if failed {
+            break;
+        }
+    }
+
+    diagnostics
+}
+
+// ============================================================================
+// Directory Validation
+// ============================================================================
+
+/// A validation error together with its 0-based line and column (in chars)
+#[cfg(all(feature = "validation", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub struct FileDiagnostic {
+    pub error: ParseError,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Validation results for a single source file
+#[cfg(all(feature = "validation", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub struct FileValidationResult {
+    pub path: std::path::PathBuf,
+    pub diagnostics: Vec<FileDiagnostic>,
+}
+
+#[cfg(all(feature = "validation", not(target_arch = "wasm32")))]
+impl FileValidationResult {
+    /// Check if the file validated without errors
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Recursively validate every `code:` block in the `*.js` / `*.ts` files under
+/// `dir` (requires "validation" feature).
+///
+/// Intended as the entry point for CI linting of a whole bot project. Results
+/// are returned for every file, valid or not, sorted by path.
+#[cfg(all(feature = "validation", not(target_arch = "wasm32")))]
+pub fn validate_directory(
+    dir: &std::path::Path,
+    config: ValidationConfig,
+    metadata: Arc<MetadataManager>,
+) -> crate::metadata::Result<Vec<FileValidationResult>> {
+    let mut results = Vec::new();
+
+    let options = crate::metadata::ScanOptions::default();
+    crate::metadata::walk_js_ts_files(dir, &options, &mut |path, content| {
+        let (_, errors) = parse_with_validation(&content, config.clone(), metadata.clone());
+        let index = crate::utils::LineIndex::new(&content);
+        let diagnostics = errors
+            .into_iter()
+            .map(|error| {
+                let (line, column) = index.line_col(error.span.start);
+                FileDiagnostic {
+                    error,
+                    line,
+                    column,
+                }
+            })
+            .collect();
+
+        results.push(FileValidationResult {
+            path: path.to_path_buf(),
+            diagnostics,
+        });
+    })?;
+
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(results)
+}
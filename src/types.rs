@@ -16,8 +16,14 @@ pub struct Function {
     pub unwrap: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub args: Option<Vec<Arg>>,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub output: Option<JsonValue>,
+    /// Names of the types the function returns. Metadata may give a single
+    /// name or an array; both are read as a list.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_output",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output: Option<Vec<String>>,
     #[serde(default)]
     pub category: Option<String>,
     #[serde(default)]
@@ -36,11 +42,179 @@ pub struct Function {
     pub local_path: Option<PathBuf>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub line: Option<u32>,
+    /// Where the function was defined (core, custom, or an extension)
+    #[serde(default)]
+    pub origin: FunctionOrigin,
     /// Capture any unrecognized keys so future JSON additions don't break deserialization
     #[serde(flatten)]
     pub extra: HashMap<String, JsonValue>,
 }
 
+impl Function {
+    /// Copy without the heavy `examples` and `extra` fields, for compact listings
+    pub fn summary(&self) -> Self {
+        Self {
+            examples: None,
+            extra: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// The declared output types; empty when the function declares none
+    #[inline]
+    pub fn output_types(&self) -> &[String] {
+        self.output.as_deref().unwrap_or_default()
+    }
+
+    /// Check whether the function can return `ty` (case-insensitive)
+    pub fn produces(&self, ty: &str) -> bool {
+        self.output_types()
+            .iter()
+            .any(|output| output.eq_ignore_ascii_case(ty))
+    }
+
+    /// Call signature such as `$name[a;b?;c...]`, marking optional arguments
+    /// with `?` and rest arguments with `...`. Functions that take no
+    /// brackets show only their name.
+    pub fn signature(&self) -> String {
+        if self.brackets.is_none() {
+            return self.name.clone();
+        }
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .flatten()
+            .map(|arg| {
+                let mut text = arg.name.clone();
+                if arg.rest {
+                    text.push_str("...");
+                } else if !arg.required.unwrap_or(false) {
+                    text.push('?');
+                }
+                text
+            })
+            .collect();
+        format!("{}[{}]", self.name, args.join(";"))
+    }
+
+    /// Editor snippet (TextMate/LSP syntax) that inserts a call to the
+    /// function, e.g. `\$sendMessage[${1:channelID};${2:content}]`.
+    ///
+    /// Every position up to the last required argument gets a tab stop, a rest
+    /// argument a single one; trailing optional arguments are left out.
+    /// Brackets are omitted for functions that take none, and kept with an
+    /// empty tab stop when required but no argument is.
+    pub fn snippet(&self) -> String {
+        let name = escape_snippet(&self.name);
+        let Some(brackets_required) = self.brackets else {
+            return name;
+        };
+
+        let args = self.args.as_deref().unwrap_or_default();
+        let count = args
+            .iter()
+            .rposition(|arg| arg.required.unwrap_or(false))
+            .map_or(0, |i| i + 1);
+        let placeholders: Vec<String> = args[..count]
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                let suffix = if arg.rest { "..." } else { "" };
+                format!("${{{}:{}{}}}", i + 1, escape_snippet(&arg.name), suffix)
+            })
+            .collect();
+
+        match (placeholders.is_empty(), brackets_required) {
+            (false, _) => format!("{}[{}]", name, placeholders.join(";")),
+            (true, true) => format!("{}[$1]", name),
+            (true, false) => name,
+        }
+    }
+}
+
+/// Escape the characters that have meaning inside snippet text
+fn escape_snippet(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '$' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Editor completion entry for a function
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompletionItem {
+    /// Name shown in the completion list
+    pub label: String,
+    /// Snippet inserted on accept, see [`Function::snippet`]
+    pub insert_text: String,
+    /// Call signature, see [`Function::signature`]
+    pub detail: String,
+    /// The function's description
+    pub documentation: String,
+}
+
+impl From<&Function> for CompletionItem {
+    fn from(func: &Function) -> Self {
+        Self {
+            label: func.name.clone(),
+            insert_text: func.snippet(),
+            detail: func.signature(),
+            documentation: func.description.clone(),
+        }
+    }
+}
+
+/// Accept `output` as a type name or an array of them; anything else is
+/// treated as no declared output rather than failing the whole function.
+fn deserialize_output<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match JsonValue::deserialize(deserializer)? {
+        JsonValue::String(ty) => Some(vec![ty]),
+        JsonValue::Array(types) => Some(
+            types
+                .into_iter()
+                .filter_map(|ty| match ty {
+                    JsonValue::String(ty) => Some(ty),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => None,
+    })
+}
+
+/// Origin of a function definition
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum FunctionOrigin {
+    /// Built into ForgeScript itself
+    #[default]
+    Core,
+    /// User-defined, loaded from custom-functions JSON or JS/TS sources
+    Custom,
+    /// Provided by a named extension
+    Extension(String),
+}
+
+impl FunctionOrigin {
+    /// Derive the origin from a metadata source extension name.
+    ///
+    /// `None` and the core `forgescript` package map to [`FunctionOrigin::Core`];
+    /// any other name is treated as an extension.
+    pub fn from_extension(extension: Option<&str>) -> Self {
+        match extension {
+            None => Self::Core,
+            Some(ext) if ext.eq_ignore_ascii_case("forgescript") => Self::Core,
+            Some(ext) => Self::Extension(ext.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct Arg {
     pub name: String,
@@ -58,8 +232,10 @@ pub struct Arg {
     pub arg_enum: Option<Vec<String>>,
     #[serde(default)]
     pub enum_name: Option<String>,
+    /// Index of another argument of the same function this argument refers to
     #[serde(default)]
     pub pointer: Option<i64>,
+    /// Property of the pointed-to argument's value this argument refers to
     #[serde(default)]
     pub pointer_property: Option<String>,
     /// Capture unrecognized keys for forward compatibility
@@ -67,6 +243,15 @@ pub struct Arg {
     pub extra: HashMap<String, JsonValue>,
 }
 
+impl Arg {
+    /// Position of the argument this one points to, or `None` when there is no
+    /// pointer or it is negative
+    pub fn pointer_index(&self) -> Option<usize> {
+        self.pointer
+            .and_then(|pointer| usize::try_from(pointer).ok())
+    }
+}
+
 /// Event definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
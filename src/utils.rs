@@ -1,6 +1,10 @@
 //! Utility functions for working with the ForgeScript AST
 
-use crate::parser::{AstNode, Span};
+use crate::metadata::{MetadataManager, dollar_prefixed};
+use crate::parser::{Argument, AstNode, Modifiers, ParseError, ParseEvent, Span, parse_events};
+use crate::types::Function;
+use crate::visitor::fold;
+use std::collections::HashSet;
 
 /// Pretty-print the AST to a string
 pub fn format_ast(node: &AstNode) -> String {
@@ -67,10 +71,14 @@ fn format_ast_impl(node: &AstNode, output: &mut String, depth: usize) {
                 indent, span.start, span.end, code
             ));
         }
-        AstNode::Escaped { content, span } => {
+        AstNode::Escaped {
+            content,
+            function,
+            span,
+        } => {
             output.push_str(&format!(
-                "{}Escaped ({}..{}): {:?}\n",
-                indent, span.start, span.end, content
+                "{}Escaped ${} ({}..{}): {:?}\n",
+                indent, function, span.start, span.end, content
             ));
         }
     }
@@ -78,48 +86,178 @@ fn format_ast_impl(node: &AstNode, output: &mut String, depth: usize) {
 
 /// Extract all function names from the AST
 pub fn extract_function_names(node: &AstNode) -> Vec<String> {
-    let mut names = Vec::new();
-    extract_function_names_impl(node, &mut names);
-    names
+    fold(node, Vec::new(), |mut names, node| {
+        if let AstNode::FunctionCall { name, .. } = node {
+            names.push(name.clone());
+        }
+        names
+    })
 }
 
-fn extract_function_names_impl(node: &AstNode, names: &mut Vec<String>) {
-    match node {
-        AstNode::Program { body, .. } => {
-            for child in body {
-                extract_function_names_impl(child, names);
-            }
+/// Find the name spans (without `$` or modifiers) of every call to
+/// `function_name`, compared case-insensitively
+pub fn find_references(ast: &AstNode, function_name: &str) -> Vec<Span> {
+    let target = function_name.trim_start_matches('$');
+    collect_call_name_spans(ast, |name| name.eq_ignore_ascii_case(target))
+}
+
+/// Like [`find_references`], but calls through any alias of the same function
+/// count as references too
+pub fn find_references_with_metadata(
+    ast: &AstNode,
+    function_name: &str,
+    metadata: &MetadataManager,
+) -> Vec<Span> {
+    let target_name = function_name.trim_start_matches('$');
+    let Some(target) = metadata.get_exact(&dollar_prefixed(target_name)) else {
+        return find_references(ast, function_name);
+    };
+
+    collect_call_name_spans(ast, |name| {
+        name.eq_ignore_ascii_case(target_name)
+            || metadata
+                .get_exact(&dollar_prefixed(name))
+                .is_some_and(|func| same_function(&func, &target))
+    })
+}
+
+/// Alias entries are registered as copies named after the alias but keeping
+/// the canonical alias list, so two entries belong to the same function when
+/// either one's name is listed among the other's aliases
+fn same_function(a: &Function, b: &Function) -> bool {
+    let lists = |func: &Function, other: &Function| {
+        let name = other.name.trim_start_matches('$');
+        func.aliases
+            .iter()
+            .flatten()
+            .any(|alias| alias.trim_start_matches('$').eq_ignore_ascii_case(name))
+    };
+    a.name.eq_ignore_ascii_case(&b.name) || lists(a, b) || lists(b, a)
+}
+
+fn collect_call_name_spans(ast: &AstNode, mut matches: impl FnMut(&str) -> bool) -> Vec<Span> {
+    fold(ast, Vec::new(), |mut spans, node| {
+        if let AstNode::FunctionCall {
+            name, name_span, ..
+        } = node
+            && matches(name)
+        {
+            spans.push(Span::new(name_span.end - name.len(), name_span.end));
         }
-        AstNode::FunctionCall { name, args, .. } => {
-            names.push(name.clone());
-            if let Some(args) = args {
-                for arg in args {
-                    for part in &arg.parts {
-                        extract_function_names_impl(part, names);
-                    }
-                }
+        spans
+    })
+}
+
+/// A replacement of the source text covered by `span`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub new_text: String,
+}
+
+/// Build edits renaming every call to `from` into `to`, without touching the
+/// tree. Only the name is replaced, so `$` and modifiers are preserved; edits
+/// are sorted by offset and never overlap.
+pub fn rename_function(ast: &AstNode, from: &str, to: &str) -> Vec<TextEdit> {
+    let new_text = to.trim_start_matches('$');
+    let mut edits: Vec<TextEdit> = find_references(ast, from)
+        .into_iter()
+        .map(|span| TextEdit {
+            span,
+            new_text: new_text.to_string(),
+        })
+        .collect();
+    edits.sort_by_key(|edit| edit.span.start);
+    edits
+}
+
+/// An argument whose static text is a value of an enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumUsage {
+    pub function: String,
+    pub arg_name: String,
+    /// Empty for inline (unnamed) enums
+    pub enum_name: String,
+    pub value: String,
+    pub span: Span,
+}
+
+/// Collect every statically-known text argument that is a valid value of the
+/// enum its parameter accepts
+pub fn collect_enum_usages(ast: &AstNode, metadata: &MetadataManager) -> Vec<EnumUsage> {
+    fold(ast, Vec::new(), |mut usages, node| {
+        let AstNode::FunctionCall {
+            args: Some(args), ..
+        } = node
+        else {
+            return usages;
+        };
+        let Some(func) = node
+            .function_full_name()
+            .and_then(|name| metadata.get_exact(&name))
+        else {
+            return usages;
+        };
+        let Some(func_args) = &func.args else {
+            return usages;
+        };
+
+        for (i, arg) in args.iter().enumerate() {
+            let Some(func_arg) = func_args
+                .get(i)
+                .or_else(|| func_args.last().filter(|last| last.rest))
+            else {
+                break;
+            };
+            let (Some(values), Some(text)) = (metadata.arg_enum_values(func_arg), arg.as_text())
+            else {
+                continue;
+            };
+
+            let value = text.trim();
+            if values.iter().any(|v| v == value) {
+                usages.push(EnumUsage {
+                    function: func.name.clone(),
+                    arg_name: func_arg.name.clone(),
+                    enum_name: func_arg.enum_name.clone().unwrap_or_default(),
+                    value: value.to_string(),
+                    span: arg.span,
+                });
             }
         }
-        _ => {}
+        usages
+    })
+}
+
+/// Output type reported by [`infer_arg_type`] for functions that declare none
+pub const VOID_TYPE: &str = "Void";
+
+/// Infer the type an argument evaluates to when it is a single nested call.
+///
+/// Returns the call's declared output type, or [`VOID_TYPE`] when the
+/// function declares no output. Surrounding whitespace is ignored; mixed
+/// content, unknown functions and functions declaring several output types
+/// give `None`.
+pub fn infer_arg_type(arg: &Argument, metadata: &MetadataManager) -> Option<String> {
+    let mut calls = arg.parts.iter().filter(|part| match part {
+        AstNode::Text { content, .. } => !content.trim().is_empty(),
+        _ => true,
+    });
+    let (Some(AstNode::FunctionCall { name, .. }), None) = (calls.next(), calls.next()) else {
+        return None;
+    };
+
+    let mut types = metadata.output_types(name)?;
+    match types.len() {
+        0 => Some(VOID_TYPE.to_string()),
+        1 => types.pop(),
+        _ => None,
     }
 }
 
 /// Count the number of nodes in the AST
 pub fn count_nodes(node: &AstNode) -> usize {
-    match node {
-        AstNode::Program { body, .. } => 1 + body.iter().map(count_nodes).sum::<usize>(),
-        AstNode::FunctionCall { args, .. } => {
-            1 + args
-                .as_ref()
-                .map(|args| {
-                    args.iter()
-                        .map(|arg| arg.parts.iter().map(count_nodes).sum::<usize>())
-                        .sum()
-                })
-                .unwrap_or(0)
-        }
-        _ => 1,
-    }
+    fold(node, 0, |count, _| count + 1)
 }
 
 /// Get all text nodes from the AST
@@ -139,12 +277,12 @@ fn extract_text_nodes_impl(node: &AstNode, texts: &mut Vec<(String, Span)>) {
         AstNode::Text { content, span } => {
             texts.push((content.clone(), *span));
         }
-        AstNode::FunctionCall { args, .. } => {
-            if let Some(args) = args {
-                for arg in args {
-                    for part in &arg.parts {
-                        extract_text_nodes_impl(part, texts);
-                    }
+        AstNode::FunctionCall {
+            args: Some(args), ..
+        } => {
+            for arg in args {
+                for part in &arg.parts {
+                    extract_text_nodes_impl(part, texts);
                 }
             }
         }
@@ -152,6 +290,244 @@ fn extract_text_nodes_impl(node: &AstNode, texts: &mut Vec<(String, Span)>) {
     }
 }
 
+/// Concatenate the static text of the tree in source order: every `Text`
+/// node and escaped content, including those inside arguments.
+///
+/// Function calls and JavaScript contribute nothing, and neither do `$c[...]`
+/// comments since they are not output, so the result is a rough preview of
+/// the literal text a script produces.
+pub fn static_text(ast: &AstNode) -> String {
+    fold(ast, String::new(), |mut text, node| {
+        match node {
+            AstNode::Text { content, .. } => text.push_str(content),
+            AstNode::Escaped { content, .. } if !is_comment(node) => text.push_str(content),
+            _ => {}
+        }
+        text
+    })
+}
+
+/// Merge consecutive `Text` siblings into a single node per run
+///
+/// Runs are merged within the program body and within each argument; escaped,
+/// JavaScript and function call nodes act as boundaries.
+pub fn coalesce_text(ast: &mut AstNode) {
+    match ast {
+        AstNode::Program { body, .. } => coalesce_siblings(body),
+        AstNode::FunctionCall {
+            args: Some(args), ..
+        } => {
+            for arg in args {
+                coalesce_siblings(&mut arg.parts);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn coalesce_siblings<C>(nodes: &mut C)
+where
+    C: Default + IntoIterator<Item = AstNode> + Extend<AstNode>,
+{
+    let mut run: Option<(String, Span)> = None;
+    for mut node in std::mem::take(nodes) {
+        if let AstNode::Text { content, span } = node {
+            match &mut run {
+                Some((text, run_span)) => {
+                    text.push_str(&content);
+                    run_span.end = span.end;
+                }
+                None => run = Some((content, span)),
+            }
+            continue;
+        }
+
+        if let Some((content, span)) = run.take() {
+            nodes.extend([AstNode::Text { content, span }]);
+        }
+        coalesce_text(&mut node);
+        nodes.extend([node]);
+    }
+
+    if let Some((content, span)) = run {
+        nodes.extend([AstNode::Text { content, span }]);
+    }
+}
+
+/// Remove `$c[...]` comments from the tree, keeping other escaped nodes such
+/// as `$escape[...]`
+///
+/// Comments are removed from the program body and from every argument.
+pub fn strip_comments(ast: &mut AstNode) {
+    match ast {
+        AstNode::Program { body, .. } => {
+            body.retain(|node| !is_comment(node));
+            body.iter_mut().for_each(strip_comments);
+        }
+        AstNode::FunctionCall {
+            args: Some(args), ..
+        } => {
+            for arg in args {
+                arg.parts.retain(|node| !is_comment(node));
+                arg.parts.iter_mut().for_each(strip_comments);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_comment(node: &AstNode) -> bool {
+    matches!(node, AstNode::Escaped { function, .. } if function.eq_ignore_ascii_case("c"))
+}
+
+/// A difference between two trees reported by [`diff_ast`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstChange {
+    /// A node present only in the new tree
+    Added(Span),
+    /// A node present only in the old tree
+    Removed(Span),
+    /// A node whose content differs between the trees
+    Changed { old: Span, new: Span },
+}
+
+/// Compare two trees and report the subtrees that differ.
+///
+/// This is not an optimal tree diff: siblings in each body or argument are
+/// compared by position, so an insertion reports every following sibling as
+/// changed. Nodes with equal content are skipped even when their spans have
+/// moved. Calls with the same name, modifiers and argument count are
+/// descended into; any other difference reports the whole node.
+pub fn diff_ast(old: &AstNode, new: &AstNode) -> Vec<AstChange> {
+    let mut changes = Vec::new();
+    diff_nodes(old, new, &mut changes);
+    changes
+}
+
+fn diff_nodes(old: &AstNode, new: &AstNode, changes: &mut Vec<AstChange>) {
+    if ast_eq_ignoring_spans(old, new) {
+        return;
+    }
+    match (old, new) {
+        (AstNode::Program { body: old, .. }, AstNode::Program { body: new, .. }) => {
+            diff_siblings(old, new, changes);
+        }
+        (
+            AstNode::FunctionCall {
+                name: old_name,
+                modifiers: old_modifiers,
+                args: old_args,
+                ..
+            },
+            AstNode::FunctionCall {
+                name: new_name,
+                modifiers: new_modifiers,
+                args: new_args,
+                ..
+            },
+        ) if old_name == new_name
+            && same_modifiers(old_modifiers, new_modifiers)
+            && old_args.as_ref().map(Vec::len) == new_args.as_ref().map(Vec::len) =>
+        {
+            for (old, new) in old_args.iter().flatten().zip(new_args.iter().flatten()) {
+                diff_siblings(&old.parts, &new.parts, changes);
+            }
+        }
+        _ => changes.push(AstChange::Changed {
+            old: old.span(),
+            new: new.span(),
+        }),
+    }
+}
+
+fn diff_siblings(old: &[AstNode], new: &[AstNode], changes: &mut Vec<AstChange>) {
+    for (old, new) in old.iter().zip(new) {
+        diff_nodes(old, new, changes);
+    }
+    changes.extend(
+        old.iter()
+            .skip(new.len())
+            .map(|n| AstChange::Removed(n.span())),
+    );
+    changes.extend(
+        new.iter()
+            .skip(old.len())
+            .map(|n| AstChange::Added(n.span())),
+    );
+}
+
+/// Compare two trees by structure and content (names, modifiers, text,
+/// escaped content and code), ignoring every span.
+///
+/// Useful for asserting that sources parse to the same tree regardless of
+/// where the code sits in the file.
+pub fn ast_eq_ignoring_spans(a: &AstNode, b: &AstNode) -> bool {
+    match (a, b) {
+        (AstNode::Program { body: a, .. }, AstNode::Program { body: b, .. }) => same_siblings(a, b),
+        (
+            AstNode::Escaped {
+                content: a,
+                function: a_function,
+                ..
+            },
+            AstNode::Escaped {
+                content: b,
+                function: b_function,
+                ..
+            },
+        ) => a == b && a_function == b_function,
+        (AstNode::Text { content: a, .. }, AstNode::Text { content: b, .. })
+        | (AstNode::JavaScript { code: a, .. }, AstNode::JavaScript { code: b, .. }) => a == b,
+        (
+            AstNode::FunctionCall {
+                name: a_name,
+                modifiers: a_modifiers,
+                args: a_args,
+                ..
+            },
+            AstNode::FunctionCall {
+                name: b_name,
+                modifiers: b_modifiers,
+                args: b_args,
+                ..
+            },
+        ) => {
+            a_name == b_name
+                && same_modifiers(a_modifiers, b_modifiers)
+                && match (a_args, b_args) {
+                    (Some(a), Some(b)) => {
+                        a.len() == b.len()
+                            && a.iter()
+                                .zip(b)
+                                .all(|(a, b)| same_siblings(&a.parts, &b.parts))
+                    }
+                    (None, None) => true,
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+fn same_siblings(a: &[AstNode], b: &[AstNode]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| ast_eq_ignoring_spans(a, b))
+}
+
+fn same_modifiers(a: &Modifiers, b: &Modifiers) -> bool {
+    a.silent == b.silent && a.negated == b.negated && a.count == b.count
+}
+
+/// Get all JavaScript expressions from the AST, including those nested in
+/// arguments. An unclosed `${` appears with empty code.
+pub fn extract_javascript(node: &AstNode) -> Vec<(String, Span)> {
+    fold(node, Vec::new(), |mut scripts, node| {
+        if let AstNode::JavaScript { code, span } = node {
+            scripts.push((code.clone(), *span));
+        }
+        scripts
+    })
+}
+
 /// Find the deepest nesting level in the AST
 pub fn max_nesting_depth(node: &AstNode) -> usize {
     max_nesting_depth_impl(node, 0)
@@ -197,13 +573,233 @@ pub fn contains_javascript(node: &AstNode) -> bool {
     }
 }
 
+/// Group errors under the innermost function call whose span contains them
+///
+/// Errors outside every call are grouped under `None`. Groups appear in the
+/// order of their first error.
+pub fn group_errors_by_call<'e>(
+    ast: &AstNode,
+    errors: &'e [ParseError],
+) -> Vec<(Option<Span>, Vec<&'e ParseError>)> {
+    let mut groups: Vec<(Option<Span>, Vec<&ParseError>)> = Vec::new();
+    for error in errors {
+        let call = innermost_call_containing(ast, error.span);
+        match groups.iter_mut().find(|(span, _)| *span == call) {
+            Some((_, group)) => group.push(error),
+            None => groups.push((call, vec![error])),
+        }
+    }
+    groups
+}
+
+fn innermost_call_containing(node: &AstNode, target: Span) -> Option<Span> {
+    match node {
+        AstNode::Program { body, .. } => body
+            .iter()
+            .find_map(|child| innermost_call_containing(child, target)),
+        AstNode::FunctionCall { args, span, .. }
+            if span.start <= target.start && target.end <= span.end =>
+        {
+            args.iter()
+                .flatten()
+                .flat_map(|arg| arg.parts.iter())
+                .find_map(|part| innermost_call_containing(part, target))
+                .or(Some(*span))
+        }
+        _ => None,
+    }
+}
+
 /// Get a slice of the source code for a given span
-pub fn get_source_slice<'a>(source: &'a str, span: Span) -> &'a str {
+pub fn get_source_slice(source: &str, span: Span) -> &str {
     &source[span.start..span.end.min(source.len())]
 }
 
-/// Calculate statistics about the AST
+/// Iterate over the characters covered by a span of the source code.
+///
+/// Offsets are clamped to the source length and moved inwards to the nearest
+/// char boundary, so only characters lying fully inside the span are yielded
+/// and mid-char offsets never panic.
+pub fn span_chars(source: &str, span: Span) -> impl Iterator<Item = char> + '_ {
+    let mut start = span.start.min(source.len());
+    while !source.is_char_boundary(start) {
+        start += 1;
+    }
+    let mut end = span.end.min(source.len());
+    while !source.is_char_boundary(end) {
+        end -= 1;
+    }
+    source[start..end.max(start)].chars()
+}
+
+/// Convert a UTF-8 byte offset into `source` to a UTF-16 code unit offset,
+/// as used by browsers and LSP clients.
+///
+/// Offsets past the end are clamped; a mid-char offset counts only the
+/// characters that end at or before it.
+pub fn utf8_to_utf16_offset(source: &str, byte_offset: usize) -> usize {
+    let mut end = byte_offset.min(source.len());
+    while !source.is_char_boundary(end) {
+        end -= 1;
+    }
+    source[..end].chars().map(char::len_utf16).sum()
+}
+
+/// Convert a UTF-16 code unit offset into `source` to a UTF-8 byte offset.
+///
+/// Offsets past the end are clamped; an offset between the two halves of a
+/// surrogate pair maps to the start of that character.
+pub fn utf16_to_utf8_offset(source: &str, utf16_offset: usize) -> usize {
+    let mut units = 0;
+    for (i, ch) in source.char_indices() {
+        units += ch.len_utf16();
+        if units > utf16_offset {
+            return i;
+        }
+    }
+    source.len()
+}
+
+/// Lookup table from byte offsets to 0-based line and column positions,
+/// built once per source so repeated lookups don't rescan it
 #[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(memchr::memchr_iter(b'\n', source.as_bytes()).map(|i| i + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// The line and column (in chars) of `byte_offset`.
+    ///
+    /// Offsets past the end are clamped; a mid-char offset counts only the
+    /// characters that end at or before it.
+    pub fn line_col(&self, byte_offset: usize) -> (usize, usize) {
+        let mut end = byte_offset.min(self.source.len());
+        while !self.source.is_char_boundary(end) {
+            end -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= end) - 1;
+        let col = self.source[self.line_starts[line]..end].chars().count();
+        (line, col)
+    }
+
+    /// The text of 0-based `line` without its line ending, or `""` past the
+    /// last line
+    pub fn line_text(&self, line: usize) -> &'a str {
+        let Some(&start) = self.line_starts.get(line) else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.source.len(), |next| next - 1);
+        self.source[start..end].trim_end_matches('\r')
+    }
+}
+
+/// Render a diagnostic for display in a terminal, rustc style: the severity
+/// and kind, the 1-based location, and the offending line with carets under
+/// the span.
+///
+/// Spans running past the end of their first line are underlined to the end
+/// of that line.
+pub fn render_diagnostic(source: &str, error: &ParseError) -> String {
+    render_with_index(&LineIndex::new(source), error)
+}
+
+/// Render every diagnostic as by [`render_diagnostic`], separated by blank lines
+pub fn format_errors(source: &str, errors: &[ParseError]) -> String {
+    let index = LineIndex::new(source);
+    errors
+        .iter()
+        .map(|error| render_with_index(&index, error))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_with_index(index: &LineIndex, error: &ParseError) -> String {
+    let (line, col) = index.line_col(error.span.start);
+    let (end_line, end_col) = index.line_col(error.span.end);
+    let text = index.line_text(line);
+    let width = if end_line == line {
+        end_col.saturating_sub(col)
+    } else {
+        text.chars().count().saturating_sub(col)
+    };
+
+    let number = (line + 1).to_string();
+    let gutter = " ".repeat(number.len());
+    format!(
+        "{severity}[{kind:?}]: {message}\n\
+         {gutter}--> {line}:{col}\n\
+         {gutter} |\n\
+         {number} | {text}\n\
+         {gutter} | {pad}{carets}\n",
+        severity = error.severity,
+        kind = error.kind,
+        message = error.message,
+        line = line + 1,
+        col = col + 1,
+        pad = " ".repeat(col),
+        carets = "^".repeat(width.max(1)),
+    )
+}
+
+/// Number of single-character insertions, deletions and substitutions
+/// needed to turn `a` into `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Candidates closest to `query` by case-insensitive edit distance, for
+/// "did you mean" hints. Returns all candidates tied for the smallest
+/// distance, or nothing when none is within a third of the query's length.
+pub fn closest_matches<'a>(
+    query: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let query = query.to_lowercase();
+    let max_distance = (query.chars().count() / 3).max(1);
+
+    let mut best = Vec::new();
+    let mut best_distance = max_distance + 1;
+    for candidate in candidates {
+        let distance = levenshtein(&query, &candidate.to_lowercase());
+        if distance < best_distance {
+            best_distance = distance;
+            best.clear();
+        }
+        if distance == best_distance {
+            best.push(candidate);
+        }
+    }
+    best
+}
+
+/// Calculate statistics about the AST
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct AstStats {
     pub total_nodes: usize,
     pub text_nodes: usize,
@@ -244,6 +840,36 @@ pub fn calculate_stats(node: &AstNode) -> AstStats {
     }
 }
 
+/// Compute the same statistics as [`calculate_stats`] straight from source,
+/// streaming parse events instead of building the AST
+pub fn stats_of(source: &str) -> AstStats {
+    let mut stats = AstStats {
+        total_nodes: 1, // Program
+        ..Default::default()
+    };
+    let mut depth = 0;
+    let mut names = HashSet::new();
+
+    parse_events(source, &mut |event| match event {
+        ParseEvent::EnterFunction { name, .. } => {
+            stats.function_calls += 1;
+            depth += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            names.insert(name);
+        }
+        ParseEvent::ExitFunction => depth -= 1,
+        ParseEvent::Text { .. } => stats.text_nodes += 1,
+        ParseEvent::JavaScript { .. } => stats.javascript_nodes += 1,
+        ParseEvent::Escaped { .. } => stats.escaped_nodes += 1,
+        ParseEvent::Error(_) => {}
+    });
+
+    stats.total_nodes +=
+        stats.text_nodes + stats.function_calls + stats.javascript_nodes + stats.escaped_nodes;
+    stats.unique_functions = names.len();
+    stats
+}
+
 fn count_node_types(
     node: &AstNode,
     text: &mut usize,
@@ -275,28 +901,108 @@ fn count_node_types(
 
 /// Flatten the AST into a linear sequence of nodes (depth-first)
 pub fn flatten_ast(node: &AstNode) -> Vec<AstNode> {
-    let mut nodes = Vec::new();
-    flatten_ast_impl(node, &mut nodes);
-    nodes
+    iter_nodes(node).cloned().collect()
+}
+
+/// Iterate over every node by reference in depth-first pre-order, without
+/// cloning the tree
+pub fn iter_nodes(root: &AstNode) -> impl Iterator<Item = &AstNode> {
+    let mut stack = vec![root];
+    std::iter::from_fn(move || {
+        let node = stack.pop()?;
+        match node {
+            AstNode::Program { body, .. } => stack.extend(body.iter().rev()),
+            AstNode::FunctionCall {
+                args: Some(args), ..
+            } => stack.extend(args.iter().rev().flat_map(|arg| arg.parts.iter().rev())),
+            _ => {}
+        }
+        Some(node)
+    })
 }
 
-fn flatten_ast_impl(node: &AstNode, nodes: &mut Vec<AstNode>) {
-    nodes.push(node.clone());
+// ============================================================================
+// AST Paths
+// ============================================================================
+
+/// Structural address of a node: child indices from the root.
+///
+/// Children of a `Program` are its body nodes; children of a `FunctionCall`
+/// are the parts of all its arguments, in source order.
+pub type AstPath = Vec<usize>;
+
+fn child_nodes(node: &AstNode) -> Vec<&AstNode> {
     match node {
-        AstNode::Program { body, .. } => {
-            for child in body {
-                flatten_ast_impl(child, nodes);
-            }
+        AstNode::Program { body, .. } => body.iter().collect(),
+        AstNode::FunctionCall {
+            args: Some(args), ..
+        } => args.iter().flat_map(|arg| arg.parts.iter()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Resolve a path to the node it addresses
+pub fn node_at_path<'a>(root: &'a AstNode, path: &AstPath) -> Option<&'a AstNode> {
+    path.iter()
+        .try_fold(root, |node, &index| child_nodes(node).get(index).copied())
+}
+
+/// Find the path of the deepest node whose span equals `target_span`
+pub fn path_to(root: &AstNode, target_span: Span) -> Option<AstPath> {
+    let span = root.span();
+    if target_span.start < span.start || target_span.end > span.end {
+        return None;
+    }
+
+    for (index, child) in child_nodes(root).into_iter().enumerate() {
+        if let Some(mut path) = path_to(child, target_span) {
+            path.insert(0, index);
+            return Some(path);
         }
-        AstNode::FunctionCall { args, .. } => {
-            if let Some(args) = args {
-                for arg in args {
-                    for part in &arg.parts {
-                        flatten_ast_impl(part, nodes);
-                    }
-                }
+    }
+
+    (span == target_span).then(Vec::new)
+}
+
+// ============================================================================
+// Whitespace Normalization
+// ============================================================================
+
+/// Options for [`normalize_whitespace`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WhitespaceOptions {
+    /// Collapse runs of blank lines into a single blank line
+    pub collapse_blank_lines: bool,
+    /// Convert `\r\n` and lone `\r` line endings to `\n`
+    pub normalize_line_endings: bool,
+    /// Ensure non-empty output ends with a newline
+    pub ensure_trailing_newline: bool,
+}
+
+/// Normalize newlines and blank lines in reconstructed source
+pub fn normalize_whitespace(source: &str, options: &WhitespaceOptions) -> String {
+    let mut output = if options.normalize_line_endings {
+        source.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        source.to_string()
+    };
+
+    if options.collapse_blank_lines {
+        let mut collapsed = String::with_capacity(output.len());
+        let mut previous_blank = false;
+        for line in output.split_inclusive('\n') {
+            let blank = line.trim().is_empty() && line.ends_with('\n');
+            if !(blank && previous_blank) {
+                collapsed.push_str(line);
             }
+            previous_blank = blank;
         }
-        _ => {}
+        output = collapsed;
+    }
+
+    if options.ensure_trailing_newline && !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
     }
+
+    output
 }
@@ -4,6 +4,7 @@
 //! without modifying the core parser code.
 
 use crate::parser::{Argument, AstNode, Modifiers, Span};
+use std::ops::ControlFlow;
 
 /// Trait for visiting AST nodes
 pub trait AstVisitor {
@@ -27,11 +28,9 @@ pub trait AstVisitor {
         modifiers: &Modifiers,
         span: Span,
     ) {
-        let _ = (name, modifiers, span);
-        if let Some(args) = args {
-            for arg in args {
-                self.visit_argument(arg);
-            }
+        let _ = (modifiers, span);
+        for (index, arg) in args.into_iter().flatten().enumerate() {
+            self.visit_argument_indexed(name, index, arg);
         }
     }
 
@@ -42,6 +41,16 @@ pub trait AstVisitor {
         }
     }
 
+    /// Visit the argument at position `index` of a call to `function_name`.
+    ///
+    /// Called by [`visit_function_call`](AstVisitor::visit_function_call)
+    /// for each argument; delegates to
+    /// [`visit_argument`](AstVisitor::visit_argument) by default.
+    fn visit_argument_indexed(&mut self, function_name: &str, index: usize, arg: &Argument) {
+        let _ = (function_name, index);
+        self.visit_argument(arg);
+    }
+
     /// Visit a JavaScript expression node
     fn visit_javascript(&mut self, code: &str, span: Span) {
         let _ = (code, span);
@@ -65,7 +74,7 @@ pub trait AstVisitor {
                 ..
             } => self.visit_function_call(name, args.as_ref(), modifiers, *span),
             AstNode::JavaScript { code, span } => self.visit_javascript(code, *span),
-            AstNode::Escaped { content, span } => self.visit_escaped(content, *span),
+            AstNode::Escaped { content, span, .. } => self.visit_escaped(content, *span),
         }
     }
 }
@@ -75,6 +84,12 @@ pub struct FunctionCollector {
     pub functions: Vec<String>,
 }
 
+impl Default for FunctionCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FunctionCollector {
     pub fn new() -> Self {
         Self {
@@ -95,10 +110,8 @@ impl AstVisitor for FunctionCollector {
 
         // Continue visiting arguments
         let _ = (modifiers, span);
-        if let Some(args) = args {
-            for arg in args {
-                self.visit_argument(arg);
-            }
+        for (index, arg) in args.into_iter().flatten().enumerate() {
+            self.visit_argument_indexed(name, index, arg);
         }
     }
 }
@@ -119,16 +132,14 @@ impl AstVisitor for NodeCounter {
 
     fn visit_function_call(
         &mut self,
-        _name: &str,
+        name: &str,
         args: Option<&Vec<Argument>>,
         _modifiers: &Modifiers,
         _span: Span,
     ) {
         self.function_nodes += 1;
-        if let Some(args) = args {
-            for arg in args {
-                self.visit_argument(arg);
-            }
+        for (index, arg) in args.into_iter().flatten().enumerate() {
+            self.visit_argument_indexed(name, index, arg);
         }
     }
 
@@ -156,7 +167,7 @@ pub trait AstVisitorMut {
                 ..
             } => self.visit_function_call_mut(name, args, modifiers, *span),
             AstNode::JavaScript { code, span } => self.visit_javascript_mut(code, *span),
-            AstNode::Escaped { content, span } => self.visit_escaped_mut(content, *span),
+            AstNode::Escaped { content, span, .. } => self.visit_escaped_mut(content, *span),
         }
     }
 
@@ -196,3 +207,436 @@ pub trait AstVisitorMut {
         let _ = (content, span);
     }
 }
+
+/// Pre-order fold over every node in the tree, descending into arguments
+///
+/// Nodes are visited in the same order as [`AstVisitor::visit`].
+pub fn fold<T>(node: &AstNode, init: T, mut f: impl FnMut(T, &AstNode) -> T) -> T {
+    fold_impl(node, init, &mut f)
+}
+
+fn fold_impl<T, F: FnMut(T, &AstNode) -> T>(node: &AstNode, acc: T, f: &mut F) -> T {
+    let mut acc = f(acc, node);
+    match node {
+        AstNode::Program { body, .. } => {
+            for child in body {
+                acc = fold_impl(child, acc, f);
+            }
+        }
+        AstNode::FunctionCall {
+            args: Some(args), ..
+        } => {
+            for arg in args {
+                for part in &arg.parts {
+                    acc = fold_impl(part, acc, f);
+                }
+            }
+        }
+        _ => {}
+    }
+    acc
+}
+
+/// Context maintained by [`ContextVisitor`] while descending into arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VisitContext<'a> {
+    /// Number of enclosing function calls (0 at the top level)
+    pub depth: usize,
+    /// Name of the innermost enclosing function call
+    pub parent_function: Option<&'a str>,
+    /// Index of the argument of `parent_function` being visited
+    pub arg_index: Option<usize>,
+}
+
+/// Visitor trait whose callbacks receive the nesting context of each node
+///
+/// Start a traversal with `visit_ctx(&ast, &VisitContext::default())`. When
+/// overriding [`visit_function_call_ctx`](Self::visit_function_call_ctx), call
+/// [`visit_arguments_ctx`](Self::visit_arguments_ctx) to keep descending with
+/// an updated context.
+pub trait ContextVisitor {
+    /// Visit a program node
+    fn visit_program_ctx(&mut self, body: &[AstNode], _span: Span, ctx: &VisitContext<'_>) {
+        for node in body {
+            self.visit_ctx(node, ctx);
+        }
+    }
+
+    /// Visit a text node
+    fn visit_text_ctx(&mut self, content: &str, span: Span, ctx: &VisitContext<'_>) {
+        let _ = (content, span, ctx);
+    }
+
+    /// Visit a function call node
+    fn visit_function_call_ctx(
+        &mut self,
+        name: &str,
+        args: Option<&Vec<Argument>>,
+        modifiers: &Modifiers,
+        span: Span,
+        ctx: &VisitContext<'_>,
+    ) {
+        let _ = (modifiers, span);
+        self.visit_arguments_ctx(name, args, ctx);
+    }
+
+    /// Visit the arguments of the function `name`, one level deeper than `ctx`
+    fn visit_arguments_ctx(
+        &mut self,
+        name: &str,
+        args: Option<&Vec<Argument>>,
+        ctx: &VisitContext<'_>,
+    ) {
+        if let Some(args) = args {
+            for (i, arg) in args.iter().enumerate() {
+                let child = VisitContext {
+                    depth: ctx.depth + 1,
+                    parent_function: Some(name),
+                    arg_index: Some(i),
+                };
+                self.visit_argument_ctx(arg, &child);
+            }
+        }
+    }
+
+    /// Visit an argument
+    fn visit_argument_ctx(&mut self, arg: &Argument, ctx: &VisitContext<'_>) {
+        for part in &arg.parts {
+            self.visit_ctx(part, ctx);
+        }
+    }
+
+    /// Visit a JavaScript expression node
+    fn visit_javascript_ctx(&mut self, code: &str, span: Span, ctx: &VisitContext<'_>) {
+        let _ = (code, span, ctx);
+    }
+
+    /// Visit an escaped content node
+    fn visit_escaped_ctx(&mut self, content: &str, span: Span, ctx: &VisitContext<'_>) {
+        let _ = (content, span, ctx);
+    }
+
+    /// Dispatch to the appropriate visit method
+    fn visit_ctx(&mut self, node: &AstNode, ctx: &VisitContext<'_>) {
+        match node {
+            AstNode::Program { body, span } => self.visit_program_ctx(body, *span, ctx),
+            AstNode::Text { content, span } => self.visit_text_ctx(content, *span, ctx),
+            AstNode::FunctionCall {
+                name,
+                args,
+                modifiers,
+                span,
+                ..
+            } => self.visit_function_call_ctx(name, args.as_ref(), modifiers, *span, ctx),
+            AstNode::JavaScript { code, span } => self.visit_javascript_ctx(code, *span, ctx),
+            AstNode::Escaped { content, span, .. } => self.visit_escaped_ctx(content, *span, ctx),
+        }
+    }
+}
+
+/// Example context visitor that records each function name with its depth
+#[derive(Default)]
+pub struct FunctionDepthCollector {
+    pub functions: Vec<(String, usize)>,
+}
+
+impl ContextVisitor for FunctionDepthCollector {
+    fn visit_function_call_ctx(
+        &mut self,
+        name: &str,
+        args: Option<&Vec<Argument>>,
+        _modifiers: &Modifiers,
+        _span: Span,
+        ctx: &VisitContext<'_>,
+    ) {
+        self.functions.push((name.to_string(), ctx.depth));
+        self.visit_arguments_ctx(name, args, ctx);
+    }
+}
+
+/// Visitor trait whose callbacks can stop the traversal early
+///
+/// Every callback returns [`ControlFlow`]; returning `ControlFlow::Break`
+/// unwinds the whole traversal and hands the break value back to the caller
+/// of [`TryVisitor::try_visit`]. The `'ast` lifetime lets break values borrow
+/// from the tree.
+pub trait TryVisitor<'ast> {
+    /// Value produced when the traversal is stopped
+    type Break;
+
+    /// Called for every node before it is dispatched
+    fn try_enter(&mut self, node: &'ast AstNode) -> ControlFlow<Self::Break> {
+        let _ = node;
+        ControlFlow::Continue(())
+    }
+
+    /// Visit a program node
+    fn try_visit_program(
+        &mut self,
+        body: &'ast [AstNode],
+        _span: Span,
+    ) -> ControlFlow<Self::Break> {
+        for node in body {
+            self.try_visit(node)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Visit a text node
+    fn try_visit_text(&mut self, content: &'ast str, span: Span) -> ControlFlow<Self::Break> {
+        let _ = (content, span);
+        ControlFlow::Continue(())
+    }
+
+    /// Visit a function call node
+    fn try_visit_function_call(
+        &mut self,
+        name: &'ast str,
+        args: Option<&'ast Vec<Argument>>,
+        modifiers: &'ast Modifiers,
+        span: Span,
+    ) -> ControlFlow<Self::Break> {
+        let _ = (name, modifiers, span);
+        if let Some(args) = args {
+            for arg in args {
+                self.try_visit_argument(arg)?;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Visit an argument
+    fn try_visit_argument(&mut self, arg: &'ast Argument) -> ControlFlow<Self::Break> {
+        for part in &arg.parts {
+            self.try_visit(part)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Visit a JavaScript expression node
+    fn try_visit_javascript(&mut self, code: &'ast str, span: Span) -> ControlFlow<Self::Break> {
+        let _ = (code, span);
+        ControlFlow::Continue(())
+    }
+
+    /// Visit an escaped content node
+    fn try_visit_escaped(&mut self, content: &'ast str, span: Span) -> ControlFlow<Self::Break> {
+        let _ = (content, span);
+        ControlFlow::Continue(())
+    }
+
+    /// Dispatch to the appropriate visit method
+    fn try_visit(&mut self, node: &'ast AstNode) -> ControlFlow<Self::Break> {
+        self.try_enter(node)?;
+        match node {
+            AstNode::Program { body, span } => self.try_visit_program(body, *span),
+            AstNode::Text { content, span } => self.try_visit_text(content, *span),
+            AstNode::FunctionCall {
+                name,
+                args,
+                modifiers,
+                span,
+                ..
+            } => self.try_visit_function_call(name, args.as_ref(), modifiers, *span),
+            AstNode::JavaScript { code, span } => self.try_visit_javascript(code, *span),
+            AstNode::Escaped { content, span, .. } => self.try_visit_escaped(content, *span),
+        }
+    }
+}
+
+/// Visitor that stops at the first node matching a predicate
+struct FirstMatch<F> {
+    predicate: F,
+}
+
+impl<'ast, F: FnMut(&AstNode) -> bool> TryVisitor<'ast> for FirstMatch<F> {
+    type Break = &'ast AstNode;
+
+    fn try_enter(&mut self, node: &'ast AstNode) -> ControlFlow<Self::Break> {
+        if (self.predicate)(node) {
+            ControlFlow::Break(node)
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Find the first node (in pre-order) matching `predicate`, stopping the
+/// traversal as soon as it is found
+pub fn find_first(root: &AstNode, predicate: impl FnMut(&AstNode) -> bool) -> Option<&AstNode> {
+    let mut finder = FirstMatch { predicate };
+    match finder.try_visit(root) {
+        ControlFlow::Break(node) => Some(node),
+        ControlFlow::Continue(()) => None,
+    }
+}
+
+/// Kind of a [`SemanticToken`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SemanticTokenKind {
+    FunctionName,
+    Modifier,
+    Bracket,
+    ArgumentSeparator,
+    JavaScript,
+    EscapedContent,
+    Text,
+}
+
+/// Token type legend for [`lsp_semantic_tokens`], to advertise in the server's
+/// `SemanticTokensLegend`
+pub const LSP_TOKEN_TYPES: &[&str] = &["function", "modifier", "operator", "macro", "string"];
+
+impl SemanticTokenKind {
+    /// Index into [`LSP_TOKEN_TYPES`], or `None` for plain text
+    pub fn lsp_type_index(self) -> Option<u32> {
+        match self {
+            Self::FunctionName => Some(0),
+            Self::Modifier => Some(1),
+            Self::Bracket | Self::ArgumentSeparator => Some(2),
+            Self::JavaScript => Some(3),
+            Self::EscapedContent => Some(4),
+            Self::Text => None,
+        }
+    }
+}
+
+/// Classified source range for editor syntax highlighting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub span: Span,
+    pub kind: SemanticTokenKind,
+}
+
+/// Build semantic tokens for the tree, in source order and non-overlapping
+///
+/// Function calls are split into their `$`, modifiers, name, brackets and
+/// `;` separators; empty ranges are omitted.
+pub fn semantic_tokens(ast: &AstNode) -> Vec<SemanticToken> {
+    let mut tokens = Vec::new();
+    collect_semantic_tokens(ast, &mut tokens);
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<SemanticToken>, span: Span, kind: SemanticTokenKind) {
+    if span.end > span.start {
+        tokens.push(SemanticToken { span, kind });
+    }
+}
+
+fn collect_semantic_tokens(node: &AstNode, tokens: &mut Vec<SemanticToken>) {
+    match node {
+        AstNode::Program { body, .. } => {
+            for child in body {
+                collect_semantic_tokens(child, tokens);
+            }
+        }
+        AstNode::Text { span, .. } => push_token(tokens, *span, SemanticTokenKind::Text),
+        AstNode::JavaScript { span, .. } => {
+            push_token(tokens, *span, SemanticTokenKind::JavaScript)
+        }
+        AstNode::Escaped { span, .. } => {
+            push_token(tokens, *span, SemanticTokenKind::EscapedContent)
+        }
+        AstNode::FunctionCall {
+            args,
+            modifiers,
+            span,
+            name_span,
+            open_bracket,
+            close_bracket,
+            separators,
+            ..
+        } => {
+            // `name_span` starts at the `$` and runs across any modifiers
+            let name_start = match modifiers.span {
+                Some(modifier_span) => {
+                    push_token(
+                        tokens,
+                        Span::new(span.start, modifier_span.start),
+                        SemanticTokenKind::FunctionName,
+                    );
+                    let mut parts = [
+                        modifiers.silent_span,
+                        modifiers.negated_span,
+                        modifiers.count_span,
+                    ];
+                    parts.sort_by_key(|part| part.map(|s| s.start));
+                    for part in parts.into_iter().flatten() {
+                        push_token(tokens, part, SemanticTokenKind::Modifier);
+                    }
+                    modifier_span.end
+                }
+                None => span.start,
+            };
+            push_token(
+                tokens,
+                Span::new(name_start, name_span.end),
+                SemanticTokenKind::FunctionName,
+            );
+
+            if let (Some(open), Some(close), Some(args)) = (open_bracket, close_bracket, args) {
+                push_token(tokens, *open, SemanticTokenKind::Bracket);
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        push_token(
+                            tokens,
+                            separators[i - 1],
+                            SemanticTokenKind::ArgumentSeparator,
+                        );
+                    }
+                    for part in &arg.parts {
+                        collect_semantic_tokens(part, tokens);
+                    }
+                }
+                push_token(tokens, *close, SemanticTokenKind::Bracket);
+            }
+        }
+    }
+}
+
+/// Encode the tree's semantic tokens in the LSP `textDocument/semanticTokens/full`
+/// format: five integers per token (delta line, delta start, length, token
+/// type, modifier bitset)
+///
+/// Positions are in UTF-16 code units. Tokens spanning several lines are split
+/// per line, plain text is left unhighlighted, and no modifiers are set.
+pub fn lsp_semantic_tokens(ast: &AstNode, source: &str) -> Vec<u32> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+
+    let mut data = Vec::new();
+    let (mut prev_line, mut prev_col) = (0, 0);
+    for token in semantic_tokens(ast) {
+        let Some(token_type) = token.kind.lsp_type_index() else {
+            continue;
+        };
+        let end = token.span.end.min(source.len());
+        let mut start = token.span.start;
+
+        while start < end {
+            let line = line_starts.partition_point(|&s| s <= start) - 1;
+            let segment_end = source[start..end].find('\n').map_or(end, |i| start + i);
+            let col = utf16_len(&source[line_starts[line]..start]);
+            let len = utf16_len(&source[start..segment_end]);
+
+            if len > 0 {
+                let line = line as u32;
+                let delta_col = if line == prev_line {
+                    col - prev_col
+                } else {
+                    col
+                };
+                data.extend([line - prev_line, delta_col, len, token_type, 0]);
+                (prev_line, prev_col) = (line, col);
+            }
+            start = segment_end + 1;
+        }
+    }
+    data
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
@@ -4,7 +4,7 @@
 
 #![cfg(feature = "wasm")]
 
-use crate::metadata::{MetadataManager, MetadataSource, github_source};
+use crate::metadata::{MetadataError, MetadataManager, MetadataSource, github_source};
 use crate::parser::{ValidationConfig, parse as rust_parse};
 use crate::types::Function;
 use crate::utils::{calculate_stats, extract_function_names, format_ast};
@@ -29,6 +29,9 @@ pub fn init() {
 // ============================================================================
 
 /// Parse ForgeScript source code (no validation)
+///
+/// Returns `{ ast, errors }` where `ast` is the serialized tree (nodes tagged
+/// by `type`, with spans and nested args). Use `formatAst` for a readable dump.
 #[wasm_bindgen(js_name = "parse")]
 pub fn parse_wasm(source: &str) -> JsValue {
     let (ast, errors) = rust_parse(source);
@@ -45,21 +48,21 @@ pub fn parse_wasm(source: &str) -> JsValue {
         .collect();
 
     let result = serde_json::json!({
-        "ast": format_ast(&ast),
+        "ast": ast,
         "errors": errors_json,
     });
 
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
 
-/// Parse and return an error if there are any parse errors, otherwise return the AST string
+/// Parse and return an error if there are any parse errors, otherwise return the AST
 #[wasm_bindgen(js_name = "parseOrError")]
 pub fn parse_or_error_wasm(source: &str) -> JsValue {
     match crate::parser::parse_with_errors(source) {
         Ok(ast) => {
             let result = serde_json::json!({
                 "ok": true,
-                "ast": format_ast(&ast),
+                "ast": ast,
             });
             serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
         }
@@ -86,56 +89,77 @@ pub fn parse_or_error_wasm(source: &str) -> JsValue {
 /// Parse with a specific validation config object
 ///
 /// `config` should be a JS object with boolean fields:
-/// `validateArguments`, `validateEnums`, `validateFunctions`, `validateBrackets`
+/// `validateArguments`, `validateEnums`, `validateFunctions`, `validateBrackets`,
+/// `validateUnwrapUsage`, `validateModifiers`, `strictText`, `validateBlocks`,
+/// `forbidJavascript`, `denyDeprecated`, `warnExperimental`, `enumCaseSensitive`,
+/// and the number `maxErrors`. Set `utf16Spans` to report error spans as UTF-16
+/// code unit offsets instead of UTF-8 byte offsets.
+#[cfg(feature = "validation")]
 #[wasm_bindgen(js_name = "parseWithConfig")]
 pub fn parse_with_config_wasm(source: &str, config: JsValue) -> JsValue {
-    // Parse config from JS object
-    let validate_arguments = js_sys::Reflect::get(&config, &JsValue::from_str("validateArguments"))
-        .ok()
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let validate_enums = js_sys::Reflect::get(&config, &JsValue::from_str("validateEnums"))
-        .ok()
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let validate_functions = js_sys::Reflect::get(&config, &JsValue::from_str("validateFunctions"))
-        .ok()
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let validate_brackets = js_sys::Reflect::get(&config, &JsValue::from_str("validateBrackets"))
-        .ok()
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-
-    let cfg = ValidationConfig {
-        validate_arguments,
-        validate_enums,
-        validate_functions,
-        validate_brackets,
-    };
+    let cfg = validation_config_from_js(&config);
+    let utf16_spans = js_flag(&config, "utf16Spans");
 
     let (ast, errors) = crate::parser::parse_with_config(source, cfg);
 
     let errors_json: Vec<serde_json::Value> = errors
         .into_iter()
         .map(|e| {
+            let (start, end) = if utf16_spans {
+                e.span.to_utf16(source)
+            } else {
+                (e.span.start, e.span.end)
+            };
             serde_json::json!({
                 "message": e.message,
-                "span": { "start": e.span.start, "end": e.span.end },
+                "span": { "start": start, "end": end },
                 "kind": format!("{:?}", e.kind),
             })
         })
         .collect();
 
     let result = serde_json::json!({
-        "ast": format_ast(&ast),
+        "ast": ast,
         "errors": errors_json,
     });
 
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
 
+/// Read a boolean field from a JS object, treating anything else as `false`
+#[cfg(feature = "validation")]
+fn js_flag(object: &JsValue, key: &str) -> bool {
+    js_sys::Reflect::get(object, &JsValue::from_str(key))
+        .ok()
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Build a [`ValidationConfig`] from the fields of a JS config object
+#[cfg(feature = "validation")]
+fn validation_config_from_js(config: &JsValue) -> ValidationConfig {
+    ValidationConfig {
+        validate_arguments: js_flag(config, "validateArguments"),
+        validate_enums: js_flag(config, "validateEnums"),
+        validate_functions: js_flag(config, "validateFunctions"),
+        validate_brackets: js_flag(config, "validateBrackets"),
+        validate_unwrap_usage: js_flag(config, "validateUnwrapUsage"),
+        validate_modifiers: js_flag(config, "validateModifiers"),
+        strict_text: js_flag(config, "strictText"),
+        validate_blocks: js_flag(config, "validateBlocks"),
+        forbid_javascript: js_flag(config, "forbidJavascript"),
+        deny_deprecated: js_flag(config, "denyDeprecated"),
+        warn_experimental: js_flag(config, "warnExperimental"),
+        enum_case_sensitive: js_flag(config, "enumCaseSensitive"),
+        max_errors: js_sys::Reflect::get(config, &JsValue::from_str("maxErrors"))
+            .ok()
+            .and_then(|v| v.as_f64())
+            .map(|n| n as usize),
+    }
+}
+
 /// Parse with validation (requires metadata)
+#[cfg(feature = "validation")]
 #[wasm_bindgen(js_name = "parseWithValidation")]
 pub fn parse_with_validation_wasm(
     source: &str,
@@ -150,6 +174,7 @@ pub fn parse_with_validation_wasm(
         validate_enums,
         validate_functions,
         validate_brackets,
+        ..Default::default()
     };
 
     let (ast, errors) =
@@ -167,7 +192,7 @@ pub fn parse_with_validation_wasm(
         .collect();
 
     let result = serde_json::json!({
-        "ast": format_ast(&ast),
+        "ast": ast,
         "errors": errors_json,
     });
 
@@ -175,6 +200,7 @@ pub fn parse_with_validation_wasm(
 }
 
 /// Parse with strict validation (all validations enabled)
+#[cfg(feature = "validation")]
 #[wasm_bindgen(js_name = "parseStrict")]
 pub fn parse_strict_wasm(source: &str, metadata_wrapper: &MetadataManagerWrapper) -> JsValue {
     let (ast, errors) = crate::parser::parse_strict(source, metadata_wrapper.manager.clone());
@@ -191,7 +217,7 @@ pub fn parse_strict_wasm(source: &str, metadata_wrapper: &MetadataManagerWrapper
         .collect();
 
     let result = serde_json::json!({
-        "ast": format_ast(&ast),
+        "ast": ast,
         "errors": errors_json,
     });
 
@@ -209,6 +235,15 @@ pub fn validation_config_strict() -> JsValue {
         "validateEnums": cfg.validate_enums,
         "validateFunctions": cfg.validate_functions,
         "validateBrackets": cfg.validate_brackets,
+        "validateUnwrapUsage": cfg.validate_unwrap_usage,
+        "validateModifiers": cfg.validate_modifiers,
+        "strictText": cfg.strict_text,
+        "validateBlocks": cfg.validate_blocks,
+        "forbidJavascript": cfg.forbid_javascript,
+        "denyDeprecated": cfg.deny_deprecated,
+        "warnExperimental": cfg.warn_experimental,
+        "enumCaseSensitive": cfg.enum_case_sensitive,
+        "maxErrors": cfg.max_errors,
     })
     .pipe(|v| serde_wasm_bindgen::to_value(&v).unwrap_or(JsValue::NULL))
 }
@@ -224,6 +259,15 @@ pub fn validation_config_syntax_only() -> JsValue {
         "validateEnums": cfg.validate_enums,
         "validateFunctions": cfg.validate_functions,
         "validateBrackets": cfg.validate_brackets,
+        "validateUnwrapUsage": cfg.validate_unwrap_usage,
+        "validateModifiers": cfg.validate_modifiers,
+        "strictText": cfg.strict_text,
+        "validateBlocks": cfg.validate_blocks,
+        "forbidJavascript": cfg.forbid_javascript,
+        "denyDeprecated": cfg.deny_deprecated,
+        "warnExperimental": cfg.warn_experimental,
+        "enumCaseSensitive": cfg.enum_case_sensitive,
+        "maxErrors": cfg.max_errors,
     })
     .pipe(|v| serde_wasm_bindgen::to_value(&v).unwrap_or(JsValue::NULL))
 }
@@ -286,11 +330,23 @@ impl MetadataManagerWrapper {
         future_to_promise(async move {
             match manager.fetch_all().await {
                 Ok(stats) => {
+                    let errors: Vec<serde_json::Value> = stats
+                        .errors
+                        .iter()
+                        .map(|e| {
+                            serde_json::json!({
+                                "extension": e.extension,
+                                "kind": e.kind.to_string().to_lowercase(),
+                                "error": metadata_error_type(&e.source),
+                                "message": e.to_string(),
+                            })
+                        })
+                        .collect();
                     let result = serde_json::json!({
                         "functions": stats.functions,
                         "enums": stats.enums,
                         "events": stats.events,
-                        "errors": stats.errors,
+                        "errors": errors,
                     });
 
                     Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
@@ -311,6 +367,12 @@ impl MetadataManagerWrapper {
             .map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
+    /// Shorter name for `addCustomFunctionsFromJson`
+    #[wasm_bindgen(js_name = "addCustomFunctions")]
+    pub fn add_custom_functions(&self, json: &str) -> Result<usize, JsValue> {
+        self.add_custom_functions_from_json(json)
+    }
+
     /// Remove all custom functions previously added via `addCustomFunctionsFromJson`
     #[wasm_bindgen(js_name = "removeCustomFunctions")]
     pub fn remove_custom_functions(&self) {
@@ -389,29 +451,49 @@ impl MetadataManagerWrapper {
     }
 
     /// Get completions for prefix
+    ///
+    /// `limit` caps the number of results and `lite` strips `examples` and
+    /// `extra` from each function to keep the payload small.
     #[wasm_bindgen(js_name = "getCompletions")]
-    pub fn get_completions(&self, prefix: &str) -> JsValue {
-        let completions: Vec<Function> = self
-            .manager
-            .get_completions(prefix)
-            .into_iter()
-            .map(|f| (*f).clone())
-            .collect();
+    pub fn get_completions(
+        &self,
+        prefix: &str,
+        limit: Option<usize>,
+        lite: Option<bool>,
+    ) -> JsValue {
+        let mut completions = self.manager.get_completions(prefix);
+        completions.truncate(limit.unwrap_or(usize::MAX));
+        functions_to_js(completions, lite.unwrap_or(false))
+    }
 
-        serde_wasm_bindgen::to_value(&completions).unwrap_or(JsValue::NULL)
+    /// Get completions for a prefix as editor items with snippet insert text
+    #[wasm_bindgen(js_name = "completionItems")]
+    pub fn completion_items(&self, prefix: &str, limit: Option<usize>) -> JsValue {
+        let mut items = self.manager.completion_items(prefix);
+        items.truncate(limit.unwrap_or(usize::MAX));
+        serde_wasm_bindgen::to_value(&items).unwrap_or(JsValue::NULL)
     }
 
     /// Get all functions
     #[wasm_bindgen(js_name = "getAllFunctions")]
     pub fn get_all_functions(&self) -> JsValue {
-        let functions: Vec<Function> = self
-            .manager
-            .all_functions()
-            .into_iter()
-            .map(|f| (*f).clone())
-            .collect();
+        functions_to_js(self.manager.all_functions(), false)
+    }
 
-        serde_wasm_bindgen::to_value(&functions).unwrap_or(JsValue::NULL)
+    /// Get a page of functions ordered by name
+    ///
+    /// Pass `lite` to strip `examples` and `extra` from each function.
+    #[wasm_bindgen(js_name = "getAllFunctionsPage")]
+    pub fn get_all_functions_page(
+        &self,
+        offset: usize,
+        limit: usize,
+        lite: Option<bool>,
+    ) -> JsValue {
+        functions_to_js(
+            self.manager.functions_page(offset, limit),
+            lite.unwrap_or(false),
+        )
     }
 
     /// Get enum values
@@ -520,6 +602,26 @@ impl MetadataManagerWrapper {
     }
 }
 
+/// Short tag naming the variant of a metadata error, e.g. `network`
+fn metadata_error_type(error: &MetadataError) -> &'static str {
+    match error {
+        MetadataError::NetworkError(_) => "network",
+        MetadataError::ParseError(_) => "parse",
+        MetadataError::NotFound(_) => "notFound",
+        MetadataError::InvalidData(_) => "invalidData",
+        MetadataError::CacheError(_) => "cache",
+    }
+}
+
+/// Serialize functions for JS, optionally stripped down to their summary
+fn functions_to_js(functions: Vec<Arc<Function>>, lite: bool) -> JsValue {
+    let functions: Vec<Function> = functions
+        .iter()
+        .map(|f| if lite { f.summary() } else { (**f).clone() })
+        .collect();
+    serde_wasm_bindgen::to_value(&functions).unwrap_or(JsValue::NULL)
+}
+
 // ============================================================================
 // Utility Functions
 // ============================================================================
@@ -598,6 +700,25 @@ pub fn extract_text_nodes_wasm(source: &str) -> JsValue {
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
 
+/// Extract all JavaScript expressions from source.
+///
+/// Returns an array of `{ code: string, span: { start: number, end: number } }`.
+#[wasm_bindgen(js_name = "extractJavaScript")]
+pub fn extract_javascript_wasm(source: &str) -> JsValue {
+    let (ast, _) = rust_parse(source);
+    let scripts = crate::utils::extract_javascript(&ast);
+    let result: Vec<serde_json::Value> = scripts
+        .into_iter()
+        .map(|(code, span)| {
+            serde_json::json!({
+                "code": code,
+                "span": { "start": span.start, "end": span.end },
+            })
+        })
+        .collect();
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
 /// Flatten the AST into a depth-first linear list of node descriptors.
 ///
 /// Returns an array of objects, each with a `type` field and relevant fields
@@ -641,9 +762,14 @@ pub fn flatten_ast_wasm(source: &str) -> JsValue {
                 "code": code,
                 "span": { "start": span.start, "end": span.end },
             }),
-            AstNode::Escaped { content, span } => serde_json::json!({
+            AstNode::Escaped {
+                content,
+                function,
+                span,
+            } => serde_json::json!({
                 "type": "Escaped",
                 "content": content,
+                "function": function,
                 "span": { "start": span.start, "end": span.end },
             }),
         })
@@ -659,6 +785,18 @@ pub fn get_source_slice_wasm(source: &str, start: usize, end: usize) -> String {
     crate::utils::get_source_slice(source, span).to_string()
 }
 
+/// Convert a UTF-8 byte offset (as used in spans) to a UTF-16 code unit offset
+#[wasm_bindgen(js_name = "utf8ToUtf16Offset")]
+pub fn utf8_to_utf16_offset_wasm(source: &str, byte_offset: usize) -> usize {
+    crate::utils::utf8_to_utf16_offset(source, byte_offset)
+}
+
+/// Convert a UTF-16 code unit offset to a UTF-8 byte offset (as used in spans)
+#[wasm_bindgen(js_name = "utf16ToUtf8Offset")]
+pub fn utf16_to_utf8_offset_wasm(source: &str, utf16_offset: usize) -> usize {
+    crate::utils::utf16_to_utf8_offset(source, utf16_offset)
+}
+
 /// Check whether the character at `byte_idx` in `source` is escaped
 /// (i.e. preceded by an odd number of backslashes).
 #[wasm_bindgen(js_name = "isEscaped")]
@@ -701,6 +839,7 @@ pub fn count_node_types_wasm(source: &str) -> JsValue {
 // ============================================================================
 
 /// Validate code and return detailed results
+#[cfg(feature = "validation")]
 #[wasm_bindgen(js_name = "validateCode")]
 pub fn validate_code_wasm(source: &str, metadata_wrapper: &MetadataManagerWrapper) -> JsValue {
     let (_, errors) = crate::parser::parse_strict(source, metadata_wrapper.manager.clone());
@@ -734,6 +873,25 @@ pub fn validate_code_wasm(source: &str, metadata_wrapper: &MetadataManagerWrappe
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
 
+/// Parse and validate a source, returning a `LintReport` object of the form
+/// `{ valid, errors: [{ message, kind, severity, start, end, line, col }] }`
+///
+/// `config` takes the same fields as `parseWithConfig`.
+#[cfg(feature = "validation")]
+#[wasm_bindgen(js_name = "lintReport")]
+pub fn lint_report_wasm(
+    source: &str,
+    config: JsValue,
+    metadata_wrapper: &MetadataManagerWrapper,
+) -> JsValue {
+    let report = crate::lint::lint_report(
+        source,
+        validation_config_from_js(&config),
+        Some(metadata_wrapper.manager.clone()),
+    );
+    serde_wasm_bindgen::to_value(&report).unwrap_or(JsValue::NULL)
+}
+
 // ============================================================================
 // Batch Operations
 // ============================================================================
@@ -751,7 +909,7 @@ pub fn parse_batch_wasm(sources: JsValue) -> JsValue {
         .map(|source| {
             let (ast, errors) = rust_parse(source);
             serde_json::json!({
-                "ast": format_ast(&ast),
+                "ast": ast,
                 "errors": errors.iter().map(|e| {
                     serde_json::json!({
                         "message": e.message,
@@ -767,6 +925,7 @@ pub fn parse_batch_wasm(sources: JsValue) -> JsValue {
 }
 
 /// Validate multiple sources at once
+#[cfg(feature = "validation")]
 #[wasm_bindgen(js_name = "validateBatch")]
 pub fn validate_batch_wasm(sources: JsValue, metadata_wrapper: &MetadataManagerWrapper) -> JsValue {
     let sources: Vec<String> = match serde_wasm_bindgen::from_value(sources) {
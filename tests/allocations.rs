@@ -0,0 +1,51 @@
+//! Allocation counts of the owned and borrowed parsers. Kept in its own test
+//! binary because it installs a counting global allocator.
+
+use forge_kit::parser::{parse, parse_borrowed};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Allocations performed while running `f`
+fn count_allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    let count = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(result);
+    count
+}
+
+#[test]
+fn test_borrowed_parse_allocates_less() {
+    let source = include_str!("../benches/data/large_script.js");
+
+    let owned = count_allocations(|| parse(source));
+    let borrowed = count_allocations(|| parse_borrowed(source));
+    // About half the allocations of the owned tree, with some headroom
+    assert!(
+        borrowed * 5 <= owned * 3,
+        "parse_borrowed made {borrowed} allocations, parse made {owned}"
+    );
+}
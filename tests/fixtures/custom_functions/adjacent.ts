@@ -0,0 +1,20 @@
+import { ArgType } from "@tryforge/forgescript"
+
+export const functions = [
+    {
+        description: "Has no arguments",
+        name: "ping",
+    },
+    {
+        args: [
+            {
+                name: "amount",
+                type: ArgType.Number,
+                rest: true,
+                check: { name: "nested", limits: [1, 10] },
+            },
+        ],
+        name: "$sum",
+        description: "Adds numbers",
+    },
+]
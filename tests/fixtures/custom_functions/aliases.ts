@@ -0,0 +1,9 @@
+import { NativeFunction } from "@tryforge/forgescript"
+
+export default new NativeFunction({
+    name: "$fetchUser",
+    description: "Fetches a user",
+    category: "users",
+    aliases: ["$getUser", 'userFetch'],
+    unwrap: true,
+})
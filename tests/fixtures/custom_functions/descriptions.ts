@@ -0,0 +1,23 @@
+import { ArgType, NativeFunction } from "@tryforge/forgescript"
+
+export default new NativeFunction({
+    name: "$greet",
+    description: "Greets a user, " +
+        'optionally by ' +
+        `nickname`,
+    brackets: true,
+    // name: "$commented" is not a function
+    args: [
+        {
+            name: "user",
+            description: "The user's " + "ID",
+            required: true,
+            type: ArgType.User,
+        },
+    ],
+    output: ArgType.String,
+    execute(ctx, [user]) {
+        const opts = { name: "not-a-function" }
+        return this.success(`Hello ${user}`)
+    },
+})
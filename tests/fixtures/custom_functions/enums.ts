@@ -0,0 +1,25 @@
+import { ArgType, NativeFunction } from "@tryforge/forgescript"
+import { ChannelType } from "discord.js"
+
+export default new NativeFunction({
+    name: "$createChannel",
+    description: "Creates a channel",
+    args: [
+        {
+            name: "type",
+            description: "The channel type",
+            required: true,
+            type: ArgType.Enum,
+            enum: ChannelType,
+        },
+        {
+            name: "mode",
+            description: "How to create it",
+            required: false,
+            type: ArgType.Enum,
+            enum: ["fast", "safe"],
+            enumName: "CreateMode",
+        },
+    ],
+    unwrap: true,
+})
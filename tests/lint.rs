@@ -0,0 +1,150 @@
+use forge_kit::lint::{LintConfig, lint};
+use forge_kit::metadata::{MetadataCache, MetadataManager};
+use forge_kit::parser::{ErrorKind, Severity, parse};
+use forge_kit::types::Function;
+use std::collections::HashMap;
+
+fn metadata_with_deprecated() -> MetadataManager {
+    let manager = MetadataManager::new();
+    let old = Function {
+        name: "$oldFunc".to_string(),
+        deprecated: Some(true),
+        ..Default::default()
+    };
+    let new = Function {
+        name: "$newFunc".to_string(),
+        ..Default::default()
+    };
+    manager
+        .import_cache(MetadataCache::new(vec![old, new], HashMap::new(), vec![]))
+        .expect("Import failed");
+    manager
+}
+
+#[test]
+fn test_lint_disabled_by_default() {
+    let (ast, _) = parse("code: `\n\t $foo\n`");
+    assert!(lint(&ast, &LintConfig::default(), None).is_empty());
+}
+
+#[test]
+fn test_lint_mixed_indentation() {
+    let source = "code: `$if[a]\n\t $send[hi]\n    $else\n\t$end`";
+    let (ast, _) = parse(source);
+    let config = LintConfig {
+        lint_indentation: true,
+        ..Default::default()
+    };
+
+    let warnings = lint(&ast, &config, None);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind, ErrorKind::MixedIndentation);
+    assert_eq!(warnings[0].severity, Severity::Warning);
+    assert_eq!(&source[warnings[0].span.start..warnings[0].span.end], "\t ");
+}
+
+#[test]
+fn test_lint_deprecated_requires_metadata() {
+    let (ast, _) = parse("code: `$oldFunc`");
+    let config = LintConfig {
+        lint_deprecated: true,
+        ..Default::default()
+    };
+    assert!(lint(&ast, &config, None).is_empty());
+}
+
+#[test]
+fn test_lint_runs_all_enabled_lints() {
+    let source = "code: `$newFunc[$oldFunc]\n\t $oldFunc\n`";
+    let (ast, _) = parse(source);
+    let metadata = metadata_with_deprecated();
+    let config = LintConfig {
+        lint_indentation: true,
+        lint_deprecated: true,
+        ..Default::default()
+    };
+
+    let warnings = lint(&ast, &config, Some(&metadata));
+    assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+
+    let deprecated: Vec<_> = warnings
+        .iter()
+        .filter(|w| w.kind == ErrorKind::Deprecated)
+        .collect();
+    assert_eq!(deprecated.len(), 2);
+    assert_eq!(
+        &source[deprecated[0].span.start..deprecated[0].span.end],
+        "$oldFunc"
+    );
+    assert_eq!(
+        warnings
+            .iter()
+            .filter(|w| w.kind == ErrorKind::MixedIndentation)
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_lint_unreachable() {
+    let config = LintConfig {
+        lint_unreachable: true,
+        ..Default::default()
+    };
+
+    let source = "code: `$return[x] $get[y]\n`";
+    let (ast, _) = parse(source);
+    let warnings = lint(&ast, &config, None);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind, ErrorKind::Unreachable);
+    assert_eq!(warnings[0].severity, Severity::Warning);
+    assert_eq!(
+        &source[warnings[0].span.start..warnings[0].span.end],
+        "$get[y]"
+    );
+
+    // Nested arguments, trailing whitespace and later blocks are not flagged
+    let (ast, _) = parse("a: `$if[x;$stop $get[y]] $STOP\n` b: `$get[z]`");
+    assert!(lint(&ast, &config, None).is_empty());
+
+    let config = LintConfig {
+        terminators: Some(vec!["exit".to_string()]),
+        ..config
+    };
+    let (ast, _) = parse("code: `$return $exit $get[y] text`");
+    let warnings = lint(&ast, &config, None);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].span.start, 21);
+}
+
+#[cfg(feature = "validation")]
+#[test]
+fn test_lint_report() {
+    use forge_kit::lint::lint_report;
+    use forge_kit::parser::ValidationConfig;
+
+    let source = "a\ncode: `\n  $get[x`";
+    let report = lint_report(source, ValidationConfig::syntax_only(), None);
+    assert!(!report.valid);
+    assert_eq!(report.errors.len(), 1);
+    let error = &report.errors[0];
+    assert_eq!(error.kind, ErrorKind::UnclosedBracket);
+    assert_eq!((error.start, error.end), (16, 17));
+    assert_eq!((error.line, error.col), (2, 6));
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["valid"], false);
+    assert_eq!(json["errors"][0]["kind"], "UnclosedBracket");
+    assert_eq!(json["errors"][0]["severity"], "Error");
+    assert_eq!(json["errors"][0]["line"], 2);
+    assert_eq!(json["errors"][0]["col"], 6);
+
+    let metadata = std::sync::Arc::new(metadata_with_deprecated());
+    let report = lint_report(
+        "code: `$oldFunc`",
+        ValidationConfig::strict(),
+        Some(metadata),
+    );
+    assert!(report.valid);
+    assert!(report.errors.is_empty());
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use forge_kit::metadata::{FunctionTrie, MetadataCache, MetadataManager};
-    use forge_kit::types::{Event, Function};
+    use forge_kit::metadata::{FunctionTrie, MetadataCache, MetadataManager, MetadataSource};
+    use forge_kit::types::{Arg, Event, Function, FunctionOrigin};
     use std::collections::HashMap;
     use std::sync::Arc;
 
@@ -108,6 +108,94 @@ mod tests {
         assert!(trie.get_exact("$GetVar").is_some());
     }
 
+    #[test]
+    fn test_trie_prefix_with_remainder() {
+        let mut trie = FunctionTrie::new();
+        trie.insert("$ping", Arc::new(create_test_function("$ping")));
+        trie.insert("$İd", Arc::new(create_test_function("$İd")));
+
+        let (name, func, rest) = trie.get_prefix_with_remainder("$pingmsoko").unwrap();
+        assert_eq!(
+            (name.as_str(), func.name.as_str(), rest),
+            ("$ping", "$ping", "msoko")
+        );
+
+        // The remainder keeps the original casing and byte offsets
+        let (name, _, rest) = trie.get_prefix_with_remainder("$PINGMsoko").unwrap();
+        assert_eq!((name.as_str(), rest), ("$ping", "Msoko"));
+        let (_, _, rest) = trie.get_prefix_with_remainder("$İDÄx").unwrap();
+        assert_eq!(rest, "Äx");
+
+        assert!(trie.get_prefix_with_remainder("$send").is_none());
+    }
+
+    #[test]
+    fn test_borrowing_lookups() {
+        let mut trie = FunctionTrie::new();
+        let func = Arc::new(create_test_function("$GetVar"));
+        trie.insert("$GetVar", func.clone());
+
+        let found = trie.get_exact_ref("$GETVAR").expect("case-insensitive hit");
+        assert!(Arc::ptr_eq(found, &func));
+        assert_eq!(Arc::strong_count(&func), 2);
+        assert!(trie.get_exact_ref("$GetVa").is_none());
+
+        let manager = MetadataManager::new();
+        manager
+            .import_cache(MetadataCache::new(
+                vec![create_test_function("$GetVar")],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+        assert_eq!(manager.with_function("$getvar", |f| f.unwrap), Some(true));
+        assert_eq!(manager.with_function("$missing", |f| f.unwrap), None);
+    }
+
+    #[test]
+    fn test_trie_casing_mismatch() {
+        let mut trie = FunctionTrie::new();
+        trie.insert("$GetVar", Arc::new(create_test_function("$GetVar")));
+
+        let found = trie
+            .get_case_match("$getvar")
+            .expect("lookup stays case-insensitive");
+        assert_eq!(found.canonical, "$GetVar");
+        assert!(!found.exact_case);
+
+        assert!(trie.get_case_match("$GetVar").unwrap().exact_case);
+        assert!(trie.get_case_match("$GetVa").is_none());
+
+        let manager = MetadataManager::new();
+        manager
+            .import_cache(MetadataCache::new(
+                vec![create_test_function("$GetVar")],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+        let (matched, _) = manager.get_with_match("$getvar").unwrap();
+        assert_eq!(matched, "$GetVar");
+    }
+
+    #[test]
+    fn test_lookup_preserves_registered_casing() {
+        let manager = MetadataManager::new();
+        let mut get_var = create_test_function("$getVar");
+        get_var.aliases = Some(vec!["getV".to_string()]);
+        manager
+            .import_cache(MetadataCache::new(vec![get_var], HashMap::new(), vec![]))
+            .unwrap();
+
+        for query in ["$GETVAR", "$getvar", "$getVar"] {
+            assert_eq!(manager.get_exact(query).unwrap().name, "$getVar");
+            assert_eq!(manager.get(query).unwrap().name, "$getVar");
+        }
+
+        // Alias entries keep the casing the alias was declared with
+        assert_eq!(manager.get_exact("$GETV").unwrap().name, "$getV");
+    }
+
     #[test]
     fn test_trie_completions_logic() {
         let mut trie = FunctionTrie::new();
@@ -128,6 +216,1241 @@ mod tests {
         assert!(non_existent.is_empty());
     }
 
+    #[test]
+    fn test_trie_stats() {
+        let mut trie = FunctionTrie::new();
+        assert_eq!(trie.stats().nodes, 1);
+        assert_eq!(trie.stats().entries, 0);
+
+        for name in ["$get", "$getVar", "$ping"] {
+            trie.insert(name, Arc::new(create_test_function(name)));
+        }
+        let stats = trie.stats();
+        assert_eq!(stats.entries, trie.len());
+        assert_eq!(stats.max_depth, "$getVar".len());
+        // Root, "$get", "Var" and "ping"
+        assert_eq!(stats.nodes, 1 + 4 + 3 + 4);
+        assert!((stats.avg_depth - 16.0 / 3.0).abs() < 1e-9);
+
+        let manager = MetadataManager::new();
+        manager.update_function(create_test_function("$ping"));
+        assert_eq!(manager.trie_stats().entries, 1);
+    }
+
+    #[test]
+    fn test_trie_get_all_with_keys() {
+        let mut trie = FunctionTrie::new();
+        let func = Arc::new(create_test_function("$foo"));
+        trie.insert("$foo", func.clone());
+        trie.insert("$bar", func);
+
+        let mut entries = trie.get_all_with_keys();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["$bar", "$foo"]);
+        assert!(entries.iter().all(|(_, func)| func.name == "$foo"));
+    }
+
+    #[test]
+    fn test_trie_merge() {
+        let build = |names: &[&str], description: &str| {
+            let mut trie = FunctionTrie::new();
+            for name in names {
+                let func = Function {
+                    description: description.to_string(),
+                    ..create_test_function(name)
+                };
+                trie.insert(name, Arc::new(func));
+            }
+            trie
+        };
+        let other = build(&["$Ban", "$kick"], "other");
+
+        let mut kept = build(&["$ban", "$mute"], "base");
+        kept.merge(&other, false);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept.get_exact("$ban").unwrap().description, "base");
+        assert_eq!(kept.get_case_match("$ban").unwrap().canonical, "$ban");
+        assert_eq!(kept.get_exact("$kick").unwrap().description, "other");
+
+        let mut replaced = build(&["$ban", "$mute"], "base");
+        replaced.merge(&other, true);
+        assert_eq!(replaced.len(), 3);
+        assert_eq!(replaced.get_exact("$ban").unwrap().description, "other");
+        assert_eq!(replaced.get_case_match("$ban").unwrap().canonical, "$Ban");
+        assert_eq!(replaced.get_completions("$").len(), 3);
+    }
+
+    #[test]
+    fn test_trie_remove_prunes_completions() {
+        let mut trie = FunctionTrie::new();
+        trie.insert("$add", Arc::new(create_test_function("$add")));
+        trie.insert("$abs", Arc::new(create_test_function("$abs")));
+
+        let removed = trie.remove("$add").expect("$add should be removed");
+        assert_eq!(removed.name, "$add");
+        assert_eq!(trie.len(), 1);
+        assert!(trie.remove("$add").is_none());
+
+        let completions = trie.get_completions("$a");
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].name, "$abs");
+        assert!(trie.get_completions("$ad").is_empty());
+    }
+
+    #[test]
+    fn test_manager_remove_and_update_function() {
+        let manager = MetadataManager::new();
+        let mut func = create_test_function("$foo");
+        func.aliases = Some(vec!["bar".to_string()]);
+        manager
+            .import_cache(MetadataCache::new(vec![func], HashMap::new(), vec![]))
+            .unwrap();
+        assert_eq!(manager.function_count(), 2);
+
+        let mut updated = create_test_function("$foo");
+        updated.description = "Updated".to_string();
+        updated.aliases = Some(vec!["$baz".to_string()]);
+        manager.update_function(updated);
+
+        assert_eq!(manager.get_exact("$foo").unwrap().description, "Updated");
+        assert!(manager.get_exact("$bar").is_none());
+        assert!(manager.get_exact("$baz").is_some());
+
+        assert!(manager.remove_function("$foo").is_some());
+        assert!(manager.get_exact("$baz").is_none());
+        assert_eq!(manager.function_count(), 0);
+    }
+
+    #[test]
+    fn test_fuzzy_complete_ranking() {
+        let manager = MetadataManager::new();
+        let functions = [
+            "$sendMessage",
+            "$sum",
+            "$messageID",
+            "$sendDM",
+            "$findMember",
+        ]
+        .into_iter()
+        .map(create_test_function)
+        .collect();
+        manager
+            .import_cache(MetadataCache::new(functions, HashMap::new(), vec![]))
+            .unwrap();
+
+        let results = manager.fuzzy_complete("sndmsg", 10);
+        assert_eq!(results[0].0.name, "$sendMessage");
+        assert!(results.iter().all(|(f, _)| f.name != "$sum"));
+
+        // Prefix matches outrank scattered subsequence hits
+        let results = manager.fuzzy_complete("$send", 10);
+        let names: Vec<&str> = results.iter().map(|(f, _)| f.name.as_str()).collect();
+        assert_eq!(&names[..2], &["$sendDM", "$sendMessage"]);
+
+        assert_eq!(manager.fuzzy_complete("s", 1).len(), 1);
+    }
+
+    #[test]
+    fn test_custom_functions_origin() {
+        let manager = MetadataManager::new();
+        manager
+            .import_cache(MetadataCache::new(
+                vec![create_test_function("$core")],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+
+        let json = r#"[{ "name": "myFunc", "aliases": ["myAlias"] }]"#;
+        assert_eq!(manager.add_custom_functions_from_json(json).unwrap(), 1);
+
+        let func = manager.get_exact("$myFunc").unwrap();
+        assert_eq!(func.origin, FunctionOrigin::Custom);
+        assert_eq!(
+            manager.get_exact("$core").unwrap().origin,
+            FunctionOrigin::Core
+        );
+
+        let mut custom: Vec<String> = manager
+            .custom_functions()
+            .iter()
+            .map(|f| f.name.clone())
+            .collect();
+        custom.sort();
+        assert_eq!(custom, vec!["$myAlias", "$myFunc"]);
+    }
+
+    #[test]
+    fn test_functions_using_enum() {
+        let manager = MetadataManager::new();
+        let mut enums = HashMap::new();
+        enums.insert(
+            "TimeUnit".to_string(),
+            vec!["seconds".to_string(), "minutes".to_string()],
+        );
+
+        let by_name = |name: &str| Function {
+            name: name.to_string(),
+            args: Some(vec![Arg {
+                name: "unit".to_string(),
+                enum_name: Some("TimeUnit".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let inline = Function {
+            name: "$inline".to_string(),
+            args: Some(vec![Arg {
+                name: "unit".to_string(),
+                arg_enum: Some(vec!["seconds".to_string(), "minutes".to_string()]),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        manager
+            .import_cache(MetadataCache::new(
+                vec![
+                    by_name("$wait"),
+                    by_name("$delay"),
+                    inline,
+                    create_test_function("$unrelated"),
+                ],
+                enums,
+                vec![],
+            ))
+            .unwrap();
+
+        let names: Vec<String> = manager
+            .functions_using_enum("TimeUnit")
+            .iter()
+            .map(|f| f.name.clone())
+            .collect();
+        assert_eq!(names, vec!["$delay", "$inline", "$wait"]);
+        assert!(manager.functions_using_enum("Missing").is_empty());
+    }
+
+    #[test]
+    fn test_completion_items() {
+        let arg = |name: &str, required: bool, rest: bool| Arg {
+            name: name.to_string(),
+            required: Some(required),
+            rest,
+            ..Default::default()
+        };
+        let manager = MetadataManager::new();
+        manager.update_function(Function {
+            name: "$sendMessage".to_string(),
+            description: "Sends a message".to_string(),
+            brackets: Some(true),
+            args: Some(vec![
+                arg("channelID", true, false),
+                arg("content", true, false),
+                arg("returnID", false, false),
+            ]),
+            ..Default::default()
+        });
+        manager.update_function(Function {
+            name: "$sum".to_string(),
+            brackets: Some(true),
+            args: Some(vec![arg("numbers", true, true)]),
+            ..Default::default()
+        });
+        manager.update_function(Function {
+            name: "$ping".to_string(),
+            brackets: None,
+            ..Default::default()
+        });
+
+        let items = manager.completion_items("$send");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "$sendMessage");
+        assert_eq!(
+            items[0].insert_text,
+            r"\$sendMessage[${1:channelID};${2:content}]"
+        );
+        assert_eq!(items[0].detail, "$sendMessage[channelID;content;returnID?]");
+        assert_eq!(items[0].documentation, "Sends a message");
+
+        assert_eq!(
+            manager.completion_items("$sum")[0].insert_text,
+            r"\$sum[${1:numbers...}]"
+        );
+        assert_eq!(manager.completion_items("$ping")[0].insert_text, r"\$ping");
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let manager = MetadataManager::new();
+        manager
+            .import_cache(MetadataCache::new(
+                vec![create_test_function("$ping")],
+                HashMap::from([("Colors".to_string(), vec!["Red".to_string()])]),
+                vec![],
+            ))
+            .unwrap();
+
+        let snapshot = manager.snapshot();
+        assert!(snapshot.get("$PING").is_some());
+        assert_eq!(snapshot.get_enum("Colors"), Some(&["Red".to_string()][..]));
+        assert!(Arc::ptr_eq(&snapshot, &manager.snapshot()));
+
+        manager.update_function(create_test_function("$pong"));
+        let refreshed = manager.snapshot();
+        assert!(!Arc::ptr_eq(&snapshot, &refreshed));
+        assert_eq!(refreshed.function_count(), 2);
+        // Snapshots taken earlier are unaffected by later changes
+        assert_eq!(snapshot.function_count(), 1);
+        assert!(snapshot.get_exact("$pong").is_none());
+    }
+
+    #[test]
+    fn test_functions_using_enum_index_invalidation() {
+        let manager = MetadataManager::new();
+        let using_colors = |name: &str| Function {
+            name: name.to_string(),
+            args: Some(vec![Arg {
+                name: "color".to_string(),
+                enum_name: Some("Colors".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let names = |manager: &MetadataManager| -> Vec<String> {
+            manager
+                .functions_using_enum("Colors")
+                .iter()
+                .map(|f| f.name.clone())
+                .collect()
+        };
+
+        manager
+            .import_cache(MetadataCache::new(
+                vec![using_colors("$paint"), create_test_function("$other")],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+        assert_eq!(names(&manager), vec!["$paint"]);
+
+        manager.remove_function("$paint");
+        assert!(names(&manager).is_empty());
+
+        manager.update_function(using_colors("$tint"));
+        assert_eq!(names(&manager), vec!["$tint"]);
+
+        manager
+            .merge(MetadataCache::new(
+                vec![using_colors("$brush")],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+        assert_eq!(names(&manager), vec!["$brush", "$tint"]);
+
+        manager.clear();
+        assert!(names(&manager).is_empty());
+    }
+
+    #[test]
+    fn test_find_references_resolves_aliases() {
+        use forge_kit::parser::parse;
+        use forge_kit::utils::{find_references, find_references_with_metadata};
+
+        let manager = MetadataManager::new();
+        let mut send = create_test_function("$sendMessage");
+        send.aliases = Some(vec!["sendMsg".to_string()]);
+        manager
+            .import_cache(MetadataCache::new(
+                vec![send, create_test_function("$reply")],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+
+        let source = "code: `$sendMessage[a] $sendMsg[b] $reply[c]`";
+        let (ast, _) = parse(source);
+
+        assert_eq!(find_references(&ast, "$sendMessage").len(), 1);
+
+        for name in ["$sendMessage", "$sendMsg"] {
+            let names: Vec<&str> = find_references_with_metadata(&ast, name, &manager)
+                .iter()
+                .map(|span| &source[span.start..span.end])
+                .collect();
+            assert_eq!(names, vec!["sendMessage", "sendMsg"]);
+        }
+    }
+
+    #[test]
+    fn test_find_references_keeps_alias_less_functions_apart() {
+        use forge_kit::parser::parse;
+        use forge_kit::utils::find_references_with_metadata;
+
+        let manager = MetadataManager::new();
+        let mut ban = create_test_function("$ban");
+        ban.aliases = Some(vec![]);
+        let mut kick = create_test_function("$kick");
+        kick.aliases = Some(vec![]);
+        manager
+            .import_cache(MetadataCache::new(
+                vec![
+                    ban,
+                    kick,
+                    create_test_function("$mute"),
+                    create_test_function("$warn"),
+                ],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+
+        let source = "code: `$ban[a] $kick[b] $mute[c] $warn[d]`";
+        let (ast, _) = parse(source);
+
+        for name in ["$ban", "$mute"] {
+            let spans = find_references_with_metadata(&ast, name, &manager);
+            assert_eq!(spans.len(), 1);
+            assert_eq!(&source[spans[0].start..spans[0].end], &name[1..]);
+        }
+    }
+
+    #[test]
+    fn test_functions_page_covers_all_disjointly() {
+        let manager = MetadataManager::new();
+        let functions: Vec<Function> = (0..23)
+            .map(|i| create_test_function(&format!("$func{:02}", i)))
+            .collect();
+        manager
+            .import_cache(MetadataCache::new(functions, HashMap::new(), vec![]))
+            .unwrap();
+
+        let mut seen = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = manager.functions_page(offset, 5);
+            if page.is_empty() {
+                break;
+            }
+            assert!(page.len() <= 5);
+            offset += page.len();
+            seen.extend(page.iter().map(|f| f.name.clone()));
+        }
+
+        let mut expected: Vec<String> = manager
+            .all_functions()
+            .iter()
+            .map(|f| f.name.clone())
+            .collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+        assert!(manager.functions_page(100, 5).is_empty());
+    }
+
+    #[test]
+    fn test_function_summary_strips_heavy_fields() {
+        let mut func = create_test_function("$heavy");
+        func.examples = Some(vec!["$heavy[]".to_string()]);
+        func.extra
+            .insert("blob".to_string(), serde_json::json!("x".repeat(64)));
+
+        let summary = func.summary();
+        assert!(summary.examples.is_none());
+        assert!(summary.extra.is_empty());
+        assert_eq!(summary.name, func.name);
+        assert_eq!(summary.args, func.args);
+    }
+
+    #[test]
+    fn test_collect_enum_usages() {
+        use forge_kit::parser::parse;
+        use forge_kit::utils::collect_enum_usages;
+
+        let manager = MetadataManager::new();
+        let mut enums = HashMap::new();
+        enums.insert(
+            "ButtonStyle".to_string(),
+            vec!["Primary".to_string(), "Danger".to_string()],
+        );
+        let button = Function {
+            name: "$addButton".to_string(),
+            args: Some(vec![
+                Arg {
+                    name: "id".to_string(),
+                    ..Default::default()
+                },
+                Arg {
+                    name: "style".to_string(),
+                    enum_name: Some("ButtonStyle".to_string()),
+                    ..Default::default()
+                },
+            ]),
+            brackets: Some(true),
+            ..Default::default()
+        };
+        let toggle = Function {
+            name: "$toggle".to_string(),
+            args: Some(vec![Arg {
+                name: "state".to_string(),
+                arg_enum: Some(vec!["on".to_string(), "off".to_string()]),
+                ..Default::default()
+            }]),
+            brackets: Some(true),
+            ..Default::default()
+        };
+        manager
+            .import_cache(MetadataCache::new(vec![button, toggle], enums, vec![]))
+            .unwrap();
+
+        let source =
+            "code: `$addButton[a;Danger] $addButton[b;$get[s]] $addButton[c;Nope] $toggle[ on ]`";
+        let (ast, _) = parse(source);
+        let usages = collect_enum_usages(&ast, &manager);
+
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].function, "$addButton");
+        assert_eq!(usages[0].arg_name, "style");
+        assert_eq!(usages[0].enum_name, "ButtonStyle");
+        assert_eq!(usages[0].value, "Danger");
+        assert_eq!(&source[usages[0].span.start..usages[0].span.end], "Danger");
+        assert_eq!(usages[1].enum_name, "");
+        assert_eq!(usages[1].value, "on");
+    }
+
+    #[test]
+    fn test_infer_arg_type() {
+        use forge_kit::parser::{AstNode, parse};
+        use forge_kit::utils::{VOID_TYPE, infer_arg_type};
+
+        let manager = MetadataManager::new();
+        let number = Function {
+            output: Some(vec!["Number".to_string()]),
+            ..create_test_function("$random")
+        };
+        let either = Function {
+            output: Some(vec!["String".to_string(), "Number".to_string()]),
+            ..create_test_function("$either")
+        };
+        let void = create_test_function("$log");
+        manager
+            .import_cache(MetadataCache::new(
+                vec![number, either, void],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+
+        assert_eq!(
+            manager.output_types("random"),
+            Some(vec!["Number".to_string()])
+        );
+        assert_eq!(manager.output_types("$either").map(|t| t.len()), Some(2));
+        assert_eq!(manager.output_types("$log"), Some(vec![]));
+        assert_eq!(manager.output_types("$missing"), None);
+
+        let (ast, _) =
+            parse("code: `$addField[ $random[] ;$log[x];$either[];$random[]px;$missing[];plain]`");
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        let AstNode::FunctionCall {
+            args: Some(args), ..
+        } = &body[0]
+        else {
+            panic!("expected $addField");
+        };
+
+        let inferred: Vec<_> = args
+            .iter()
+            .map(|arg| infer_arg_type(arg, &manager))
+            .collect();
+        assert_eq!(
+            inferred,
+            vec![
+                Some("Number".to_string()),
+                Some(VOID_TYPE.to_string()),
+                None,
+                None,
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_functions_skips_bad_entries() {
+        use forge_kit::metadata::Fetcher;
+
+        let json = r#"[
+            {"name": "$ping", "description": "Latency"},
+            {"name": 42},
+            {"name": "$ban", "args": [{"name": "user", "type": "User"}]}
+        ]"#;
+        let url = "https://example.com/functions.json";
+        let functions = Fetcher::parse_functions(json, url, "forgedb").unwrap();
+
+        let names: Vec<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["$ping", "$ban"]);
+        for func in &functions {
+            assert_eq!(func.extension.as_deref(), Some("forgedb"));
+            assert_eq!(func.source_url.as_deref(), Some(url));
+            assert_eq!(
+                func.origin,
+                FunctionOrigin::Extension("forgedb".to_string())
+            );
+        }
+
+        assert!(Fetcher::parse_functions("{}", url, "forgedb").is_err());
+        assert!(Fetcher::parse_functions("[] trailing", url, "forgedb").is_err());
+    }
+
+    #[test]
+    fn test_function_output_types() {
+        let func: Function = serde_json::from_value(serde_json::json!({
+            "name": "$either",
+            "output": ["String", "Number"]
+        }))
+        .unwrap();
+        assert_eq!(func.output_types(), ["String", "Number"]);
+        assert!(func.produces("Number"));
+        assert!(func.produces("string"));
+        assert!(!func.produces("Boolean"));
+
+        let single: Function =
+            serde_json::from_value(serde_json::json!({"name": "$random", "output": "Number"}))
+                .unwrap();
+        assert_eq!(single.output_types(), ["Number"]);
+
+        let none = create_test_function("$log");
+        assert!(none.output_types().is_empty());
+        assert!(!none.produces("Number"));
+    }
+
+    #[test]
+    fn test_js_scanner_output_matches_json_shape() {
+        let dir = std::env::temp_dir().join(format!("forge-kit-output-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("functions.js"),
+            r#"
+export const either = {
+    name: "$either",
+    description: "Returns either",
+    output: [ArgType.String, "Number"],
+}
+export const random = {
+    name: "$random",
+    description: "Random number",
+    output: ArgType.Number,
+}
+"#,
+        )
+        .unwrap();
+
+        let json = MetadataManager::new()
+            .generate_custom_functions_json(&dir)
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let functions: Vec<Function> = serde_json::from_str(&json).unwrap();
+        let either = functions.iter().find(|f| f.name == "$either").unwrap();
+        assert_eq!(either.output_types(), ["String", "Number"]);
+        let random = functions.iter().find(|f| f.name == "$random").unwrap();
+        assert_eq!(random.output_types(), ["Number"]);
+    }
+
+    #[test]
+    fn test_fetch_all_with_mock_transport() {
+        use forge_kit::metadata::{
+            FetchErrorKind, MetadataError, MetadataTransport, TransportFuture,
+        };
+
+        struct MockTransport(HashMap<&'static str, (u16, &'static str)>);
+
+        impl MetadataTransport for MockTransport {
+            fn get<'a>(
+                &'a self,
+                url: &'a str,
+                _headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                let (status, body) = self.0.get(url).copied().unwrap_or((404, ""));
+                Box::pin(async move { Ok((status, body.to_string())) })
+            }
+        }
+
+        let transport = MockTransport(HashMap::from([
+            (
+                "https://meta.test/core/functions.json",
+                (200, r#"[{"name": "$ping"}, {"name": 1}, {"name": "$ban"}]"#),
+            ),
+            (
+                "https://meta.test/core/enums.json",
+                (200, r#"{"Color": ["Red", "Blue"]}"#),
+            ),
+            ("https://meta.test/db/functions.json", (500, "oops")),
+        ]));
+        let manager = MetadataManager::with_transport(transport);
+        manager.add_source(
+            MetadataSource::new("core")
+                .with_functions("https://meta.test/core/functions.json")
+                .with_enums("https://meta.test/core/enums.json")
+                .with_events("https://meta.test/core/events.json"),
+        );
+        manager.add_source(
+            MetadataSource::new("db").with_functions("https://meta.test/db/functions.json"),
+        );
+
+        let stats = tokio_test::block_on(manager.fetch_all()).unwrap();
+        assert_eq!(stats.functions, 2);
+        assert_eq!(stats.enums, 1);
+        assert_eq!(stats.events, 0);
+        // The missing events file is tolerated, the server error is not
+        assert_eq!(stats.errors.len(), 1);
+        let error = &stats.errors[0];
+        assert_eq!(error.extension, "db");
+        assert_eq!(error.kind, FetchErrorKind::Functions);
+        assert!(matches!(error.source, MetadataError::NetworkError(_)));
+        assert_eq!(
+            error.to_string(),
+            "Functions from db: Network error: HTTP 500: https://meta.test/db/functions.json"
+        );
+
+        let ping = manager.get_exact("$ping").unwrap();
+        assert_eq!(
+            ping.source_url.as_deref(),
+            Some("https://meta.test/core/functions.json")
+        );
+        assert_eq!(manager.get_enum("Color").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_fetch_all_or_cache_falls_back_on_failure() {
+        use forge_kit::metadata::{MetadataTransport, TransportFuture};
+
+        struct FailingTransport;
+
+        impl MetadataTransport for FailingTransport {
+            fn get<'a>(
+                &'a self,
+                _url: &'a str,
+                _headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                Box::pin(async { Ok((503, String::new())) })
+            }
+        }
+
+        let cache_path = std::env::temp_dir().join(format!(
+            "forge_kit_fallback_cache_{}.json",
+            std::process::id()
+        ));
+        let saved = MetadataManager::new();
+        saved.update_function(create_test_function("$ping"));
+        saved.save_cache_to_file(&cache_path).unwrap();
+
+        let manager = MetadataManager::with_transport(FailingTransport);
+        manager.add_source(
+            MetadataSource::new("core").with_functions("https://meta.test/core/functions.json"),
+        );
+        let stats = tokio_test::block_on(manager.fetch_all_or_cache(&cache_path)).unwrap();
+        std::fs::remove_file(&cache_path).unwrap();
+
+        assert!(stats.from_cache);
+        assert_eq!(stats.errors.len(), 1);
+        assert!(manager.get_exact("$ping").is_some());
+
+        // Without any failure the cache is left alone
+        let manager = MetadataManager::with_transport(FailingTransport);
+        let stats = tokio_test::block_on(manager.fetch_all_or_cache(&cache_path)).unwrap();
+        assert!(!stats.from_cache);
+        assert_eq!(manager.function_count(), 0);
+    }
+
+    #[test]
+    fn test_fetch_source_replaces_extension_functions() {
+        use forge_kit::metadata::{MetadataTransport, TransportFuture};
+        use std::sync::Mutex;
+
+        struct MutableTransport(Mutex<HashMap<&'static str, &'static str>>);
+
+        impl MetadataTransport for MutableTransport {
+            fn get<'a>(
+                &'a self,
+                url: &'a str,
+                _headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                let body = self.0.lock().unwrap().get(url).copied();
+                Box::pin(
+                    async move { Ok(body.map_or((404, String::new()), |b| (200, b.to_string()))) },
+                )
+            }
+        }
+
+        let transport = Arc::new(MutableTransport(Mutex::new(HashMap::from([
+            ("https://meta.test/core.json", r#"[{"name": "$ping"}]"#),
+            (
+                "https://meta.test/db.json",
+                r#"[{"name": "$dbGet", "aliases": ["$dbFetch"]}, {"name": "$dbSet"}]"#,
+            ),
+        ]))));
+
+        struct Shared(Arc<MutableTransport>);
+
+        impl MetadataTransport for Shared {
+            fn get<'a>(
+                &'a self,
+                url: &'a str,
+                headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                self.0.get(url, headers)
+            }
+        }
+
+        let manager = MetadataManager::with_transport(Shared(transport.clone()));
+        manager
+            .add_source(MetadataSource::new("core").with_functions("https://meta.test/core.json"));
+        manager.add_source(MetadataSource::new("db").with_functions("https://meta.test/db.json"));
+        tokio_test::block_on(manager.fetch_all()).unwrap();
+        assert_eq!(manager.function_count(), 4);
+
+        transport.0.lock().unwrap().insert(
+            "https://meta.test/db.json",
+            r#"[{"name": "$dbGet"}, {"name": "$dbDelete"}]"#,
+        );
+        let stats = tokio_test::block_on(manager.fetch_source("db")).unwrap();
+        assert_eq!(stats.functions, 2);
+        assert!(stats.errors.is_empty());
+
+        assert!(manager.get_exact("$dbGet").is_some());
+        assert!(manager.get_exact("$dbDelete").is_some());
+        assert!(manager.get_exact("$dbSet").is_none());
+        assert!(manager.get_exact("$dbFetch").is_none());
+        assert!(manager.get_exact("$ping").is_some());
+        assert_eq!(manager.function_count(), 3);
+
+        assert!(tokio_test::block_on(manager.fetch_source("missing")).is_err());
+    }
+
+    #[test]
+    fn test_function_collisions_across_extensions() {
+        use forge_kit::metadata::{
+            Collision, CollisionPolicy, MetadataError, MetadataTransport, TransportFuture,
+        };
+
+        struct MockTransport;
+
+        impl MetadataTransport for MockTransport {
+            fn get<'a>(
+                &'a self,
+                url: &'a str,
+                _headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                let body = match url {
+                    "https://meta.test/canvas.json" => r#"[{"name": "$get"}, {"name": "$draw"}]"#,
+                    "https://meta.test/db.json" => r#"[{"name": "$dbGet", "aliases": ["get"]}]"#,
+                    _ => "[]",
+                };
+                Box::pin(async move { Ok((200, body.to_string())) })
+            }
+        }
+
+        let add_sources = |manager: &MetadataManager| {
+            manager.add_source(
+                MetadataSource::new("canvas").with_functions("https://meta.test/canvas.json"),
+            );
+            manager
+                .add_source(MetadataSource::new("db").with_functions("https://meta.test/db.json"));
+        };
+        let expected = Collision {
+            name: "$get".to_string(),
+            existing: Some("canvas".to_string()),
+            incoming: Some("db".to_string()),
+        };
+
+        let manager = MetadataManager::with_transport(MockTransport);
+        add_sources(&manager);
+        let stats = tokio_test::block_on(manager.fetch_all()).unwrap();
+        assert!(stats.errors.is_empty());
+        assert_eq!(manager.collisions(), vec![expected.clone()]);
+        assert_eq!(
+            manager.get_exact("$get").unwrap().extension.as_deref(),
+            Some("db")
+        );
+
+        // Refetching the same extension is not a collision
+        tokio_test::block_on(manager.fetch_source("db")).unwrap();
+        assert_eq!(manager.collisions().len(), 1);
+
+        let manager = MetadataManager::with_transport(MockTransport)
+            .with_collision_policy(CollisionPolicy::Error);
+        add_sources(&manager);
+        let stats = tokio_test::block_on(manager.fetch_all()).unwrap();
+        assert_eq!(manager.collisions(), vec![expected]);
+        assert_eq!(stats.errors.len(), 1);
+        assert!(matches!(
+            stats.errors[0].source,
+            MetadataError::InvalidData(_)
+        ));
+        assert_eq!(
+            manager.get_exact("$get").unwrap().extension.as_deref(),
+            Some("canvas")
+        );
+        assert_eq!(
+            manager.get_exact("$dbGet").unwrap().extension.as_deref(),
+            Some("db")
+        );
+    }
+
+    #[test]
+    fn test_managers_share_fetcher() {
+        use forge_kit::metadata::{Fetcher, MetadataTransport, TransportFuture};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct CountingTransport(Arc<AtomicUsize>);
+
+        impl MetadataTransport for CountingTransport {
+            fn get<'a>(
+                &'a self,
+                _url: &'a str,
+                _headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Ok((200, r#"[{"name": "$ping"}]"#.to_string())) })
+            }
+        }
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let fetcher = Arc::new(Fetcher::with_transport(CountingTransport(requests.clone())));
+        let first = MetadataManager::with_fetcher(fetcher.clone());
+        let second = MetadataManager::with_fetcher(fetcher.clone());
+
+        for manager in [&first, &second] {
+            manager.add_source(
+                MetadataSource::new("core").with_functions("https://meta.test/functions.json"),
+            );
+            let stats = tokio_test::block_on(manager.fetch_all()).unwrap();
+            assert_eq!(stats.functions, 1);
+            assert!(manager.get_exact("$ping").is_some());
+        }
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(Arc::strong_count(&fetcher), 3);
+    }
+
+    #[test]
+    fn test_fetcher_passes_headers_to_transport() {
+        use forge_kit::metadata::{Fetcher, MetadataTransport, TransportFuture};
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct RecordingTransport(Mutex<Vec<(String, String)>>);
+
+        impl MetadataTransport for RecordingTransport {
+            fn get<'a>(
+                &'a self,
+                _url: &'a str,
+                headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                *self.0.lock().unwrap() = headers
+                    .iter()
+                    .map(|&(name, value)| (name.to_string(), value.to_string()))
+                    .collect();
+                Box::pin(async { Ok((200, "[]".to_string())) })
+            }
+        }
+
+        let transport = Arc::new(RecordingTransport::default());
+
+        struct Shared(Arc<RecordingTransport>);
+
+        impl MetadataTransport for Shared {
+            fn get<'a>(
+                &'a self,
+                url: &'a str,
+                headers: &'a [(&'a str, &'a str)],
+            ) -> TransportFuture<'a> {
+                self.0.get(url, headers)
+            }
+        }
+
+        let fetcher = Fetcher::with_transport(Shared(transport.clone()))
+            .with_header("Authorization", "Bearer secret");
+        tokio_test::block_on(fetcher.fetch_functions("https://meta.test/f.json", "core".into()))
+            .unwrap();
+
+        let headers = transport.0.lock().unwrap().clone();
+        assert!(
+            headers
+                .iter()
+                .any(|(name, value)| name == "User-Agent" && value.starts_with("forge-kit/"))
+        );
+        assert!(headers.contains(&("Authorization".to_string(), "Bearer secret".to_string())));
+    }
+
+    #[test]
+    fn test_js_scanner_fixtures() {
+        let dir = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/custom_functions"
+        );
+        let json = MetadataManager::new()
+            .generate_custom_functions_json(dir)
+            .unwrap();
+        let functions: Vec<Function> = serde_json::from_str(&json).unwrap();
+        let find = |name: &str| {
+            functions
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap_or_else(|| panic!("{} not scanned", name))
+        };
+
+        let mut names: Vec<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            ["$createChannel", "$fetchUser", "$greet", "$ping", "$sum"]
+        );
+
+        // Concatenated string descriptions
+        let greet = find("$greet");
+        assert_eq!(greet.description, "Greets a user, optionally by nickname");
+        assert_eq!(greet.output_types(), ["String"]);
+        let user = &greet.args.as_ref().unwrap()[0];
+        assert_eq!(user.description, "The user's ID");
+        assert_eq!(user.arg_type, serde_json::json!("User"));
+        assert_eq!(greet.line, Some(3));
+        assert_eq!(greet.category.as_deref(), Some("custom"));
+        assert!(greet.aliases.is_none());
+
+        // Enum args by reference and by inline values
+        let args = find("$createChannel").args.clone().unwrap();
+        assert_eq!(args[0].arg_type, serde_json::json!("Enum"));
+        assert_eq!(args[0].enum_name.as_deref(), Some("ChannelType"));
+        assert_eq!(args[0].arg_enum, None);
+        assert_eq!(args[1].enum_name.as_deref(), Some("CreateMode"));
+        assert_eq!(
+            args[1].arg_enum,
+            Some(vec!["fast".to_string(), "safe".to_string()])
+        );
+        assert_eq!(args[1].required, Some(false));
+
+        // Adjacent objects keep their own fields, whatever the key order
+        let ping = find("$ping");
+        assert_eq!(ping.description, "Has no arguments");
+        assert!(ping.args.is_none());
+        let sum = find("$sum");
+        assert_eq!(sum.description, "Adds numbers");
+        let sum_args = sum.args.as_ref().unwrap();
+        assert_eq!(sum_args.len(), 1);
+        assert_eq!(sum_args[0].name, "amount");
+        assert!(sum_args[0].rest);
+        assert_eq!(sum_args[0].arg_type, serde_json::json!("Number"));
+    }
+
+    #[test]
+    fn test_js_scanner_aliases_round_trip() {
+        let dir = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/custom_functions"
+        );
+        let manager = MetadataManager::new();
+        let json = manager.generate_custom_functions_json(dir).unwrap();
+
+        let functions: Vec<Function> = serde_json::from_str(&json).unwrap();
+        let fetch_user = functions.iter().find(|f| f.name == "$fetchUser").unwrap();
+        assert_eq!(fetch_user.category.as_deref(), Some("users"));
+        assert_eq!(
+            fetch_user.aliases,
+            Some(vec!["$getUser".to_string(), "userFetch".to_string()])
+        );
+
+        manager.add_custom_functions_from_json(&json).unwrap();
+        for name in ["$fetchUser", "$getUser", "$userFetch"] {
+            let func = manager
+                .get_exact(name)
+                .unwrap_or_else(|| panic!("{} not registered", name));
+            assert_eq!(func.category.as_deref(), Some("users"));
+        }
+    }
+
+    #[test]
+    fn test_scan_skips_ignored_dirs_and_symlink_cycles() {
+        use forge_kit::metadata::ScanOptions;
+
+        let dir = std::env::temp_dir().join(format!("forge-kit-scan-{}", std::process::id()));
+        let write = |rel: &str, name: &str| {
+            let path = dir.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, format!("export default {{ name: \"{}\" }}", name)).unwrap();
+        };
+        write("src/commands/own.ts", "$own");
+        write("node_modules/pkg/index.js", "$vendored");
+        write("build-cache/out.js", "$built");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.join("src"), dir.join("src/commands/loop")).unwrap();
+
+        let names = |json: String| {
+            let mut names: Vec<String> = serde_json::from_str::<Vec<Function>>(&json)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.name)
+                .collect();
+            names.sort();
+            names
+        };
+        let manager = MetadataManager::new();
+
+        let default_scan = names(manager.generate_custom_functions_json(&dir).unwrap());
+        assert_eq!(default_scan, ["$built", "$own"]);
+
+        let custom = manager
+            .generate_custom_functions_json_with_options(
+                &dir,
+                ScanOptions::default().with_ignore(["node_*", "build*"]),
+            )
+            .unwrap();
+        assert_eq!(names(custom), ["$own"]);
+
+        let everything = manager
+            .generate_custom_functions_json_with_options(
+                &dir,
+                ScanOptions::default().with_ignore(Vec::<String>::new()),
+            )
+            .unwrap();
+        assert_eq!(names(everything), ["$built", "$own", "$vendored"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_sequential() {
+        use forge_kit::metadata::ScanOptions;
+
+        let dir = std::env::temp_dir().join(format!("forge-kit-par-{}", std::process::id()));
+        for i in 0..40 {
+            let sub = dir.join(format!("group{}", i % 4));
+            std::fs::create_dir_all(&sub).unwrap();
+            std::fs::write(
+                sub.join(format!("file{}.ts", i)),
+                format!(
+                    "export const a = {{ name: \"$first{i}\" }}\nexport const b = {{ name: \"$second{i}\" }}"
+                ),
+            )
+            .unwrap();
+        }
+
+        let manager = MetadataManager::new();
+        let scan = |parallel: bool| {
+            manager
+                .generate_custom_functions_json_with_options(
+                    &dir,
+                    ScanOptions::default().with_parallel(parallel),
+                )
+                .unwrap()
+        };
+        let sequential = scan(false);
+        let parallel = scan(true);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(parallel, sequential);
+        let functions: Vec<Function> = serde_json::from_str(&parallel).unwrap();
+        assert_eq!(functions.len(), 80);
+        let order: Vec<_> = functions
+            .iter()
+            .map(|f| (f.local_path.clone(), f.line))
+            .collect();
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(order, sorted);
+    }
+
+    #[test]
+    fn test_function_origin_from_extension() {
+        assert_eq!(FunctionOrigin::from_extension(None), FunctionOrigin::Core);
+        assert_eq!(
+            FunctionOrigin::from_extension(Some("ForgeScript")),
+            FunctionOrigin::Core
+        );
+        assert_eq!(
+            FunctionOrigin::from_extension(Some("ForgeDB")),
+            FunctionOrigin::Extension("ForgeDB".to_string())
+        );
+    }
+
+    #[test]
+    fn test_metadata_source_relative_urls() {
+        let source = MetadataSource::new("ForgeDB")
+            .with_base("https://cdn.example.com/forgedb/")
+            .with_functions("functions.json")
+            .with_enums("/meta/enums.json")
+            .with_events("https://other.example.com/events.json");
+
+        assert_eq!(
+            source.resolved_functions_url().as_deref(),
+            Some("https://cdn.example.com/forgedb/functions.json")
+        );
+        assert_eq!(
+            source.resolved_enums_url().as_deref(),
+            Some("https://cdn.example.com/forgedb/meta/enums.json")
+        );
+        assert_eq!(
+            source.resolved_events_url().as_deref(),
+            Some("https://other.example.com/events.json")
+        );
+
+        // Without a base, URLs are used as-is
+        let plain = MetadataSource::new("core").with_functions("functions.json");
+        assert_eq!(
+            plain.resolved_functions_url().as_deref(),
+            Some("functions.json")
+        );
+    }
+
+    #[test]
+    fn test_for_each_without_collecting() {
+        let manager = MetadataManager::new();
+        let functions: Vec<Function> = (0..10)
+            .map(|i| create_test_function(&format!("$func{}", i)))
+            .collect();
+        let mut enums = HashMap::new();
+        enums.insert(
+            "Color".to_string(),
+            vec!["Red".to_string(), "Blue".to_string()],
+        );
+        let event = Event {
+            name: "onMessage".to_string(),
+            description: "Test event".to_string(),
+            fields: None,
+        };
+        manager
+            .import_cache(MetadataCache::new(functions, enums, vec![event]))
+            .unwrap();
+
+        let mut matching = 0;
+        manager.for_each_function(|f| {
+            if f.name.ends_with(['1', '3', '5']) {
+                matching += 1;
+            }
+        });
+        assert_eq!(matching, 3);
+
+        let mut total = 0;
+        manager.for_each_function(|_| total += 1);
+        assert_eq!(total, manager.all_functions().len());
+
+        let mut enum_values = 0;
+        manager.for_each_enum(|name, values| {
+            assert_eq!(name, "Color");
+            enum_values += values.len();
+        });
+        assert_eq!(enum_values, 2);
+
+        let mut events = Vec::new();
+        manager.for_each_event(|e| events.push(e.name.clone()));
+        assert_eq!(events, vec!["onMessage"]);
+    }
+
     #[test]
     fn test_manager_clear() {
         let manager = MetadataManager::new();
@@ -143,4 +1466,122 @@ mod tests {
         manager.clear();
         assert_eq!(manager.function_count(), 0);
     }
+
+    #[test]
+    fn test_merge_layers_on_existing_metadata() {
+        let manager = MetadataManager::new();
+        let mut core_enums = HashMap::new();
+        core_enums.insert("Color".to_string(), vec!["Red".to_string()]);
+        manager
+            .import_cache(MetadataCache::new(
+                vec![create_test_function("$ping"), create_test_function("$send")],
+                core_enums,
+                vec![],
+            ))
+            .unwrap();
+
+        let mut custom_send = create_test_function("$send");
+        custom_send.description = "Custom send".to_string();
+        manager
+            .merge(MetadataCache::new(
+                vec![create_test_function("$myCustom"), custom_send],
+                HashMap::new(),
+                vec![],
+            ))
+            .expect("Merge failed");
+
+        assert_eq!(manager.function_count(), 3);
+        assert!(manager.get_exact("$ping").is_some());
+        assert!(manager.get_exact("$myCustom").is_some());
+        assert_eq!(
+            manager.get_exact("$send").unwrap().description,
+            "Custom send"
+        );
+        assert_eq!(manager.get_enum("Color"), Some(vec!["Red".to_string()]));
+    }
+
+    #[test]
+    fn test_merge_from_manager() {
+        let core = MetadataManager::new();
+        core.import_cache(MetadataCache::new(
+            vec![create_test_function("$ping")],
+            HashMap::new(),
+            vec![],
+        ))
+        .unwrap();
+
+        let mut enums = HashMap::new();
+        enums.insert("Mode".to_string(), vec!["Fast".to_string()]);
+        let extra = MetadataManager::new();
+        extra
+            .import_cache(MetadataCache::new(
+                vec![create_test_function("$extra")],
+                enums,
+                vec![],
+            ))
+            .unwrap();
+
+        core.merge_from(&extra);
+        core.merge_from(&core);
+
+        assert_eq!(core.function_count(), 2);
+        assert!(core.get_exact("$ping").is_some());
+        assert!(core.get_exact("$extra").is_some());
+        assert!(core.get_enum("Mode").is_some());
+        assert_eq!(extra.function_count(), 1);
+    }
+
+    #[test]
+    fn test_merge_from_both_ways_concurrently() {
+        let left = MetadataManager::new();
+        left.update_function(create_test_function("$left"));
+        let right = MetadataManager::new();
+        right.update_function(create_test_function("$right"));
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| (0..200).for_each(|_| left.merge_from(&right)));
+            scope.spawn(|| (0..200).for_each(|_| right.merge_from(&left)));
+        });
+
+        assert_eq!(left.function_count(), 2);
+        assert_eq!(right.function_count(), 2);
+    }
+
+    #[test]
+    fn test_cache_migration() {
+        let source = MetadataManager::new();
+        source
+            .import_cache(MetadataCache::new(
+                vec![create_test_function("$ping")],
+                HashMap::new(),
+                vec![],
+            ))
+            .unwrap();
+        let json = source.cache_to_json().unwrap();
+
+        let manager = MetadataManager::new();
+        manager
+            .cache_from_json(&json)
+            .expect("current version loads");
+        assert!(manager.get_exact("$ping").is_some());
+
+        let v1 = serde_json::json!({
+            "functions": [create_test_function("$v1")],
+            "enums": { "Color": ["Red"] },
+            "events": [],
+            "version": 1,
+        });
+        let cache = MetadataCache::migrate(&v1.to_string()).expect("v1 cache migrates");
+        assert_eq!(cache.version, 1);
+        assert_eq!(cache.functions.len(), 1);
+        assert_eq!(cache.functions[0].name, "$v1");
+        assert_eq!(cache.enums["Color"], vec!["Red".to_string()]);
+
+        let mut future: serde_json::Value = serde_json::from_str(&json).unwrap();
+        future["version"] = serde_json::json!(999);
+        let err = MetadataCache::migrate(&future.to_string()).unwrap_err();
+        assert!(err.to_string().contains("999"));
+        assert!(manager.cache_from_json(&future.to_string()).is_err());
+        assert!(manager.get_exact("$ping").is_some());
+    }
 }
@@ -4,6 +4,9 @@ use forge_kit::utils::{calculate_stats, contains_javascript, count_nodes};
 #[cfg(test)]
 mod tests {
     use super::{AstNode, calculate_stats, contains_javascript, count_nodes, parse};
+    use forge_kit::parser::{
+        Argument, Modifiers, ParseEvent, Span, parse_events, parse_raw, parse_until,
+    };
     use forge_kit::visitor::{AstVisitor, FunctionCollector, NodeCounter};
 
     #[test]
@@ -293,22 +296,22 @@ mod tests {
         let (ast, errors) = parse("code: `$parent[$inner[a;b];outer_second]`");
         assert!(errors.is_empty());
 
-        if let AstNode::Program { body, .. } = ast {
-            if let AstNode::FunctionCall { args, .. } = &body[0] {
-                let args = args.as_ref().unwrap();
-                assert_eq!(args.len(), 2);
-            }
+        if let AstNode::Program { body, .. } = ast
+            && let AstNode::FunctionCall { args, .. } = &body[0]
+        {
+            let args = args.as_ref().unwrap();
+            assert_eq!(args.len(), 2);
         }
     }
 
     #[test]
     fn test_empty_and_whitespace_args() {
         let (ast, _) = parse("code: `$func[; ;last]`");
-        if let AstNode::Program { body, .. } = ast {
-            if let AstNode::FunctionCall { args, .. } = &body[0] {
-                let args = args.as_ref().unwrap();
-                assert_eq!(args.len(), 3);
-            }
+        if let AstNode::Program { body, .. } = ast
+            && let AstNode::FunctionCall { args, .. } = &body[0]
+        {
+            let args = args.as_ref().unwrap();
+            assert_eq!(args.len(), 3);
         }
     }
 
@@ -317,17 +320,79 @@ mod tests {
         let (ast, errors) = parse("code: `$@[ 100 ]funcName`");
         assert!(errors.is_empty());
 
-        if let AstNode::Program { body, .. } = ast {
-            if let AstNode::FunctionCall {
+        if let AstNode::Program { body, .. } = ast
+            && let AstNode::FunctionCall {
                 name, modifiers, ..
             } = &body[0]
-            {
-                assert_eq!(name, "funcName");
-                assert_eq!(modifiers.count.as_deref(), Some(" 100 "));
-            }
+        {
+            assert_eq!(name, "funcName");
+            assert_eq!(modifiers.count.as_deref(), Some(" 100 "));
         }
     }
 
+    #[test]
+    fn test_per_modifier_spans() {
+        let source = "code: `$!#@[2]foo`";
+        let (ast, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let AstNode::Program { body, .. } = ast else {
+            panic!("expected program");
+        };
+        let AstNode::FunctionCall { modifiers, .. } = &body[0] else {
+            panic!("expected function call");
+        };
+
+        let silent = modifiers.silent_span.unwrap();
+        let negated = modifiers.negated_span.unwrap();
+        let count = modifiers.count_span.unwrap();
+        assert_eq!(&source[silent.start..silent.end], "!");
+        assert_eq!(&source[negated.start..negated.end], "#");
+        assert_eq!(&source[count.start..count.end], "@[2]");
+        assert!(silent.end <= negated.start && negated.end <= count.start);
+        assert_eq!(modifiers.span, Some(Span::new(silent.start, count.end)));
+    }
+
+    #[test]
+    fn test_empty_argument_list_has_zero_args() {
+        let arg_count = |source: &str| {
+            let (ast, _) = parse(source);
+            match ast {
+                AstNode::Program { body, .. } => match &body[0] {
+                    AstNode::FunctionCall { args, .. } => args.as_ref().map(Vec::len),
+                    other => panic!("expected function call, got {:?}", other),
+                },
+                _ => unreachable!(),
+            }
+        };
+
+        assert_eq!(arg_count("code: `$f`"), None);
+        assert_eq!(arg_count("code: `$f[]`"), Some(0));
+        assert_eq!(arg_count("code: `$f[ ]`"), Some(1));
+        assert_eq!(arg_count("code: `$f[;]`"), Some(2));
+        assert_eq!(arg_count("code: `$f[a;]`"), Some(2));
+    }
+
+    #[test]
+    fn test_as_text_resolves_escapes() {
+        // Inside the block: $let[agent;iPhone\\; iOS\\] \\$x \`]
+        let source = "code: `$let[agent;iPhone\\\\; iOS\\\\] \\\\$x \\`]`";
+        let (ast, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let AstNode::Program { body, .. } = ast else {
+            panic!("expected program");
+        };
+        let AstNode::FunctionCall {
+            args: Some(args), ..
+        } = &body[0]
+        else {
+            panic!("expected function call with args");
+        };
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1].as_text().as_deref(), Some("iPhone; iOS] $x `"));
+    }
+
     #[test]
     fn test_unicode_safety() {
         let input = "Stars: ⭐ and 宝箱";
@@ -400,6 +465,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bracket_spans() {
+        let input = "x code: `$get[a]`";
+        let (ast, _) = parse(input);
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        let AstNode::FunctionCall {
+            open_bracket: Some(open),
+            close_bracket: Some(close),
+            ..
+        } = &body[1]
+        else {
+            panic!("expected bracketed call, got {:?}", body[1]);
+        };
+        assert_eq!((open.start, open.end), (13, 14));
+        assert_eq!((close.start, close.end), (15, 16));
+        assert_eq!(&input[open.start..open.end], "[");
+        assert_eq!(&input[close.start..close.end], "]");
+
+        // Without brackets, and with an unclosed list, there is no close bracket
+        let (ast, _) = parse("code: `$ping $get[a`");
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        let brackets: Vec<_> = body
+            .iter()
+            .filter_map(|n| match n {
+                AstNode::FunctionCall {
+                    open_bracket,
+                    close_bracket,
+                    ..
+                } => Some((open_bracket.is_some(), close_bracket.is_some())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(brackets, vec![(false, false), (true, false)]);
+    }
+
+    #[test]
+    fn test_sibling_blocks_skip_embedded_keys() {
+        let input = "a: { code: `$first[${ \"code: `\" }]` },\n\
+                     js: ${ \"code: `\" },\n\
+                     b: { code: `$c[code: `$fake`] $second` }";
+        let (ast, errors) = parse(input);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let names = forge_kit::utils::extract_function_names(&ast);
+        assert_eq!(names, vec!["first", "second"]);
+
+        if let AstNode::Program { body, .. } = &ast {
+            let second = body
+                .iter()
+                .find(|n| matches!(n, AstNode::FunctionCall { name, .. } if name == "second"))
+                .unwrap();
+            let span = second.span();
+            assert_eq!(&input[span.start..span.end], "$second");
+        }
+    }
+
     // =========================================================================
     // Bare-bracket tests (Fix #2)
     // =========================================================================
@@ -523,170 +648,1824 @@ mod tests {
             }
         }
     }
-}
 
-#[cfg(feature = "validation")]
-mod validation_tests {
-    use forge_kit::metadata::{MetadataCache, MetadataManager};
-    use forge_kit::parser::{AstNode, ErrorKind, Parser, ValidationConfig};
-    use forge_kit::types::{Arg, Function};
-    use std::collections::HashMap;
-    use std::sync::Arc;
+    #[test]
+    fn test_parse_until_offset() {
+        let source = "code: `$first[a] $second[$inner[b]] $third` tail code: `$later`";
+        let offset = source.find("inner").unwrap();
+        let (ast, errors) = parse_until(source, offset);
+        assert!(errors.is_empty());
 
-    fn create_mock_metadata() -> Arc<MetadataManager> {
-        let manager = MetadataManager::new();
-        let valid_func = Function {
-            name: "$validFunc".to_string(),
-            args: Some(vec![
-                Arg {
-                    name: "arg1".to_string(),
-                    required: Some(true),
-                    ..Default::default()
-                },
-                Arg {
-                    name: "arg2".to_string(),
-                    required: Some(false),
-                    ..Default::default()
-                },
-            ]),
-            brackets: Some(true),
-            ..Default::default()
-        };
+        let names = forge_kit::utils::extract_function_names(&ast);
+        assert_eq!(names, vec!["first", "second", "inner"]);
 
-        let enum_func = Function {
-            name: "$enumFunc".to_string(),
-            args: Some(vec![Arg {
-                name: "option".to_string(),
-                required: Some(true),
-                arg_enum: Some(vec!["yes".to_string(), "no".to_string()]),
-                ..Default::default()
-            }]),
-            brackets: Some(true),
+        match ast {
+            AstNode::Program { body, span } => {
+                let last = body.last().expect("Expected parsed nodes");
+                assert!(last.span().start <= offset && offset < last.span().end);
+                assert!(span.end <= source.find(" tail").unwrap());
+            }
+            _ => panic!("Expected program node"),
+        }
+
+        // An offset past the end parses everything
+        let (full, _) = parse_until(source, source.len());
+        assert_eq!(count_nodes(&full), count_nodes(&parse(source).0));
+    }
+
+    #[test]
+    fn test_ast_path_round_trip() {
+        use forge_kit::utils::{node_at_path, path_to};
+
+        let source = "code: `hi $outer[a;$inner[x]]`";
+        let (ast, _) = parse(source);
+
+        let start = source.find("$inner").unwrap();
+        let inner_span = Span::new(start, start + "$inner[x]".len());
+
+        let path = path_to(&ast, inner_span).expect("path to $inner");
+        assert_eq!(path, vec![1, 1]);
+
+        match node_at_path(&ast, &path) {
+            Some(AstNode::FunctionCall { name, span, .. }) => {
+                assert_eq!(name, "inner");
+                assert_eq!(*span, inner_span);
+            }
+            other => panic!("expected $inner, got {:?}", other),
+        }
+
+        assert!(node_at_path(&ast, &vec![]).is_some());
+        assert!(node_at_path(&ast, &vec![5]).is_none());
+        assert!(path_to(&ast, Span::new(0, 1)).is_none());
+    }
+
+    #[test]
+    fn test_normalize_whitespace_options() {
+        use forge_kit::utils::{WhitespaceOptions, normalize_whitespace};
+
+        let script = "code: `\r\n$let[a;1]\r\n\r\n\r\n$get[a]\n\n\n$log[a]`";
+
+        let untouched = normalize_whitespace(script, &WhitespaceOptions::default());
+        assert_eq!(untouched, script);
+
+        let endings = WhitespaceOptions {
+            normalize_line_endings: true,
             ..Default::default()
         };
+        assert_eq!(
+            normalize_whitespace(script, &endings),
+            "code: `\n$let[a;1]\n\n\n$get[a]\n\n\n$log[a]`"
+        );
 
-        let forbidden_brackets_func = Function {
-            name: "$forbidden".to_string(),
-            brackets: None,
+        let collapse = WhitespaceOptions {
+            collapse_blank_lines: true,
             ..Default::default()
         };
-
-        let cache = MetadataCache::new(
-            vec![valid_func, enum_func, forbidden_brackets_func],
-            HashMap::new(),
-            vec![],
+        assert_eq!(
+            normalize_whitespace(script, &collapse),
+            "code: `\r\n$let[a;1]\r\n\r\n$get[a]\n\n$log[a]`"
         );
-        manager.import_cache(cache).unwrap();
-        Arc::new(manager)
-    }
 
-    #[test]
-    fn test_unclosed_bracket() {
-        let (_ast, errors) =
-            Parser::with_config("code: `$get[unclosed`", ValidationConfig::syntax_only()).parse();
-        assert!(!errors.is_empty());
+        let all = WhitespaceOptions {
+            collapse_blank_lines: true,
+            normalize_line_endings: true,
+            ensure_trailing_newline: true,
+        };
+        let normalized = normalize_whitespace(script, &all);
+        assert_eq!(normalized, "code: `\n$let[a;1]\n\n$get[a]\n\n$log[a]`\n");
+        assert_eq!(normalize_whitespace(&normalized, &all), normalized);
+        assert_eq!(normalize_whitespace("", &all), "");
     }
 
     #[test]
-    fn test_unclosed_js() {
-        let (_ast, errors) =
-            Parser::with_config("code: `${ unclosed`", ValidationConfig::syntax_only()).parse();
-        assert!(!errors.is_empty());
+    fn test_stats_of_matches_calculate_stats() {
+        use forge_kit::utils::stats_of;
+
+        let source = "intro code: `$let[a;$get[$c[note] b]] ${ x } \\$ $get[a]` outro";
+        let (ast, _) = parse(source);
+        assert_eq!(stats_of(source), calculate_stats(&ast));
+
+        let (ast, _) = parse("");
+        assert_eq!(stats_of(""), calculate_stats(&ast));
     }
 
     #[test]
-    fn test_unclosed_block() {
-        let (ast, errors) =
-            Parser::with_config("code: `$foo", ValidationConfig::syntax_only()).parse();
-        assert_eq!(errors.len(), 1);
-        assert_eq!(errors[0].message, "Unclosed code block");
-        if let AstNode::Program { body, .. } = ast {
-            if let AstNode::Text { content, .. } = &body[0] {
-                assert_eq!(content, "code: `$foo");
+    fn test_coalesce_text_runs() {
+        use forge_kit::utils::coalesce_text;
+
+        // `\\$a\\$b` escapes both dollars, leaving four small text nodes
+        let source = "code: `\\\\$a\\\\$b`";
+        let (mut ast, _) = parse(source);
+        assert_eq!(count_nodes(&ast), 5);
+
+        coalesce_text(&mut ast);
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        assert_eq!(body.len(), 1);
+        match &body[0] {
+            AstNode::Text { content, span } => {
+                assert_eq!(content, "$a$b");
+                assert_eq!(&source[span.start..span.end], "\\\\$a\\\\$b");
             }
+            other => panic!("expected text, got {:?}", other),
         }
+
+        // Function calls and escaped nodes stay as boundaries, runs inside
+        // arguments are merged too
+        let (mut ast, _) = parse("code: `a\\\\$b$get[x\\\\$y]c$c[z]d`");
+        coalesce_text(&mut ast);
+        assert_eq!(count_nodes(&ast), 7);
     }
 
-    /// A \` inside a code block should NOT trigger an "Unclosed code block" error
-    /// because the backtick is escaped.
     #[test]
-    fn test_escaped_backtick_does_not_close_block() {
-        // code: `hello\`still_inside` — the \` is escaped, block closes at the final `.
-        let (_, errors) = Parser::with_config(
-            "code: `hello\\`still_inside`",
-            ValidationConfig::syntax_only(),
-        )
-        .parse();
+    fn test_static_text() {
+        use forge_kit::utils::static_text;
+
+        let (ast, _) = parse("Hello code: `$get[x] world`");
+        assert_eq!(static_text(&ast), "Hello x world");
+
+        let (ast, _) = parse("code: `a ${1 + 1} $c[note]$escape[b;c] $if[$x;yes]`");
+        assert_eq!(static_text(&ast), "a  b;c yes");
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        use forge_kit::utils::strip_comments;
+
+        let (mut ast, _) = parse("code: `$c[note]$escape[x]$get[$C[inner];y]`");
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        assert!(matches!(&body[0], AstNode::Escaped { function, .. } if function == "c"));
+        assert!(matches!(&body[1], AstNode::Escaped { function, .. } if function == "escape"));
+
+        strip_comments(&mut ast);
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        assert_eq!(body.len(), 2);
         assert!(
-            errors.is_empty(),
-            "Escaped backtick must not close the code block: {:?}",
-            errors
+            matches!(&body[0], AstNode::Escaped { content, function, .. } if content == "x" && function == "escape")
         );
+        let AstNode::FunctionCall {
+            args: Some(args), ..
+        } = &body[1]
+        else {
+            panic!("expected call");
+        };
+        assert!(args[0].parts.is_empty());
     }
 
     #[test]
-    fn test_validation_argument_count() {
-        let metadata = create_mock_metadata();
-        let config = ValidationConfig {
-            validate_arguments: true,
-            validate_functions: true,
-            ..Default::default()
-        };
-        let (_ast, errors) =
-            Parser::with_validation("code: `$validFunc[]`", config.clone(), metadata.clone())
-                .parse();
-        assert_eq!(errors[0].kind, ErrorKind::ArgumentCount);
+    fn test_ast_serializes_as_tagged_tree() {
+        let (ast, _) = parse("code: `$!get[a;$c[b]]`");
+        let json = serde_json::to_value(&ast).unwrap();
+
+        assert_eq!(json["type"], "Program");
+        let call = &json["body"][0];
+        assert_eq!(call["type"], "FunctionCall");
+        assert_eq!(call["name"], "get");
+        assert_eq!(call["modifiers"]["silent"], true);
+        assert_eq!(call["name_span"]["start"], 7);
+        assert_eq!(call["args"][0]["parts"][0]["content"], "a");
+        assert_eq!(call["args"][1]["parts"][0]["type"], "Escaped");
+
+        let back: AstNode = serde_json::from_value(json).unwrap();
+        assert_eq!(count_nodes(&back), count_nodes(&ast));
     }
 
     #[test]
-    fn test_validation_enum_values() {
-        let metadata = create_mock_metadata();
-        let config = ValidationConfig {
-            validate_enums: true,
-            validate_functions: true,
-            ..Default::default()
+    fn test_find_references() {
+        use forge_kit::utils::find_references;
+
+        let source = "code: `$get[x] text $get[y] $!GET[$get[z]] $getter`";
+        let (ast, _) = parse(source);
+
+        let spans = find_references(&ast, "$get");
+        assert_eq!(spans.len(), 4);
+        for span in &spans {
+            assert!(source[span.start..span.end].eq_ignore_ascii_case("get"));
+        }
+        assert_eq!(find_references(&ast, "get"), spans);
+        assert!(find_references(&ast, "set").is_empty());
+    }
+
+    #[test]
+    fn test_argument_separator_spans() {
+        let input = "code: `$f[a;b;c] $g[$h[x;y]] $i[]`";
+        let (ast, _) = parse(input);
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
         };
-        let (_ast, errors) =
-            Parser::with_validation("code: `$enumFunc[maybe]`", config.clone(), metadata.clone())
-                .parse();
-        assert_eq!(errors[0].kind, ErrorKind::EnumValue);
+        let separators: Vec<Vec<usize>> = body
+            .iter()
+            .filter_map(|n| match n {
+                AstNode::FunctionCall { separators, .. } => Some(
+                    separators
+                        .iter()
+                        .inspect(|s| assert_eq!(&input[s.start..s.end], ";"))
+                        .map(|s| s.start)
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect();
+        // Separators of nested calls belong to the nested call
+        assert_eq!(separators, vec![vec![11, 13], vec![], vec![]]);
     }
 
     #[test]
-    fn test_validation_brackets() {
-        let metadata = create_mock_metadata();
-        let config = ValidationConfig {
-            validate_brackets: true,
-            validate_functions: true,
-            ..Default::default()
+    fn test_parse_error_display() {
+        use forge_kit::parser::{ErrorKind, ParseError};
+        use forge_kit::utils::format_errors;
+
+        let source = "code: `\n  $validFunc $x`";
+        let errors = [
+            ParseError::new(
+                "$validFunc requires brackets",
+                Span::new(10, 20),
+                ErrorKind::BracketUsage,
+            ),
+            ParseError::warning(
+                "Unknown function: $x",
+                Span::new(21, 23),
+                ErrorKind::UnknownFunction,
+            ),
+        ];
+        assert_eq!(
+            errors[0].to_string(),
+            "error[BracketUsage]: $validFunc requires brackets (10..20)"
+        );
+
+        let as_error: Box<dyn std::error::Error> = Box::new(errors[1].clone());
+        assert!(as_error.to_string().starts_with("warning[UnknownFunction]"));
+
+        assert_eq!(
+            format_errors(source, &errors),
+            "error[BracketUsage]: $validFunc requires brackets\n\
+             \x20--> 2:3\n\
+             \x20 |\n\
+             2 |   $validFunc $x`\n\
+             \x20 |   ^^^^^^^^^^\n\
+             \n\
+             warning[UnknownFunction]: Unknown function: $x\n\
+             \x20--> 2:14\n\
+             \x20 |\n\
+             2 |   $validFunc $x`\n\
+             \x20 |              ^^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_diagnostic_multiline() {
+        use forge_kit::parser::{ErrorKind, ParseError};
+        use forge_kit::utils::render_diagnostic;
+
+        let source = "module.exports = {\n    code: `$send[hello\n    world]`\n};\n";
+        let start = source.find("$send").unwrap();
+        let end = source.find("]`").unwrap() + 1;
+        let error = ParseError::new(
+            "$send expects 2 arguments",
+            Span::new(start, end),
+            ErrorKind::ArgumentCount,
+        );
+        assert_eq!(
+            render_diagnostic(source, &error),
+            "error[ArgumentCount]: $send expects 2 arguments\n\
+             \x20--> 2:12\n\
+             \x20 |\n\
+             2 |     code: `$send[hello\n\
+             \x20 |            ^^^^^^^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_ast_eq_ignoring_spans() {
+        use forge_kit::utils::ast_eq_ignoring_spans;
+
+        let script = "$!#@[2]get[a;$c[b]] ${x} \\$y";
+        let (ast, _) = parse_raw(script);
+        let mut shifted = ast.clone();
+        shifted.offset_spans(42);
+        assert!(ast_eq_ignoring_spans(&ast, &shifted));
+
+        let call = |source: &str| {
+            let (ast, _) = parse(source);
+            let AstNode::Program { mut body, .. } = ast else {
+                panic!("expected program");
+            };
+            body.retain(|node| matches!(node, AstNode::FunctionCall { .. }));
+            body.remove(0)
         };
-        let (_ast, errors) =
-            Parser::with_validation("code: `$validFunc`", config.clone(), metadata.clone()).parse();
-        assert_eq!(errors[0].kind, ErrorKind::BracketUsage);
+        let a = call(&format!("code: `{}`", script));
+        let b = call(&format!("header\ncode: `{}`", script));
+        assert_ne!(a.span(), b.span());
+        assert!(ast_eq_ignoring_spans(&a, &b));
+
+        // Content differences still count
+        let c = call("code: `$!#@[2]get[a;$c[B]]`");
+        assert!(!ast_eq_ignoring_spans(&a, &c));
+        let d = call("code: `$!@[2]get[a;$c[b]]`");
+        assert!(!ast_eq_ignoring_spans(&a, &d));
     }
 
     #[test]
-    fn test_validation_unknown_function() {
-        let metadata = create_mock_metadata();
-        let config = ValidationConfig {
-            validate_functions: true,
-            ..Default::default()
+    fn test_diff_ast() {
+        use forge_kit::utils::{AstChange, diff_ast};
+
+        let old_src = "code: `$get[a]`";
+        let new_src = "code: `$get[b]`";
+        let (old, _) = parse(old_src);
+        let (new, _) = parse(new_src);
+        let changes = diff_ast(&old, &new);
+        assert_eq!(changes.len(), 1);
+        let AstChange::Changed { old: o, new: n } = changes[0] else {
+            panic!("expected a change, got {:?}", changes[0]);
         };
-        let (_ast, errors) =
-            Parser::with_validation("code: `$unknown[]`", config.clone(), metadata.clone()).parse();
-        assert_eq!(errors[0].kind, ErrorKind::UnknownFunction);
+        assert_eq!(&old_src[o.start..o.end], "a");
+        assert_eq!(&new_src[n.start..n.end], "b");
+
+        // Only the prefix is reported; the moved call has equal content
+        let (shifted, _) = parse("x code: `$get[a]`");
+        let (moved, _) = parse("xyz code: `$get[a]`");
+        let changes = diff_ast(&shifted, &moved);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            changes[0],
+            AstChange::Changed { old, .. } if old.start == 0
+        ));
+
+        let (longer, _) = parse("code: `$get[a] $ping`");
+        let changes = diff_ast(&old, &longer);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(changes[1], AstChange::Added(_)));
+        assert_eq!(diff_ast(&longer, &old).len(), 2);
     }
 
-    /// Bare brackets in args must not cause false positives in syntax-only mode.
     #[test]
-    fn test_bare_brackets_no_syntax_error() {
-        let (_, errors) =
-            Parser::with_config("code: `$ban[user; 1m; []`", ValidationConfig::syntax_only())
-                .parse();
-        assert!(
-            errors.is_empty(),
-            "Bare `[` in args should not produce a syntax error: {:?}",
-            errors
+    fn test_rename_function_edits() {
+        use forge_kit::utils::{extract_function_names, rename_function};
+
+        let source = "code: `$get[a] $!#@[2]GET[$get[b]] $getter $set[$get]`";
+        let (ast, _) = parse(source);
+
+        let edits = rename_function(&ast, "$get", "$fetch");
+        assert_eq!(edits.len(), 4);
+        for pair in edits.windows(2) {
+            assert!(pair[0].span.end <= pair[1].span.start);
+        }
+
+        let mut renamed = source.to_string();
+        for edit in edits.iter().rev() {
+            renamed.replace_range(edit.span.start..edit.span.end, &edit.new_text);
+        }
+        assert_eq!(
+            renamed,
+            "code: `$fetch[a] $!#@[2]fetch[$fetch[b]] $getter $set[$fetch]`"
+        );
+
+        let (renamed_ast, errors) = parse(&renamed);
+        assert!(errors.is_empty());
+        assert_eq!(
+            extract_function_names(&renamed_ast),
+            vec!["fetch", "fetch", "fetch", "getter", "set", "fetch"]
+        );
+    }
+
+    #[test]
+    fn test_iter_nodes_matches_flatten_order() {
+        use forge_kit::utils::{flatten_ast, iter_nodes};
+        use forge_kit::visitor::fold;
+
+        let source = include_str!("../benches/data/large_script.js");
+        let (ast, _) = parse(source);
+
+        let iterated: Vec<Span> = iter_nodes(&ast).map(AstNode::span).collect();
+        let flattened: Vec<Span> = flatten_ast(&ast).iter().map(AstNode::span).collect();
+        let folded = fold(&ast, Vec::new(), |mut spans, node| {
+            spans.push(node.span());
+            spans
+        });
+
+        assert_eq!(iterated.len(), count_nodes(&ast));
+        assert_eq!(iterated, flattened);
+        assert_eq!(iterated, folded);
+    }
+
+    #[test]
+    fn test_extract_javascript() {
+        use forge_kit::utils::extract_javascript;
+
+        let source = "code: `${ a } $get[${ 1 };$set[${b}]] ${ unclosed`";
+        let (ast, _) = parse(source);
+
+        let scripts = extract_javascript(&ast);
+        let codes: Vec<&str> = scripts.iter().map(|(code, _)| code.as_str()).collect();
+        assert_eq!(codes, vec![" a ", " 1 ", "b", ""]);
+
+        let (_, nested) = &scripts[2];
+        assert_eq!(&source[nested.start..nested.end], "${b}");
+        let (_, unclosed) = &scripts[3];
+        assert!(source[unclosed.start..].starts_with("${ unclosed"));
+    }
+
+    #[test]
+    fn test_span_chars_multibyte() {
+        use forge_kit::utils::span_chars;
+
+        let source = "aé€😀b";
+        let all: String = span_chars(source, Span::new(0, source.len())).collect();
+        assert_eq!(all, source);
+
+        // 'é' occupies bytes 1..3, '€' 3..6, '😀' 6..10
+        let mid: Vec<char> = span_chars(source, Span::new(1, 6)).collect();
+        assert_eq!(mid, vec!['é', '€']);
+
+        // Mid-char offsets are clamped inwards
+        let clamped: Vec<char> = span_chars(source, Span::new(2, 8)).collect();
+        assert_eq!(clamped, vec!['€']);
+
+        assert_eq!(
+            span_chars(source, Span::new(7, 100)).collect::<String>(),
+            "b"
         );
+        assert_eq!(span_chars(source, Span::new(7, 8)).count(), 0);
+    }
+
+    #[test]
+    fn test_utf16_spans() {
+        use forge_kit::utils::{utf8_to_utf16_offset, utf16_to_utf8_offset};
+
+        let source = "code: `😀 $get[x]`";
+        let (ast, _) = parse(source);
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        let call = body
+            .iter()
+            .find(|node| matches!(node, AstNode::FunctionCall { .. }))
+            .expect("function call");
+
+        // The emoji is 4 UTF-8 bytes but 2 UTF-16 code units
+        let span = call.span();
+        assert_eq!((span.start, span.end), (12, 19));
+        assert_eq!(span.to_utf16(source), (10, 17));
+        assert_eq!(utf16_to_utf8_offset(source, 10), 12);
+
+        // Offsets inside the emoji clamp to its start
+        assert_eq!(utf8_to_utf16_offset(source, 9), 7);
+        assert_eq!(utf16_to_utf8_offset(source, 8), 7);
+        assert_eq!(utf16_to_utf8_offset(source, 100), source.len());
+    }
+
+    #[test]
+    fn test_configurable_block_keys() {
+        let source = "reply: `$send[hi]` code: `$get[x]` execute:`$run`";
+
+        let (ast, _) = parse(source);
+        assert_eq!(forge_kit::utils::extract_function_names(&ast), vec!["get"]);
+
+        let (ast, errors) = forge_kit::parser::Parser::new(source)
+            .with_block_keys(["reply", "code", "execute"])
+            .parse();
+        assert!(errors.is_empty());
+        assert_eq!(
+            forge_kit::utils::extract_function_names(&ast),
+            vec!["send", "get", "run"]
+        );
+
+        // Content starts right after the backtick regardless of the key length
+        // or the whitespace before the backtick.
+        if let AstNode::Program { body, .. } = ast {
+            let run = body.last().unwrap();
+            assert_eq!(&source[run.span().start..run.span().end], "$run");
+        }
+    }
+
+    #[test]
+    fn test_quoted_block_delimiters() {
+        use forge_kit::parser::Parser;
+        use forge_kit::utils::extract_function_names;
+
+        for source in ["code: \"$get[x]\"", "code: '$get[x]'"] {
+            let (ast, errors) = parse(source);
+            assert!(errors.is_empty());
+            assert!(extract_function_names(&ast).is_empty());
+            let AstNode::Program { body, .. } = &ast else {
+                panic!("expected program");
+            };
+            assert!(matches!(&body[..], [AstNode::Text { content, .. }] if content == source));
+
+            let (ast, errors) = Parser::new(source)
+                .with_block_delimiters(['`', '"', '\''])
+                .parse();
+            assert!(errors.is_empty());
+            assert_eq!(extract_function_names(&ast), vec!["get"]);
+        }
+
+        // An escaped quote is literal text and does not close the block
+        let source = r#"code: "$get[a\"b]" tail"#;
+        let (ast, errors) = Parser::new(source).with_block_delimiters(['"']).parse();
+        assert!(errors.is_empty());
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        let AstNode::FunctionCall {
+            args: Some(args), ..
+        } = &body[0]
+        else {
+            panic!("expected function call");
+        };
+        assert_eq!(args[0].as_text().as_deref(), Some("a\"b"));
+        assert!(matches!(&body[1], AstNode::Text { content, .. } if content == " tail"));
+
+        // Only the configured delimiters open blocks
+        let (ast, _) = Parser::new("code: `$ping`")
+            .with_block_delimiters(['"'])
+            .parse();
+        assert!(extract_function_names(&ast).is_empty());
+    }
+
+    #[test]
+    fn test_configurable_escape_functions() {
+        use forge_kit::parser::Parser;
+
+        let source = "code: `$raw[a;b] $get[$raw[x;y]] $c[z]`";
+
+        let (ast, _) = Parser::new(source)
+            .with_escape_functions(["c", "raw"])
+            .parse();
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        assert!(matches!(&body[0], AstNode::Escaped { content, .. } if content == "a;b"));
+        match &body[2] {
+            AstNode::FunctionCall {
+                args: Some(args), ..
+            } => {
+                assert_eq!(args.len(), 1);
+                assert!(matches!(args[0].parts[0], AstNode::Escaped { .. }));
+            }
+            other => panic!("expected $get, got {:?}", other),
+        }
+        assert!(matches!(&body[4], AstNode::Escaped { .. }));
+
+        // Without configuration `$raw` is an ordinary call
+        let (ast, _) = parse(source);
+        assert_eq!(
+            forge_kit::utils::extract_function_names(&ast),
+            vec!["raw", "get", "raw"]
+        );
+    }
+
+    #[test]
+    fn test_double_dollar_escape() {
+        use forge_kit::parser::Parser;
+        use forge_kit::utils::{extract_function_names, flatten_ast};
+
+        let parse_dd = |source: &str| Parser::new(source).with_double_dollar_escape(true).parse();
+        let text_of = |ast: &AstNode| -> String {
+            flatten_ast(ast)
+                .into_iter()
+                .filter_map(|node| match node {
+                    AstNode::Text { content, .. } => Some(content),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let (ast, errors) = parse_dd("code: `$$`");
+        assert!(errors.is_empty());
+        assert_eq!(text_of(&ast), "$");
+
+        let (ast, _) = parse_dd("code: `$$foo`");
+        assert!(extract_function_names(&ast).is_empty());
+        assert_eq!(text_of(&ast), "$foo");
+
+        // The backslash escape wins: `\\$` is a literal `$`, then `$foo` is a call
+        let (ast, _) = parse_dd("code: `\\\\$$foo`");
+        assert_eq!(extract_function_names(&ast), vec!["foo"]);
+        assert_eq!(text_of(&ast), "$");
+
+        // Inside arguments the pair is plain content and its brackets are bare
+        let (ast, _) = parse_dd("code: `$send[$$x[a]]`");
+        assert_eq!(extract_function_names(&ast), vec!["send"]);
+        assert_eq!(text_of(&ast), "$x[a]");
+
+        // Off by default
+        let (ast, _) = parse("code: `$$foo`");
+        assert_eq!(extract_function_names(&ast), vec!["foo"]);
+    }
+
+    #[test]
+    fn test_parser_builder() {
+        use forge_kit::parser::Parser;
+        use forge_kit::utils::extract_function_names;
+
+        let builder = Parser::builder()
+            .block_keys(["reply"])
+            .escape_functions(["raw"])
+            .double_dollar_escape(true);
+
+        let (ast, errors) = builder.parse("reply: `$raw[a;b] $$c $get[x]`");
+        assert!(errors.is_empty());
+        assert_eq!(extract_function_names(&ast), vec!["get"]);
+
+        // The builder is reusable and its output matches the chained setters
+        let source = "code: `$get[x]` reply: `$get[y]`";
+        let (ast, _) = builder.parse(source);
+        let (expected, _) = Parser::new(source)
+            .with_block_keys(["reply"])
+            .with_escape_functions(["raw"])
+            .with_double_dollar_escape(true)
+            .parse();
+        assert_eq!(
+            forge_kit::utils::format_ast(&ast),
+            forge_kit::utils::format_ast(&expected)
+        );
+    }
+
+    #[test]
+    fn test_max_depth() {
+        use forge_kit::parser::Parser;
+        use forge_kit::utils::extract_function_names;
+
+        let source = "code: `$f[$g[$h[x]]]`";
+        let (ast, errors) = Parser::new(source).with_max_depth(1).parse();
+        assert_eq!(extract_function_names(&ast), vec!["f", "g"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("depth"));
+        assert_eq!(&source[errors[0].span.start..errors[0].span.end], "$h[x]");
+
+        let mut events = Vec::new();
+        Parser::new(source)
+            .with_max_depth(1)
+            .parse_events(&mut |event| events.push(event));
+        assert_eq!(
+            events
+                .iter()
+                .filter(|event| matches!(event, ParseEvent::Error(_)))
+                .count(),
+            1
+        );
+
+        // `$h[x]` is the third argument level
+        let (ast, errors) = Parser::new(source).with_max_depth(2).parse();
+        assert_eq!(extract_function_names(&ast), vec!["f", "g", "h"]);
+        assert_eq!(&source[errors[0].span.start..errors[0].span.end], "x");
+
+        let (_, errors) = Parser::new(source).with_max_depth(3).parse();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_escape_text_source_span() {
+        use forge_kit::utils::flatten_ast;
+
+        let source = "code: `a\\\\b`";
+        let (ast, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let texts: Vec<AstNode> = flatten_ast(&ast)
+            .into_iter()
+            .filter(|node| matches!(node, AstNode::Text { .. }))
+            .collect();
+        let escape = texts
+            .iter()
+            .find(|node| node.is_escape_text(source))
+            .expect("escape-derived text node");
+        let AstNode::Text { content, .. } = escape else {
+            unreachable!()
+        };
+        assert_eq!(content, "\\");
+        assert_eq!(escape.source_text(source), "\\\\");
+
+        let plain: Vec<_> = texts
+            .iter()
+            .filter(|node| !node.is_escape_text(source))
+            .map(|node| node.source_text(source))
+            .collect();
+        assert_eq!(plain, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_function_full_name() {
+        let (ast, errors) = parse("code: `$get[x]`");
+        assert!(errors.is_empty());
+
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        let call = body
+            .iter()
+            .find(|node| matches!(node, AstNode::FunctionCall { .. }))
+            .expect("function call");
+        assert_eq!(call.function_full_name().as_deref(), Some("$get"));
+        assert_eq!(ast.function_full_name(), None);
+
+        let (text, _) = parse("code: `hello`");
+        let AstNode::Program { body, .. } = &text else {
+            panic!("expected program");
+        };
+        assert_eq!(body[0].function_full_name(), None);
+    }
+
+    #[test]
+    fn test_many_blocks_match_per_block_parse() {
+        use forge_kit::parser::Parser;
+        use forge_kit::utils::format_ast;
+
+        let segments = [
+            "intro text code: `$func[a;b] plain`",
+            " costs $5 \\\\$ code: `${ 'code: `' } $c[code: `x`] tail`",
+            " recode: `not a block` reply: `$ping` code: `$x`",
+            " {code: `$if[$a==b;yes;no]`",
+            " héllo, code:\n  `ünïcode \\` $get[x]`",
+        ];
+        let source: String = segments.iter().cycle().take(200).copied().collect();
+
+        let parse_stitched = |parser: &dyn Fn(&str) -> AstNode| {
+            let mut body = Vec::new();
+            let mut offset = 0;
+            for segment in segments.iter().cycle().take(200) {
+                let mut ast = parser(segment);
+                ast.offset_spans(offset);
+                if let AstNode::Program { body: nodes, .. } = ast {
+                    body.extend(nodes);
+                }
+                offset += segment.len();
+            }
+            format_ast(&AstNode::Program {
+                body,
+                span: Span::new(0, offset),
+            })
+        };
+
+        let (ast, _) = parse(&source);
+        assert_eq!(format_ast(&ast), parse_stitched(&|s| parse(s).0));
+
+        let with_keys = |s: &str| Parser::new(s).with_block_keys(["code", "reply"]).parse().0;
+        assert_eq!(format_ast(&with_keys(&source)), parse_stitched(&with_keys));
+    }
+
+    /// Rebuilds the event stream from a parsed tree so it can be compared with
+    /// the output of `parse_events`.
+    struct EventRecorder {
+        events: Vec<ParseEvent>,
+    }
+
+    impl AstVisitor for EventRecorder {
+        fn visit_text(&mut self, _content: &str, span: Span) {
+            self.events.push(ParseEvent::Text { span });
+        }
+
+        fn visit_function_call(
+            &mut self,
+            name: &str,
+            args: Option<&Vec<Argument>>,
+            _modifiers: &Modifiers,
+            span: Span,
+        ) {
+            self.events.push(ParseEvent::EnterFunction {
+                name: name.to_string(),
+                span,
+            });
+            if let Some(args) = args {
+                for arg in args {
+                    self.visit_argument(arg);
+                }
+            }
+            self.events.push(ParseEvent::ExitFunction);
+        }
+
+        fn visit_javascript(&mut self, _code: &str, span: Span) {
+            self.events.push(ParseEvent::JavaScript { span });
+        }
+
+        fn visit_escaped(&mut self, _content: &str, span: Span) {
+            self.events.push(ParseEvent::Escaped { span });
+        }
+    }
+
+    #[test]
+    fn test_parse_events_match_tree_walk() {
+        let sources = [
+            "Hello, world!",
+            "before code: `$get[$getUserVar[coins;$authorID]] text` after",
+            "code: `$!#@[2]send[a;;${ 1 + 1 };$c[raw]] \\$literal \\` $`",
+            "code: `$ban[user; 1m; []` code: `$if[$a==b;yes;no]`",
+            "code: `héllo $wörld[ünïcode] \\\\]`",
+            "code: `$unclosed[arg",
+        ];
+
+        for source in sources {
+            let (ast, _) = parse(source);
+            let mut recorder = EventRecorder { events: Vec::new() };
+            recorder.visit(&ast);
+
+            let mut events = Vec::new();
+            parse_events(source, &mut |event| events.push(event));
+
+            assert_eq!(events, recorder.events, "Event mismatch for {:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_parse_raw() {
+        let (ast, errors) = parse_raw("$get[x]");
+        assert!(errors.is_empty());
+        let AstNode::Program { body, .. } = ast else {
+            panic!("expected program");
+        };
+        assert_eq!(body.len(), 1);
+        assert!(matches!(&body[0], AstNode::FunctionCall { name, .. } if name == "get"));
+
+        // The wrapped parser sees the same input as plain text
+        let (ast, _) = parse("$get[x]");
+        let AstNode::Program { body, .. } = ast else {
+            panic!("expected program");
+        };
+        assert!(matches!(&body[0], AstNode::Text { .. }));
+    }
+
+    #[test]
+    fn test_parse_borrowed_matches_owned() {
+        use forge_kit::parser::{BorrowedAstNode, Parser, parse_borrowed};
+
+        let sources = [
+            "Hello, world!",
+            "before code: `$get[$getUserVar[coins;$authorID]] text` after",
+            "code: `$!#@[2]send[a;;${ 1 + 1 };$c[raw]] \\$literal \\` $`",
+            "code: `$ban[user; 1m; []` code: `$if[$a==b;yes;no]`",
+            "code: `héllo $wörld[ünïcode] \\\\] \\\\$x \\\\ \\q`",
+            "code: `$c $f[] $g[;] ${ unclosed`",
+            "code: `$unclosed[arg",
+        ];
+
+        for source in sources {
+            let (owned, owned_errors) = Parser::new(source).parse();
+            let (borrowed, borrowed_errors) = parse_borrowed(source);
+            assert_eq!(borrowed_errors, owned_errors);
+            assert_eq!(
+                format!("{:?}", borrowed.into_owned()),
+                format!("{:?}", owned),
+                "Tree mismatch for {:?}",
+                source
+            );
+        }
+
+        // Text is borrowed from the source, escapes included
+        let source = "code: `a \\\\$b \\`c`";
+        let (ast, _) = parse_borrowed(source);
+        let BorrowedAstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        let range = source.as_bytes().as_ptr_range();
+        for node in body {
+            let BorrowedAstNode::Text { content, .. } = node else {
+                panic!("expected text, got {:?}", node);
+            };
+            assert!(range.contains(&content.as_ptr()));
+        }
+
+        let (ast, errors) = Parser::new("code: `$a[$b[$c[x]]]`")
+            .with_max_depth(1)
+            .parse_borrowed();
+        let (owned, owned_errors) = Parser::new("code: `$a[$b[$c[x]]]`")
+            .with_max_depth(1)
+            .parse();
+        assert_eq!(errors, owned_errors);
+        assert_eq!(
+            serde_json::to_value(ast.into_owned()).unwrap(),
+            serde_json::to_value(&owned).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_identifier_must_not_start_with_digit() {
+        use forge_kit::utils::flatten_ast;
+
+        let (ast, errors) = parse("code: `$123abc $abc123 $_x[1]`");
+        assert!(errors.is_empty());
+
+        let names: Vec<_> = flatten_ast(&ast)
+            .into_iter()
+            .filter_map(|node| match node {
+                AstNode::FunctionCall { name, .. } => Some(name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["abc123", "_x"]);
+
+        let text: String = flatten_ast(&ast)
+            .into_iter()
+            .filter_map(|node| match node {
+                AstNode::Text { content, .. } => Some(content),
+                _ => None,
+            })
+            .collect();
+        assert!(text.contains("$123abc"));
+    }
+}
+
+#[cfg(feature = "validation")]
+mod validation_tests {
+    use forge_kit::metadata::{MetadataCache, MetadataManager};
+    use forge_kit::parser::{AstNode, ErrorKind, ParseEvent, Parser, Severity, ValidationConfig};
+    use forge_kit::types::{Arg, Function};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn create_mock_metadata() -> Arc<MetadataManager> {
+        let manager = MetadataManager::new();
+        let valid_func = Function {
+            name: "$validFunc".to_string(),
+            args: Some(vec![
+                Arg {
+                    name: "arg1".to_string(),
+                    required: Some(true),
+                    ..Default::default()
+                },
+                Arg {
+                    name: "arg2".to_string(),
+                    required: Some(false),
+                    ..Default::default()
+                },
+            ]),
+            brackets: Some(true),
+            ..Default::default()
+        };
+
+        let enum_func = Function {
+            name: "$enumFunc".to_string(),
+            args: Some(vec![Arg {
+                name: "option".to_string(),
+                required: Some(true),
+                arg_enum: Some(vec!["yes".to_string(), "no".to_string()]),
+                ..Default::default()
+            }]),
+            brackets: Some(true),
+            ..Default::default()
+        };
+
+        let forbidden_brackets_func = Function {
+            name: "$forbidden".to_string(),
+            brackets: None,
+            ..Default::default()
+        };
+
+        let typed_func = Function {
+            name: "$typed".to_string(),
+            args: Some(vec![Arg {
+                name: "amount".to_string(),
+                required: Some(true),
+                arg_type: serde_json::json!("Number"),
+                ..Default::default()
+            }]),
+            brackets: Some(true),
+            ..Default::default()
+        };
+
+        let unwrap_func = Function {
+            name: "$unwrapped".to_string(),
+            unwrap: true,
+            ..Default::default()
+        };
+
+        let cache = MetadataCache::new(
+            vec![
+                valid_func,
+                enum_func,
+                forbidden_brackets_func,
+                typed_func,
+                unwrap_func,
+            ],
+            HashMap::new(),
+            vec![],
+        );
+        manager.import_cache(cache).unwrap();
+        Arc::new(manager)
+    }
+
+    #[test]
+    fn test_unclosed_bracket() {
+        let (_ast, errors) =
+            Parser::with_config("code: `$get[unclosed`", ValidationConfig::syntax_only()).parse();
+        assert!(!errors.is_empty());
+        assert!(errors.iter().all(|e| e.kind == ErrorKind::UnclosedBracket));
+
+        let (_ast, errors) =
+            Parser::with_config("code: `$c[unclosed`", ValidationConfig::syntax_only()).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::UnclosedBracket);
+    }
+
+    #[test]
+    fn test_unclosed_js() {
+        let (_ast, errors) =
+            Parser::with_config("code: `${ unclosed`", ValidationConfig::syntax_only()).parse();
+        assert!(!errors.is_empty());
+        assert!(
+            errors
+                .iter()
+                .all(|e| e.kind == ErrorKind::UnclosedJavaScript)
+        );
+    }
+
+    #[test]
+    fn test_unclosed_block() {
+        let (ast, errors) =
+            Parser::with_config("code: `$foo", ValidationConfig::syntax_only()).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unclosed code block");
+        if let AstNode::Program { body, .. } = ast
+            && let AstNode::Text { content, .. } = &body[0]
+        {
+            assert_eq!(content, "code: `$foo");
+        }
+    }
+
+    /// A \` inside a code block should NOT trigger an "Unclosed code block" error
+    /// because the backtick is escaped.
+    #[test]
+    fn test_escaped_backtick_does_not_close_block() {
+        // code: `hello\`still_inside` — the \` is escaped, block closes at the final `.
+        let (_, errors) = Parser::with_config(
+            "code: `hello\\`still_inside`",
+            ValidationConfig::syntax_only(),
+        )
+        .parse();
+        assert!(
+            errors.is_empty(),
+            "Escaped backtick must not close the code block: {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_validation_argument_count() {
+        let metadata = create_mock_metadata();
+        let config = ValidationConfig {
+            validate_arguments: true,
+            validate_functions: true,
+            ..Default::default()
+        };
+        let (_ast, errors) =
+            Parser::with_validation("code: `$validFunc[]`", config.clone(), metadata.clone())
+                .parse();
+        assert_eq!(errors[0].kind, ErrorKind::ArgumentCount);
+    }
+
+    #[test]
+    fn test_validation_enum_values() {
+        let metadata = create_mock_metadata();
+        let config = ValidationConfig {
+            validate_enums: true,
+            validate_functions: true,
+            ..Default::default()
+        };
+        let (_ast, errors) =
+            Parser::with_validation("code: `$enumFunc[maybe]`", config.clone(), metadata.clone())
+                .parse();
+        assert_eq!(errors[0].kind, ErrorKind::EnumValue);
+        assert!(errors[0].message.contains("expected one of"));
+
+        // Near misses get a suggestion instead of the full list
+        let case_sensitive = ValidationConfig {
+            enum_case_sensitive: true,
+            ..config.clone()
+        };
+        for (value, suggestion) in [("yess", "'yes'"), ("NO", "'no'")] {
+            let source = format!("code: `$enumFunc[{}]`", value);
+            let (_, errors) =
+                Parser::with_validation(&source, case_sensitive.clone(), metadata.clone()).parse();
+            assert_eq!(errors[0].kind, ErrorKind::EnumValue);
+            assert_eq!(
+                errors[0].message,
+                format!(
+                    "Invalid value '{}' for option; did you mean {}?",
+                    value, suggestion
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_validation_enum_case() {
+        let metadata = create_mock_metadata();
+        let config = ValidationConfig {
+            validate_enums: true,
+            ..Default::default()
+        };
+
+        let (_, errors) =
+            Parser::with_validation("code: `$enumFunc[yes]`", config.clone(), metadata.clone())
+                .parse();
+        assert!(errors.is_empty());
+
+        // Case-only mismatches are accepted with a hint at the canonical casing
+        let (_, errors) =
+            Parser::with_validation("code: `$enumFunc[YES]`", config.clone(), metadata.clone())
+                .parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::EnumValue);
+        assert_eq!(errors[0].severity, Severity::Warning);
+        assert_eq!(
+            errors[0].message,
+            "Value 'YES' for option should be written 'yes'"
+        );
+
+        let config = ValidationConfig {
+            enum_case_sensitive: true,
+            ..config
+        };
+        let (_, errors) =
+            Parser::with_validation("code: `$enumFunc[YES]`", config, metadata).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_validation_brackets() {
+        let metadata = create_mock_metadata();
+        let config = ValidationConfig {
+            validate_brackets: true,
+            validate_functions: true,
+            ..Default::default()
+        };
+        let (_ast, errors) =
+            Parser::with_validation("code: `$validFunc`", config.clone(), metadata.clone()).parse();
+        assert_eq!(errors[0].kind, ErrorKind::BracketUsage);
+    }
+
+    #[test]
+    fn test_resolved_extension() {
+        let metadata = create_mock_metadata();
+        metadata.update_function(Function {
+            name: "$dbGet".to_string(),
+            extension: Some("forge.db".to_string()),
+            brackets: Some(false),
+            ..Default::default()
+        });
+        let config = ValidationConfig {
+            validate_functions: true,
+            ..Default::default()
+        };
+
+        let (ast, _) =
+            Parser::with_validation("code: `$dbGet $validFunc[a] $nope`", config, metadata).parse();
+        let AstNode::Program { body, .. } = ast else {
+            panic!("expected program");
+        };
+        let calls: Vec<(bool, Option<String>)> = body
+            .into_iter()
+            .filter_map(|node| match node {
+                AstNode::FunctionCall {
+                    resolved,
+                    resolved_extension,
+                    ..
+                } => Some((resolved, resolved_extension)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (true, Some("forge.db".to_string())),
+                (true, None),
+                (false, None),
+            ]
+        );
+
+        // Without metadata nothing is resolved
+        let (ast, _) = forge_kit::parser::parse("code: `$dbGet`");
+        let AstNode::Program { body, .. } = ast else {
+            panic!("expected program");
+        };
+        assert!(matches!(
+            &body[0],
+            AstNode::FunctionCall {
+                resolved: false,
+                resolved_extension: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_bare_escape_functions() {
+        let source = "code: `$c note $get[$c] $escape`";
+
+        // Brackets are required by default
+        let (_, errors) = Parser::with_config(source, ValidationConfig::syntax_only()).parse();
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().all(|e| e.kind == ErrorKind::BracketUsage));
+
+        let (ast, errors) = Parser::with_config(source, ValidationConfig::syntax_only())
+            .with_bare_escape_functions(["c"])
+            .parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "$escape requires brackets");
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        assert!(matches!(&body[0], AstNode::Text { content, .. } if content == "$c"));
+
+        // Bracketed calls are still escaped
+        let (ast, errors) = Parser::builder()
+            .validation(ValidationConfig::syntax_only())
+            .bare_escape_functions(["c"])
+            .parse("code: `$c[a;b]`");
+        assert!(errors.is_empty());
+        let AstNode::Program { body, .. } = &ast else {
+            panic!("expected program");
+        };
+        assert!(matches!(&body[0], AstNode::Escaped { content, .. } if content == "a;b"));
+    }
+
+    #[test]
+    fn test_max_errors_stops_early() {
+        let metadata = create_mock_metadata();
+        let source = "code: `$a[] $b[] $g[]` text code: `$d[] $e[$f[]]`";
+        let config = ValidationConfig {
+            validate_functions: true,
+            ..Default::default()
+        };
+
+        let (_, errors) = Parser::with_validation(source, config.clone(), metadata.clone()).parse();
+        assert_eq!(errors.len(), 6);
+
+        let config = ValidationConfig {
+            max_errors: Some(1),
+            ..config
+        };
+        let (ast, errors) =
+            Parser::with_validation(source, config.clone(), metadata.clone()).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unknown function: $a");
+        let AstNode::Program { body, span } = &ast else {
+            panic!("expected program");
+        };
+        assert!(span.end < source.len());
+        assert!(body.iter().all(|node| node.span().end <= span.end));
+
+        // The budget is shared across blocks and nested arguments
+        let config = ValidationConfig {
+            max_errors: Some(5),
+            ..config
+        };
+        let (_, errors) = Parser::with_validation(source, config, metadata).parse();
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors[4].message, "Unknown function: $f");
+    }
+
+    #[test]
+    fn test_max_errors_zero_reports_first_error() {
+        let config = ValidationConfig {
+            max_errors: Some(0),
+            ..ValidationConfig::syntax_only()
+        };
+        let (_, errors) = Parser::with_config("code: `$get[x`", config).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::UnclosedBracket);
+    }
+
+    #[test]
+    fn test_max_errors_borrowed_and_events() {
+        let source = "code: `$get[x` text code: `$set[y` code: `$has[z`";
+        let config = ValidationConfig {
+            max_errors: Some(2),
+            ..ValidationConfig::syntax_only()
+        };
+
+        let (owned, errors) = Parser::with_config(source, config.clone()).parse();
+        assert_eq!(errors.len(), 2);
+
+        let (borrowed, borrowed_errors) =
+            Parser::with_config(source, config.clone()).parse_borrowed();
+        assert_eq!(
+            serde_json::to_value(borrowed.into_owned()).unwrap(),
+            serde_json::to_value(&owned).unwrap()
+        );
+        assert_eq!(borrowed_errors, errors);
+
+        let mut event_errors = Vec::new();
+        Parser::with_config(source, config).parse_events(&mut |event| {
+            if let ParseEvent::Error(error) = event {
+                event_errors.push(error);
+            }
+        });
+        assert_eq!(event_errors, errors);
+    }
+
+    #[test]
+    fn test_validation_unknown_function() {
+        let metadata = create_mock_metadata();
+        let config = ValidationConfig {
+            validate_functions: true,
+            ..Default::default()
+        };
+        let (_ast, errors) =
+            Parser::with_validation("code: `$unknown[]`", config.clone(), metadata.clone()).parse();
+        assert_eq!(errors[0].kind, ErrorKind::UnknownFunction);
+    }
+
+    #[test]
+    fn test_group_errors_by_call() {
+        use forge_kit::utils::group_errors_by_call;
+
+        let metadata = create_mock_metadata();
+        let source = "code: `$validFunc[a;b;c] $validFunc[$enumFunc[maybe]]` tail code: `oops";
+        let (ast, errors) =
+            Parser::with_validation(source, ValidationConfig::strict(), metadata).parse();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+
+        let groups = group_errors_by_call(&ast, &errors);
+        let summary: Vec<(Option<&str>, Vec<ErrorKind>)> = groups
+            .iter()
+            .map(|(call, errors)| {
+                (
+                    call.map(|span| &source[span.start..span.end]),
+                    errors.iter().map(|e| e.kind).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("$validFunc[a;b;c]"), vec![ErrorKind::ArgumentCount]),
+                (Some("$enumFunc[maybe]"), vec![ErrorKind::EnumValue]),
+                (None, vec![ErrorKind::Syntax]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validation_across_sibling_blocks() {
+        let metadata = create_mock_metadata();
+        let config = ValidationConfig {
+            validate_functions: true,
+            ..Default::default()
+        };
+        let source = "a: { code: `$validFunc[x] ${ \"code: `$fake\" }` },\n\
+                      b: { code: `$missing[]` }";
+        let (_ast, errors) = Parser::with_validation(source, config, metadata).parse();
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].kind, ErrorKind::UnknownFunction);
+        assert_eq!(
+            &source[errors[0].span.start..errors[0].span.end],
+            "$missing"
+        );
+    }
+
+    #[test]
+    fn test_validation_duplicate_modifiers() {
+        let config = ValidationConfig {
+            validate_modifiers: true,
+            ..Default::default()
+        };
+
+        let source = "code: `$!!foo`";
+        let (ast, errors) = Parser::with_config(source, config.clone()).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::DuplicateModifier);
+        assert!(errors[0].is_warning());
+        assert_eq!(errors[0].span.start, source.find("!!").unwrap() + 1);
+        if let AstNode::Program { body, .. } = ast
+            && let AstNode::FunctionCall { modifiers, .. } = &body[0]
+        {
+            assert!(modifiers.silent);
+        }
+
+        let source = "code: `$#@[1]@[2]foo`";
+        let (ast, errors) = Parser::with_config(source, config).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::DuplicateModifier);
+        assert_eq!(&source[errors[0].span.start..errors[0].span.end], "@[2]");
+        if let AstNode::Program { body, .. } = ast
+            && let AstNode::FunctionCall {
+                name, modifiers, ..
+            } = &body[0]
+        {
+            assert_eq!(name, "foo");
+            assert!(modifiers.negated);
+            assert_eq!(modifiers.count.as_deref(), Some("1"));
+        }
+
+        let (_ast, errors) =
+            Parser::with_config("code: `$!!foo`", ValidationConfig::syntax_only()).parse();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_validation_count_modifier() {
+        let config = ValidationConfig {
+            validate_modifiers: true,
+            ..Default::default()
+        };
+
+        for source in ["code: `$@[abc]foo`", "code: `$@[0]foo`"] {
+            let (_, errors) = Parser::with_config(source, config.clone()).parse();
+            assert_eq!(errors.len(), 1, "{:?}", source);
+            assert_eq!(errors[0].kind, ErrorKind::Syntax);
+            assert!(!errors[0].is_warning());
+            assert!(source[errors[0].span.start..errors[0].span.end].starts_with("@["));
+        }
+
+        for source in [
+            "code: `$@[ 100 ]foo`",
+            "code: `$@[$get[n]]foo`",
+            "code: `$@[${ 2 * 3 }]foo`",
+        ] {
+            let (_, errors) = Parser::with_config(source, config.clone()).parse();
+            assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        }
+
+        let (_, errors) =
+            Parser::with_config("code: `$@[abc]foo`", ValidationConfig::syntax_only()).parse();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_validation_required_after_optional() {
+        let manager = MetadataManager::new();
+        let anomalous = Function {
+            name: "$anomalous".to_string(),
+            args: Some(vec![
+                Arg {
+                    name: "first".to_string(),
+                    required: Some(false),
+                    ..Default::default()
+                },
+                Arg {
+                    name: "second".to_string(),
+                    required: Some(true),
+                    ..Default::default()
+                },
+            ]),
+            brackets: Some(true),
+            ..Default::default()
+        };
+        manager
+            .import_cache(MetadataCache::new(vec![anomalous], HashMap::new(), vec![]))
+            .unwrap();
+        let metadata = Arc::new(manager);
+        let config = ValidationConfig {
+            validate_arguments: true,
+            ..Default::default()
+        };
+
+        let (_ast, errors) =
+            Parser::with_validation("code: `$anomalous[x]`", config.clone(), metadata.clone())
+                .parse();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(errors[0].kind, ErrorKind::ArgumentOrder);
+        assert!(errors[0].is_warning());
+        assert_eq!(errors[1].kind, ErrorKind::ArgumentCount);
+        assert!(errors[1].message.contains("missing `second` (position 2)"));
+
+        let (_ast, errors) =
+            Parser::with_validation("code: `$anomalous[;y]`", config, metadata).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::ArgumentOrder);
+    }
+
+    #[test]
+    fn test_validation_out_of_range_pointer() {
+        let manager = MetadataManager::new();
+        let pointing = Function {
+            name: "$pointing".to_string(),
+            args: Some(vec![
+                Arg {
+                    name: "target".to_string(),
+                    ..Default::default()
+                },
+                Arg {
+                    name: "valid".to_string(),
+                    pointer: Some(0),
+                    ..Default::default()
+                },
+                Arg {
+                    name: "dangling".to_string(),
+                    pointer: Some(5),
+                    pointer_property: Some("id".to_string()),
+                    ..Default::default()
+                },
+            ]),
+            brackets: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(pointing.args.as_ref().unwrap()[1].pointer_index(), Some(0));
+        manager
+            .import_cache(MetadataCache::new(vec![pointing], HashMap::new(), vec![]))
+            .unwrap();
+        let config = ValidationConfig {
+            validate_arguments: true,
+            ..Default::default()
+        };
+
+        let (_ast, errors) =
+            Parser::with_validation("code: `$pointing[a;b;c]`", config, Arc::new(manager)).parse();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].kind, ErrorKind::ArgumentPointer);
+        assert!(errors[0].is_warning());
+        assert!(errors[0].message.contains("`dangling`"));
+    }
+
+    #[test]
+    fn test_validation_unwrap_usage() {
+        let metadata = create_mock_metadata();
+        let config = ValidationConfig {
+            validate_unwrap_usage: true,
+            ..Default::default()
+        };
+        let (_ast, errors) = Parser::with_validation(
+            "code: `$typed[$unwrapped]`",
+            config.clone(),
+            metadata.clone(),
+        )
+        .parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::UnwrapUsage);
+        assert!(errors[0].is_warning());
+        assert_eq!(errors[0].span.start, 14);
+
+        // The lint is opt-in: strict validation does not report it.
+        let (_ast, errors) = Parser::with_validation(
+            "code: `$typed[$unwrapped]`",
+            ValidationConfig::strict(),
+            metadata,
+        )
+        .parse();
+        assert!(errors.iter().all(|e| e.kind != ErrorKind::UnwrapUsage));
+    }
+
+    #[test]
+    fn test_validate_directory() {
+        use forge_kit::parser::validate_directory;
+
+        let dir = std::env::temp_dir().join(format!("forge-kit-validate-{}", std::process::id()));
+        let nested = dir.join("commands");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            dir.join("valid.js"),
+            "module.exports = { code: `$validFunc[x]` }",
+        )
+        .unwrap();
+        std::fs::write(
+            nested.join("invalid.ts"),
+            "export default {\n  code: `$unknown[]`\n}",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "code: `$unknown[]`").unwrap();
+
+        let config = ValidationConfig {
+            validate_functions: true,
+            ..Default::default()
+        };
+        let results = validate_directory(&dir, config, create_mock_metadata()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results.len(), 2);
+        let invalid = &results[0];
+        assert!(invalid.path.ends_with("commands/invalid.ts"));
+        assert_eq!(invalid.diagnostics.len(), 1);
+        assert_eq!(
+            invalid.diagnostics[0].error.kind,
+            ErrorKind::UnknownFunction
+        );
+        assert_eq!(invalid.diagnostics[0].line, 1);
+        assert_eq!(invalid.diagnostics[0].column, 9);
+
+        assert!(results[1].path.ends_with("valid.js"));
+        assert!(results[1].is_valid());
+    }
+
+    /// Bare brackets in args must not cause false positives in syntax-only mode.
+    #[test]
+    fn test_bare_brackets_no_syntax_error() {
+        let (_, errors) =
+            Parser::with_config("code: `$ban[user; 1m; []`", ValidationConfig::syntax_only())
+                .parse();
+        assert!(
+            errors.is_empty(),
+            "Bare `[` in args should not produce a syntax error: {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_missing_function_name() {
+        for (source, offset) in [("code: `$[x]`", 7), ("code: `price: $`", 14)] {
+            let (_, errors) = Parser::with_config(source, ValidationConfig::syntax_only()).parse();
+            assert_eq!(errors.len(), 1, "{:?}: {:?}", source, errors);
+            assert_eq!(errors[0].kind, ErrorKind::Syntax);
+            assert!(errors[0].message.contains("expected function name"));
+            assert_eq!(errors[0].span.start, offset);
+
+            let (_, errors) = Parser::new(source).parse();
+            assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        }
+
+        // Escaped dollars and JavaScript expressions are not missing a name
+        let (_, errors) =
+            Parser::with_config("code: `\\\\$ ${ 1 }`", ValidationConfig::syntax_only()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // Neither is a `$` before a digit, which stays plain text
+        let (ast, errors) =
+            Parser::with_config("code: `costs $5`", ValidationConfig::syntax_only()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(
+            forge_kit::utils::flatten_ast(&ast)
+                .iter()
+                .all(|node| matches!(node, AstNode::Program { .. } | AstNode::Text { .. }))
+        );
+    }
+
+    #[test]
+    fn test_parser_builder_with_validation() {
+        let builder = Parser::builder()
+            .validation(ValidationConfig {
+                validate_functions: true,
+                ..Default::default()
+            })
+            .metadata(create_mock_metadata());
+
+        let (_, errors) = builder.parse("code: `$unknownFunc`");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::UnknownFunction);
+    }
+
+    #[test]
+    fn test_strict_text() {
+        let source = "a ; b code: `a ; b $get[x;y] \\\\; [c]`";
+        let config = ValidationConfig {
+            strict_text: true,
+            ..Default::default()
+        };
+        let (_, errors) = Parser::with_config(source, config).parse();
+
+        let flagged: Vec<_> = errors
+            .iter()
+            .map(|e| {
+                assert!(e.is_warning());
+                assert_eq!(e.kind, ErrorKind::UnescapedText);
+                &source[e.span.start..e.span.end]
+            })
+            .collect();
+        assert_eq!(flagged, vec![";", "[", "]"]);
+        assert_eq!(errors[0].span.start, 15);
+
+        let (_, errors) = Parser::with_config(source, ValidationConfig::syntax_only()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_validate_layered() {
+        use forge_kit::parser::validate_layered;
+
+        let metadata = create_mock_metadata();
+        let layers = [ValidationConfig::syntax_only(), ValidationConfig::strict()];
+
+        // The unclosed block stops the run before `$unknown` is looked up
+        let errors = validate_layered("code: `$unknown[] ${", &layers, Some(&metadata));
+        assert!(!errors.is_empty());
+        assert!(errors.iter().all(|e| e.kind == ErrorKind::Syntax));
+
+        // Valid syntax falls through to semantic validation
+        let errors = validate_layered("code: `$unknown[]`", &layers, Some(&metadata));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::UnknownFunction);
+
+        // Warnings do not stop the run and repeated diagnostics are merged
+        let strict_text = ValidationConfig {
+            strict_text: true,
+            ..Default::default()
+        };
+        let errors = validate_layered("code: `a;b`", &[strict_text.clone(), strict_text], None);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is_warning());
+    }
+
+    #[test]
+    fn test_validation_block_balance() {
+        let config = ValidationConfig {
+            validate_blocks: true,
+            ..Default::default()
+        };
+
+        let source = "code: `$if[$a==b]$get[x]`";
+        let (_, errors) = Parser::with_config(source, config.clone()).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::BlockBalance);
+        assert_eq!(
+            &source[errors[0].span.start..errors[0].span.end],
+            "$if[$a==b]"
+        );
+
+        let balanced = "code: `$if[a]$try $get[x] $catch $endtry$endif $if[b]$endIf`";
+        let (_, errors) = Parser::with_config(balanced, config.clone()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // A stray closer, and an opener left open by its enclosing block's closer
+        let source = "code: `$endtry $if[a]$try$endif`";
+        let (_, errors) = Parser::with_config(source, config.clone()).parse();
+        let flagged: Vec<_> = errors
+            .iter()
+            .map(|e| &source[e.span.start..e.span.end])
+            .collect();
+        assert_eq!(flagged, vec!["$endtry", "$try"]);
+
+        // Each code block is balanced on its own
+        let source = "code: `$if[a]` text code: `$endif`";
+        let (_, errors) = Parser::with_config(source, config.clone()).parse();
+        let flagged: Vec<_> = errors
+            .iter()
+            .map(|e| &source[e.span.start..e.span.end])
+            .collect();
+        assert_eq!(flagged, vec!["$if[a]", "$endif"]);
+
+        // Calls nested in arguments are not block statements
+        let (_, errors) = Parser::with_config("code: `$let[x;$if[a]]`", config.clone()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // Custom pairs replace the defaults
+        let (_, errors) = Parser::with_config("code: `$loop $if[a]`", config.clone())
+            .with_block_pairs([("loop", "endloop")])
+            .parse();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("$endloop"));
+
+        let (_, errors) =
+            Parser::with_config("code: `$if[$a==b]$get[x]`", ValidationConfig::syntax_only())
+                .parse();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_forbid_javascript() {
+        let source = "code: `$send[${1+1}] done`";
+        let config = ValidationConfig {
+            forbid_javascript: true,
+            ..Default::default()
+        };
+        let (_, errors) = Parser::with_config(source, config).parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::ForbiddenJavaScript);
+        assert_eq!(&source[errors[0].span.start..errors[0].span.end], "${1+1}");
+
+        let (_, errors) = Parser::with_config(source, ValidationConfig::syntax_only()).parse();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_deprecated_and_experimental() {
+        let manager = MetadataManager::new();
+        let cache = MetadataCache::new(
+            vec![
+                Function {
+                    name: "$oldFunc".to_string(),
+                    deprecated: Some(true),
+                    version: Some(serde_json::json!("1.2.0")),
+                    ..Default::default()
+                },
+                Function {
+                    name: "$betaFunc".to_string(),
+                    experimental: Some(true),
+                    ..Default::default()
+                },
+            ],
+            HashMap::new(),
+            vec![],
+        );
+        manager.import_cache(cache).unwrap();
+        let metadata = Arc::new(manager);
+
+        let config = ValidationConfig {
+            deny_deprecated: true,
+            warn_experimental: true,
+            ..Default::default()
+        };
+        let (_, errors) =
+            Parser::with_validation("code: `$oldFunc $betaFunc`", config, metadata.clone()).parse();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].kind, ErrorKind::Deprecated);
+        assert_eq!(errors[0].severity, Severity::Error);
+        assert!(errors[0].message.contains("$oldFunc"));
+        assert!(errors[0].message.contains("1.2.0"));
+        assert_eq!(errors[1].kind, ErrorKind::Experimental);
+        assert_eq!(errors[1].severity, Severity::Warning);
+        assert!(errors[1].message.contains("$betaFunc"));
+
+        let (_, errors) = Parser::with_validation(
+            "code: `$oldFunc $betaFunc`",
+            ValidationConfig::default(),
+            metadata,
+        )
+        .parse();
+        assert!(errors.is_empty());
     }
 }
@@ -1,5 +1,10 @@
 use forge_kit::parser::{AstNode, parse};
-use forge_kit::visitor::{AstVisitor, AstVisitorMut, FunctionCollector, NodeCounter};
+use forge_kit::utils::count_nodes;
+use forge_kit::visitor::{
+    AstVisitor, AstVisitorMut, ContextVisitor, FunctionCollector, FunctionDepthCollector,
+    LSP_TOKEN_TYPES, NodeCounter, SemanticTokenKind, VisitContext, find_first, fold,
+    lsp_semantic_tokens, semantic_tokens,
+};
 
 #[test]
 fn test_function_collector_basic() {
@@ -102,6 +107,45 @@ impl AstVisitor for OrderVisitor {
     }
 }
 
+#[test]
+fn test_visit_argument_indexed() {
+    #[derive(Default)]
+    struct ArgumentRecorder {
+        seen: Vec<(String, usize)>,
+        texts: usize,
+    }
+
+    impl AstVisitor for ArgumentRecorder {
+        fn visit_text(&mut self, _content: &str, _span: forge_kit::parser::Span) {
+            self.texts += 1;
+        }
+
+        fn visit_argument_indexed(
+            &mut self,
+            function_name: &str,
+            index: usize,
+            arg: &forge_kit::parser::Argument,
+        ) {
+            self.seen.push((function_name.to_string(), index));
+            self.visit_argument(arg);
+        }
+    }
+
+    let (ast, _) = parse("code: `$a[x;y] $b[z]`");
+    let mut recorder = ArgumentRecorder::default();
+    recorder.visit(&ast);
+    assert_eq!(
+        recorder.seen,
+        vec![
+            ("a".to_string(), 0),
+            ("a".to_string(), 1),
+            ("b".to_string(), 0)
+        ]
+    );
+    // Argument contents are still visited
+    assert_eq!(recorder.texts, 4);
+}
+
 #[test]
 fn test_traversal_order() {
     let (ast, _) = parse("code: `$a[$b[]]`");
@@ -111,3 +155,239 @@ fn test_traversal_order() {
     // Expected: enter:a -> enter:b -> exit:b -> exit:a
     assert_eq!(visitor.log, vec!["enter:a", "enter:b", "exit:b", "exit:a"]);
 }
+
+#[test]
+fn test_find_first_stops_early() {
+    let source = format!("code: `$a $ban[user] {}`", "$b[$c[x]] ".repeat(1000));
+    let (ast, _) = parse(&source);
+
+    let mut visited = 0;
+    let found = find_first(&ast, |node| {
+        visited += 1;
+        matches!(node, AstNode::FunctionCall { name, .. } if name == "ban")
+    });
+
+    match found {
+        Some(AstNode::FunctionCall { name, span, .. }) => {
+            assert_eq!(name, "ban");
+            assert_eq!(&source[span.start..span.end], "$ban[user]");
+        }
+        other => panic!("Expected $ban call, got {:?}", other),
+    }
+
+    // Program, $a, " ", $ban — nothing after the match is visited
+    assert_eq!(visited, 4);
+    assert!(count_nodes(&ast) > 1000);
+
+    assert!(find_first(&ast, |node| matches!(node, AstNode::JavaScript { .. })).is_none());
+}
+
+#[test]
+fn test_context_visitor_depths() {
+    // `$c` is the escape function, so use `$d` for the innermost call
+    let (ast, _) = parse("code: `$a[$b[$d[]]]`");
+    let mut collector = FunctionDepthCollector::default();
+    collector.visit_ctx(&ast, &VisitContext::default());
+
+    assert_eq!(
+        collector.functions,
+        vec![
+            ("a".to_string(), 0),
+            ("b".to_string(), 1),
+            ("d".to_string(), 2)
+        ]
+    );
+}
+
+// Visitor recording the parent function and argument index of text nodes
+struct ParentRecorder {
+    log: Vec<(String, Option<String>, Option<usize>)>,
+}
+
+impl ContextVisitor for ParentRecorder {
+    fn visit_text_ctx(
+        &mut self,
+        content: &str,
+        _span: forge_kit::parser::Span,
+        ctx: &VisitContext<'_>,
+    ) {
+        self.log.push((
+            content.to_string(),
+            ctx.parent_function.map(str::to_string),
+            ctx.arg_index,
+        ));
+    }
+}
+
+#[test]
+fn test_context_visitor_parent_and_index() {
+    let (ast, _) = parse("code: `top $set[key;$get[val]]`");
+    let mut recorder = ParentRecorder { log: Vec::new() };
+    recorder.visit_ctx(&ast, &VisitContext::default());
+
+    assert_eq!(
+        recorder.log,
+        vec![
+            ("top ".to_string(), None, None),
+            ("key".to_string(), Some("set".to_string()), Some(0)),
+            ("val".to_string(), Some("get".to_string()), Some(0)),
+        ]
+    );
+}
+
+// Visitor logging every node it reaches, for comparison with `fold`
+struct SpanLogger {
+    log: Vec<forge_kit::parser::Span>,
+}
+
+impl AstVisitor for SpanLogger {
+    fn visit_program(&mut self, body: &[AstNode], span: forge_kit::parser::Span) {
+        self.log.push(span);
+        for node in body {
+            self.visit(node);
+        }
+    }
+
+    fn visit_text(&mut self, _content: &str, span: forge_kit::parser::Span) {
+        self.log.push(span);
+    }
+
+    fn visit_function_call(
+        &mut self,
+        _name: &str,
+        args: Option<&Vec<forge_kit::parser::Argument>>,
+        _modifiers: &forge_kit::parser::Modifiers,
+        span: forge_kit::parser::Span,
+    ) {
+        self.log.push(span);
+        if let Some(args) = args {
+            for arg in args {
+                self.visit_argument(arg);
+            }
+        }
+    }
+
+    fn visit_javascript(&mut self, _code: &str, span: forge_kit::parser::Span) {
+        self.log.push(span);
+    }
+
+    fn visit_escaped(&mut self, _content: &str, span: forge_kit::parser::Span) {
+        self.log.push(span);
+    }
+}
+
+#[test]
+fn test_fold_matches_visitor_order() {
+    let (ast, _) = parse("code: `a $x[$y[1;${ 2 }];$c[z]] b $w`");
+
+    let mut logger = SpanLogger { log: Vec::new() };
+    logger.visit(&ast);
+
+    let spans = fold(&ast, Vec::new(), |mut spans, node| {
+        spans.push(node.span());
+        spans
+    });
+    assert_eq!(spans, logger.log);
+    assert_eq!(count_nodes(&ast), logger.log.len());
+}
+
+#[test]
+fn test_semantic_tokens_function_call() {
+    let source = "code: `$get[a;b]`";
+    let (ast, _) = parse(source);
+
+    let tokens: Vec<(&str, SemanticTokenKind)> = semantic_tokens(&ast)
+        .iter()
+        .map(|t| (&source[t.span.start..t.span.end], t.kind))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            ("$get", SemanticTokenKind::FunctionName),
+            ("[", SemanticTokenKind::Bracket),
+            ("a", SemanticTokenKind::Text),
+            (";", SemanticTokenKind::ArgumentSeparator),
+            ("b", SemanticTokenKind::Text),
+            ("]", SemanticTokenKind::Bracket),
+        ]
+    );
+}
+
+#[test]
+fn test_semantic_tokens_ordered_and_disjoint() {
+    let source = "code: `hi $!#@[2]send[$c[x];;${ 1 }] done`";
+    let (ast, _) = parse(source);
+    let tokens = semantic_tokens(&ast);
+
+    for pair in tokens.windows(2) {
+        assert!(pair[0].span.end <= pair[1].span.start, "{:?}", pair);
+    }
+    let modifiers: Vec<&str> = tokens
+        .iter()
+        .filter(|t| t.kind == SemanticTokenKind::Modifier)
+        .map(|t| &source[t.span.start..t.span.end])
+        .collect();
+    assert_eq!(modifiers, vec!["!", "#", "@[2]"]);
+    assert!(
+        tokens
+            .iter()
+            .any(|t| t.kind == SemanticTokenKind::EscapedContent)
+    );
+    assert!(
+        tokens
+            .iter()
+            .any(|t| t.kind == SemanticTokenKind::JavaScript)
+    );
+    assert_eq!(
+        tokens
+            .iter()
+            .filter(|t| t.kind == SemanticTokenKind::ArgumentSeparator)
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn test_lsp_semantic_tokens_encoding() {
+    let source = "code: `$get[x]`";
+    let (ast, _) = parse(source);
+    let data = lsp_semantic_tokens(&ast, source);
+
+    // Decode (line, start, length, type) from the delta-encoded stream
+    let mut decoded = Vec::new();
+    let (mut line, mut col) = (0, 0);
+    for chunk in data.chunks(5) {
+        if chunk[0] > 0 {
+            col = 0;
+        }
+        line += chunk[0];
+        col += chunk[1];
+        decoded.push((line, col, chunk[2], LSP_TOKEN_TYPES[chunk[3] as usize]));
+        assert_eq!(chunk[4], 0);
+    }
+
+    // The `x` argument is plain text and left unhighlighted
+    assert_eq!(
+        decoded,
+        vec![
+            (0, 7, 4, "function"),
+            (0, 11, 1, "operator"),
+            (0, 13, 1, "operator"),
+        ]
+    );
+}
+
+#[test]
+fn test_lsp_semantic_tokens_multiline_and_utf16() {
+    let source = "code: `é $get[a]\n${ 1 +\n 2 }`";
+    let (ast, _) = parse(source);
+    let data = lsp_semantic_tokens(&ast, source);
+    let rows: Vec<&[u32]> = data.chunks(5).collect();
+
+    // `é` is one UTF-16 unit, so `$get` starts at column 9
+    assert_eq!(rows[0], &[0, 9, 4, 0, 0]);
+    // The JavaScript block is split into one token per line
+    assert_eq!(rows[3], &[1, 0, 6, 3, 0]);
+    assert_eq!(rows[4], &[1, 0, 4, 3, 0]);
+    assert_eq!(rows.len(), 5);
+}
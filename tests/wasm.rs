@@ -0,0 +1,23 @@
+#![cfg(feature = "wasm")]
+
+use forge_kit::wasm::MetadataManagerWrapper;
+
+#[test]
+fn test_add_custom_functions() {
+    let wrapper = MetadataManagerWrapper::new();
+
+    let json = r#"[
+        { "name": "myFunc", "aliases": ["myAlias"] },
+        { "name": "$other" }
+    ]"#;
+    assert_eq!(wrapper.add_custom_functions(json).unwrap(), 2);
+
+    for name in ["$myFunc", "$myAlias", "$other"] {
+        let func = wrapper
+            .get_function_exact(name)
+            .unwrap_or_else(|| panic!("{} not registered", name));
+        let func: serde_json::Value = serde_json::from_str(&func).unwrap();
+        assert_eq!(func["origin"], "Custom", "{}", name);
+    }
+    assert!(wrapper.get_function_exact("$unknown").is_none());
+}